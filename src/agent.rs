@@ -0,0 +1,355 @@
+use crate::key_cache::{vault_id, KeyCache};
+use crate::rutil::peer_cred;
+use crate::rutil::safe_string::SafeString;
+use crate::rutil::safe_vec::SafeVec;
+use std::collections::HashMap;
+use std::io::{
+    BufRead, BufReader, Error as IoError, ErrorKind as IoErrorKind, Result as IoResult, Write,
+};
+use std::ops::Deref;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use std::{ptr, sync::atomic};
+
+/// The environment variable through which clients find the agent, like `SSH_AUTH_SOCK`
+pub const ROOSTER_AGENT_SOCK_ENV_VAR: &str = "ROOSTER_AGENT_SOCK";
+
+/// How long a client may take to send its request before the agent moves on
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Client for a running `rooster agent`
+///
+/// The agent speaks a line based protocol, one request per connection:
+/// - `GET <vault>`:       replies `OK <hex key>` or `NONE`
+/// - `PUT <hex key> <vault>`: replies `OK`
+/// - `FORGET <vault>`:    replies `OK`
+/// - `LOCK`:              wipes all keys, replies `OK`
+pub struct Agent {
+    socket: PathBuf,
+}
+
+impl Agent {
+    pub fn new(socket: PathBuf) -> Agent {
+        Agent { socket }
+    }
+
+    /// Returns the agent advertised in the environment, if any
+    pub fn from_env() -> Option<Agent> {
+        std::env::var_os(ROOSTER_AGENT_SOCK_ENV_VAR)
+            .filter(|socket| !socket.is_empty())
+            .map(|socket| Agent::new(PathBuf::from(socket)))
+    }
+
+    fn request_ok(&self, request: &str) -> IoResult<()> {
+        match self.request(request)?.deref().as_str() {
+            "OK" => Ok(()),
+            _ => Err(IoError::new(
+                IoErrorKind::InvalidData,
+                "invalid agent reply",
+            )),
+        }
+    }
+
+    fn request(&self, request: &str) -> IoResult<SafeString> {
+        let mut stream = UnixStream::connect(&self.socket)?;
+        stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+        stream.write_all(request.as_bytes())?;
+        stream.write_all(b"\n")?;
+
+        let mut reply = SafeString::new();
+        BufReader::new(&stream).read_line(&mut reply)?;
+        let len = reply.trim_end().len();
+        reply.truncate(len);
+        Ok(reply)
+    }
+}
+
+impl KeyCache for Agent {
+    fn get_key(&self, vault: &Path) -> IoResult<Option<SafeVec>> {
+        let reply = self.request(format!("GET {}", vault_id(vault)?).as_str())?;
+        if reply.deref() == "NONE" {
+            return Ok(None);
+        }
+
+        match reply.strip_prefix("OK ").and_then(decode_hex) {
+            Some(key) => Ok(Some(key)),
+            None => Err(IoError::new(
+                IoErrorKind::InvalidData,
+                "invalid agent reply",
+            )),
+        }
+    }
+
+    fn put_key(&self, vault: &Path, key: &SafeVec) -> IoResult<()> {
+        let request = SafeString::from_string(format!(
+            "PUT {} {}",
+            encode_hex(key).deref(),
+            vault_id(vault)?
+        ));
+        self.request_ok(request.deref())
+    }
+
+    fn forget_key(&self, vault: &Path) -> IoResult<()> {
+        self.request_ok(format!("FORGET {}", vault_id(vault)?).as_str())
+    }
+
+    fn lock(&self, _vault: &Path) -> IoResult<()> {
+        // The agent forgets all the keys it holds, not only the one for this file.
+        self.request_ok("LOCK")
+    }
+}
+
+fn encode_hex(bytes: &[u8]) -> SafeString {
+    let mut hex = SafeString::from_string(String::with_capacity(bytes.len() * 2));
+    for b in bytes {
+        hex.push_str(format!("{:02x}", b).as_str());
+    }
+    hex
+}
+
+fn decode_hex(hex: &str) -> Option<SafeVec> {
+    let mut bytes = SafeVec::new(Vec::with_capacity(hex.len() / 2));
+    for pair in hex.as_bytes().chunks(2) {
+        let pair = std::str::from_utf8(pair)
+            .ok()
+            .filter(|pair| pair.len() == 2)?;
+        bytes.inner_mut().push(u8::from_str_radix(pair, 16).ok()?);
+    }
+    Some(bytes)
+}
+
+/// A key that is locked in RAM, so it never ends up in swap
+struct LockedKey {
+    key: SafeVec,
+}
+
+impl LockedKey {
+    fn new(key: SafeVec) -> LockedKey {
+        // Failing to lock (e.g. because of RLIMIT_MEMLOCK) is not fatal: the key still
+        // gets zeroed when dropped.
+        unsafe {
+            libc::mlock(key.as_ptr() as *const libc::c_void, key.len());
+        }
+        LockedKey { key }
+    }
+}
+
+impl Drop for LockedKey {
+    fn drop(&mut self) {
+        for b in self.key.iter_mut() {
+            unsafe { ptr::write_volatile(b, 0) };
+        }
+        atomic::fence(atomic::Ordering::SeqCst);
+
+        unsafe {
+            libc::munlock(self.key.as_ptr() as *const libc::c_void, self.key.len());
+        }
+    }
+}
+
+/// Binds the agent socket so that only the current user can connect to it
+pub fn bind(socket: &Path) -> IoResult<UnixListener> {
+    // A socket left behind by an agent that was killed can be reused.
+    if socket.exists() && UnixStream::connect(socket).is_err() {
+        std::fs::remove_file(socket)?;
+    }
+
+    let old_umask = unsafe { libc::umask(0o177) };
+    let listener = UnixListener::bind(socket);
+    unsafe { libc::umask(old_umask) };
+    listener
+}
+
+/// Prevents the keys held in memory from being read through core dumps or ptrace
+pub fn disable_core_dumps() {
+    let limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    unsafe {
+        libc::setrlimit(libc::RLIMIT_CORE, &limit);
+    }
+
+    #[cfg(target_os = "linux")]
+    unsafe {
+        libc::prctl(libc::PR_SET_DUMPABLE, 0, 0, 0, 0);
+    }
+}
+
+/// Answers client requests until an error occurs
+///
+/// Keys are wiped when no client used the agent for `idle_timeout`.
+pub fn serve(listener: &UnixListener, idle_timeout: Option<Duration>) -> IoResult<()> {
+    let mut keys: HashMap<String, LockedKey> = HashMap::new();
+    let mut last_used = Instant::now();
+
+    loop {
+        let wait = match idle_timeout {
+            Some(idle_timeout) if !keys.is_empty() => {
+                let idle = last_used.elapsed();
+                if idle >= idle_timeout {
+                    keys.clear();
+                    continue;
+                }
+                Some(idle_timeout - idle)
+            }
+            _ => None,
+        };
+
+        if !wait_for_client(listener, wait)? {
+            continue;
+        }
+
+        let stream = match listener.accept() {
+            Ok((stream, _)) => stream,
+            Err(_) => continue,
+        };
+
+        // Only processes of the user who started the agent get an answer.
+        if !peer_cred::is_same_user(&stream) {
+            continue;
+        }
+
+        if handle_client(&stream, &mut keys).is_ok() {
+            last_used = Instant::now();
+        }
+    }
+}
+
+/// Returns true if a client is waiting, false if the timeout expired first
+fn wait_for_client(listener: &UnixListener, timeout: Option<Duration>) -> IoResult<bool> {
+    let mut fds = libc::pollfd {
+        fd: listener.as_raw_fd(),
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let timeout_ms = match timeout {
+        Some(timeout) => timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int,
+        None => -1,
+    };
+
+    match unsafe { libc::poll(&mut fds, 1, timeout_ms) } {
+        -1 => {
+            let err = IoError::last_os_error();
+            if err.kind() == IoErrorKind::Interrupted {
+                Ok(false)
+            } else {
+                Err(err)
+            }
+        }
+        0 => Ok(false),
+        _ => Ok(true),
+    }
+}
+
+fn handle_client(stream: &UnixStream, keys: &mut HashMap<String, LockedKey>) -> IoResult<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+
+    let mut request = SafeString::new();
+    BufReader::new(stream).read_line(&mut request)?;
+
+    let reply = handle_request(request.trim_end(), keys);
+    let mut stream = stream;
+    stream.write_all(reply.as_bytes())?;
+    stream.write_all(b"\n")
+}
+
+fn handle_request(request: &str, keys: &mut HashMap<String, LockedKey>) -> SafeString {
+    let (command, argument) = match request.find(' ') {
+        Some(i) => (&request[..i], &request[i + 1..]),
+        None => (request, ""),
+    };
+
+    let reply = match command {
+        "GET" => match keys.get(argument) {
+            Some(locked) => format!("OK {}", encode_hex(&locked.key).deref()),
+            None => "NONE".to_owned(),
+        },
+        "PUT" => {
+            let (hex, vault) = match argument.find(' ') {
+                Some(i) => (&argument[..i], &argument[i + 1..]),
+                None => return SafeString::from_string("ERR missing file name".to_owned()),
+            };
+            match decode_hex(hex) {
+                Some(key) => {
+                    keys.insert(vault.to_owned(), LockedKey::new(key));
+                    "OK".to_owned()
+                }
+                None => "ERR invalid key".to_owned(),
+            }
+        }
+        "FORGET" => {
+            keys.remove(argument);
+            "OK".to_owned()
+        }
+        "LOCK" => {
+            keys.clear();
+            "OK".to_owned()
+        }
+        _ => "ERR unknown command".to_owned(),
+    };
+
+    SafeString::from_string(reply)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bind, decode_hex, encode_hex, serve, Agent};
+    use crate::key_cache::KeyCache;
+    use crate::rutil::safe_vec::SafeVec;
+    use std::ops::Deref;
+    use std::path::PathBuf;
+    use std::thread;
+    use std::time::Duration;
+
+    fn spawn_agent(idle_timeout: Option<Duration>) -> (tempfile::TempDir, Agent) {
+        let dir = tempfile::tempdir().unwrap();
+        let socket = dir.path().join("agent.sock");
+        let listener = bind(&socket).unwrap();
+        thread::spawn(move || serve(&listener, idle_timeout));
+        (dir, Agent::new(socket))
+    }
+
+    #[test]
+    fn test_hex_roundtrip() {
+        let bytes = SafeVec::new(vec![0, 1, 127, 128, 255]);
+        assert_eq!(encode_hex(&bytes).deref(), "00017f80ff");
+        assert_eq!(decode_hex("00017f80ff").unwrap(), bytes);
+        assert!(decode_hex("0").is_none());
+        assert!(decode_hex("zz").is_none());
+    }
+
+    #[test]
+    fn test_agent_caches_keys_per_file() {
+        let (_dir, agent) = spawn_agent(None);
+        let vault1 = PathBuf::from("/nonexistent/first file.rooster");
+        let vault2 = PathBuf::from("/nonexistent/second.rooster");
+        let key = SafeVec::new(vec![42u8; 32]);
+
+        assert_eq!(agent.get_key(&vault1).unwrap(), None);
+        agent.put_key(&vault1, &key).unwrap();
+        assert_eq!(agent.get_key(&vault1).unwrap(), Some(key.clone()));
+        assert_eq!(agent.get_key(&vault2).unwrap(), None);
+
+        agent.forget_key(&vault1).unwrap();
+        assert_eq!(agent.get_key(&vault1).unwrap(), None);
+
+        agent.put_key(&vault1, &key).unwrap();
+        agent.put_key(&vault2, &key).unwrap();
+        agent.lock(&vault1).unwrap();
+        assert_eq!(agent.get_key(&vault1).unwrap(), None);
+        assert_eq!(agent.get_key(&vault2).unwrap(), None);
+    }
+
+    #[test]
+    fn test_agent_wipes_keys_when_idle() {
+        let (_dir, agent) = spawn_agent(Some(Duration::from_millis(100)));
+        let vault = PathBuf::from("/nonexistent/vault.rooster");
+
+        agent.put_key(&vault, &SafeVec::new(vec![1u8; 32])).unwrap();
+        thread::sleep(Duration::from_millis(300));
+        assert_eq!(agent.get_key(&vault).unwrap(), None);
+    }
+}
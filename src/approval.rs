@@ -0,0 +1,39 @@
+use crate::quale::which;
+use crate::rutil::external_tool::run_tool;
+
+/// A command asking the user a yes/no question, given as its last argument, which approves by
+/// exiting with 0. For instance `zenity --question --no-markup --text`.
+pub const ROOSTER_APPROVE_COMMAND_ENV_VAR: &str = "ROOSTER_APPROVE_COMMAND";
+
+/// The command asking questions, from the environment or else a dialog we can find
+fn approve_command() -> Option<Vec<String>> {
+    let command = match std::env::var(ROOSTER_APPROVE_COMMAND_ENV_VAR) {
+        Ok(ref command) if !command.trim().is_empty() => {
+            return Some(command.split_whitespace().map(String::from).collect());
+        }
+        _ if which("zenity").is_some() => vec![
+            "zenity",
+            "--question",
+            "--no-markup",
+            "--title",
+            "Rooster",
+            "--text",
+        ],
+        _ if which("kdialog").is_some() => vec!["kdialog", "--title", "Rooster", "--yesno"],
+        _ => return None,
+    };
+    Some(command.into_iter().map(String::from).collect())
+}
+
+/// Asks the user whether to go ahead, with a dialog since programs like browsers start us
+/// without a terminal. Without a way to ask, the answer is no.
+pub fn approve(question: &str) -> bool {
+    match approve_command() {
+        Some(mut command) => {
+            command.push(question.to_owned());
+            let command: Vec<&str> = command.iter().map(|arg| arg.as_str()).collect();
+            run_tool(&command).is_ok()
+        }
+        None => false,
+    }
+}
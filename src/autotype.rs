@@ -0,0 +1,236 @@
+use crate::password::v2::Password;
+use crate::quale::which;
+use crate::rutil::external_tool::{pipe_to_tool, run_tool};
+use crate::rutil::safe_string::SafeString;
+use std::ops::Deref;
+use std::time::Duration;
+
+/// Forces the tool used to type passwords instead of detecting one: xdotool or ydotool
+pub const ROOSTER_AUTOTYPE_ENV_VAR: &str = "ROOSTER_AUTOTYPE";
+
+/// The field of a password that holds its keystroke sequence
+pub const AUTOTYPE_FIELD: &str = "autotype";
+
+/// The keystroke sequence used when a password doesn't have one
+pub const DEFAULT_SEQUENCE: &str = "{username}{tab}{password}";
+
+const DEFAULT_WAIT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, PartialEq)]
+pub enum Key {
+    Tab,
+    Enter,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Keystroke {
+    Text(SafeString),
+    Key(Key),
+    Wait(Duration),
+}
+
+/// Turns a sequence such as `{username}{enter}{wait}{password}` into keystrokes
+///
+/// Placeholders are `{username}`, `{password}`, `{tab}`, `{enter}`, `{wait}` (one second) and
+/// `{wait:N}` (N milliseconds). Everything else is typed as is.
+pub fn parse_sequence(sequence: &str, password: &Password) -> Result<Vec<Keystroke>, String> {
+    let mut keystrokes = Vec::new();
+    let mut rest = sequence;
+
+    while !rest.is_empty() {
+        let start = match rest.find('{') {
+            Some(start) => start,
+            None => {
+                keystrokes.push(Keystroke::Text(SafeString::from_string(rest.to_owned())));
+                break;
+            }
+        };
+        if start > 0 {
+            keystrokes.push(Keystroke::Text(SafeString::from_string(
+                rest[..start].to_owned(),
+            )));
+        }
+
+        let end = match rest[start..].find('}') {
+            Some(end) => start + end,
+            None => return Err(format!("\"{}\" is never closed", &rest[start..])),
+        };
+        let placeholder = &rest[start + 1..end];
+        rest = &rest[end + 1..];
+
+        keystrokes.push(match placeholder {
+            "username" => Keystroke::Text(SafeString::from_string(password.username.clone())),
+            "password" => Keystroke::Text(password.password.clone()),
+            "tab" => Keystroke::Key(Key::Tab),
+            "enter" => Keystroke::Key(Key::Enter),
+            "wait" => Keystroke::Wait(DEFAULT_WAIT),
+            _ => match placeholder
+                .strip_prefix("wait:")
+                .and_then(|ms| ms.parse::<u64>().ok())
+            {
+                Some(ms) => Keystroke::Wait(Duration::from_millis(ms)),
+                None => return Err(format!("{{{}}} is not a known placeholder", placeholder)),
+            },
+        });
+    }
+
+    Ok(keystrokes)
+}
+
+/// A tool that types into the focused window, as if the user typed on their keyboard
+pub trait Typist {
+    fn type_text(&self, text: &SafeString) -> Result<(), ()>;
+
+    fn press(&self, key: &Key) -> Result<(), ()>;
+}
+
+/// Types with xdotool, on X11
+pub struct Xdotool;
+
+impl Typist for Xdotool {
+    fn type_text(&self, text: &SafeString) -> Result<(), ()> {
+        // Reading the text from stdin keeps the password out of the process list.
+        pipe_to_tool(
+            &["xdotool", "type", "--clearmodifiers", "--file", "-"],
+            text.deref().as_bytes(),
+        )
+    }
+
+    fn press(&self, key: &Key) -> Result<(), ()> {
+        let name = match key {
+            Key::Tab => "Tab",
+            Key::Enter => "Return",
+        };
+        run_tool(&["xdotool", "key", "--clearmodifiers", name])
+    }
+}
+
+/// Types with ydotool, which works on Wayland (and anywhere the uinput device is available)
+pub struct Ydotool;
+
+impl Typist for Ydotool {
+    fn type_text(&self, text: &SafeString) -> Result<(), ()> {
+        pipe_to_tool(&["ydotool", "type", "--file", "-"], text.deref().as_bytes())
+    }
+
+    fn press(&self, key: &Key) -> Result<(), ()> {
+        // ydotool wants Linux input event codes, pressed then released.
+        let (down, up) = match key {
+            Key::Tab => ("15:1", "15:0"),
+            Key::Enter => ("28:1", "28:0"),
+        };
+        run_tool(&["ydotool", "key", down, up])
+    }
+}
+
+/// Returns the tool to type with, if one is available
+pub fn typist_from_env() -> Option<Box<dyn Typist>> {
+    match std::env::var(ROOSTER_AUTOTYPE_ENV_VAR) {
+        Ok(ref name) if name == "xdotool" => return Some(Box::new(Xdotool)),
+        Ok(ref name) if name == "ydotool" => return Some(Box::new(Ydotool)),
+        _ => {}
+    }
+
+    let is_wayland = std::env::var_os("WAYLAND_DISPLAY").is_some();
+    if is_wayland && which("ydotool").is_some() {
+        return Some(Box::new(Ydotool));
+    }
+    if std::env::var_os("DISPLAY").is_some() && which("xdotool").is_some() {
+        return Some(Box::new(Xdotool));
+    }
+    if which("ydotool").is_some() {
+        return Some(Box::new(Ydotool));
+    }
+    None
+}
+
+pub fn type_keystrokes(typist: &dyn Typist, keystrokes: &[Keystroke]) -> Result<(), ()> {
+    for keystroke in keystrokes {
+        match keystroke {
+            Keystroke::Text(text) => typist.type_text(text)?,
+            Keystroke::Key(key) => typist.press(key)?,
+            Keystroke::Wait(duration) => std::thread::sleep(*duration),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_sequence, type_keystrokes, Key, Keystroke, Typist, DEFAULT_SEQUENCE};
+    use crate::password::v2::Password;
+    use crate::rutil::safe_string::SafeString;
+    use std::cell::RefCell;
+    use std::ops::Deref;
+    use std::time::Duration;
+
+    fn text(s: &str) -> Keystroke {
+        Keystroke::Text(SafeString::from_string(s.to_owned()))
+    }
+
+    #[test]
+    fn test_parse_default_sequence() {
+        let password = Password::new("GitHub", "alice", "hunter{2}");
+        assert_eq!(
+            parse_sequence(DEFAULT_SEQUENCE, &password).unwrap(),
+            vec![text("alice"), Keystroke::Key(Key::Tab), text("hunter{2}")]
+        );
+    }
+
+    #[test]
+    fn test_parse_custom_sequence() {
+        let password = Password::new("Bank", "alice", "hunter2");
+        assert_eq!(
+            parse_sequence(
+                "id:{username}{enter}{wait}{wait:250}{password}{enter}",
+                &password
+            )
+            .unwrap(),
+            vec![
+                text("id:"),
+                text("alice"),
+                Keystroke::Key(Key::Enter),
+                Keystroke::Wait(Duration::from_secs(1)),
+                Keystroke::Wait(Duration::from_millis(250)),
+                text("hunter2"),
+                Keystroke::Key(Key::Enter),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_sequence() {
+        let password = Password::new("Bank", "alice", "hunter2");
+        assert!(parse_sequence("{username}{tab", &password).is_err());
+        assert!(parse_sequence("{username}{space}", &password).is_err());
+        assert!(parse_sequence("{wait:soon}", &password).is_err());
+    }
+
+    struct RecordingTypist {
+        typed: RefCell<Vec<String>>,
+    }
+
+    impl Typist for RecordingTypist {
+        fn type_text(&self, text: &SafeString) -> Result<(), ()> {
+            self.typed.borrow_mut().push(text.deref().to_owned());
+            Ok(())
+        }
+
+        fn press(&self, key: &Key) -> Result<(), ()> {
+            self.typed.borrow_mut().push(format!("{:?}", key));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_type_keystrokes() {
+        let typist = RecordingTypist {
+            typed: RefCell::new(Vec::new()),
+        };
+        let password = Password::new("Bank", "alice", "hunter2");
+        let keystrokes = parse_sequence("{username}{enter}{password}", &password).unwrap();
+
+        type_keystrokes(&typist, &keystrokes).unwrap();
+        assert_eq!(typist.typed.into_inner(), vec!["alice", "Enter", "hunter2"]);
+    }
+}
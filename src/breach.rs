@@ -0,0 +1,175 @@
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use std::cmp::Ordering;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Result as IoResult, Seek, SeekFrom};
+use std::path::Path;
+
+/// The Pwned Passwords file that `add`, `change` and `regenerate` check new passwords against
+pub const ROOSTER_HIBP_FILE_ENV_VAR: &str = "ROOSTER_HIBP_FILE";
+
+/// A Have I Been Pwned "Pwned Passwords" file, SHA-1 version, ordered by hash
+///
+/// Each line looks like `5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8:9545824`. These files are
+/// tens of gigabytes, so they are binary searched on disk rather than read in memory.
+pub struct HibpFile {
+    reader: BufReader<File>,
+    len: u64,
+}
+
+impl HibpFile {
+    pub fn open(path: &Path) -> IoResult<HibpFile> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        Ok(HibpFile {
+            reader: BufReader::new(file),
+            len,
+        })
+    }
+
+    /// Returns how many times a password appears in data breaches, if it does
+    pub fn count(&mut self, password: &str) -> IoResult<Option<u64>> {
+        let hash = sha1_hex(password);
+
+        // Lines that start before `low` have smaller hashes, lines that start at `high` or
+        // after have larger ones.
+        let mut low = 0;
+        let mut high = self.len;
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let (line_start, line) = match self.line_at_or_after(middle)? {
+                Some(line) => line,
+                None => {
+                    high = middle;
+                    continue;
+                }
+            };
+
+            let (line_hash, count) = match line.trim_end().find(':') {
+                Some(colon) => (&line[..colon], line[colon + 1..].trim_end()),
+                None => (line.trim_end(), ""),
+            };
+            match line_hash.to_ascii_uppercase().as_str().cmp(hash.as_str()) {
+                Ordering::Equal => return Ok(Some(count.parse::<u64>().unwrap_or(1))),
+                Ordering::Less => low = line_start + line.len() as u64,
+                Ordering::Greater => high = middle,
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the first line that starts at `position` or after, along with where it starts
+    fn line_at_or_after(&mut self, position: u64) -> IoResult<Option<(u64, String)>> {
+        let mut line_start = position;
+        if position > 0 {
+            // Unless the previous character ends a line, we're in the middle of one: skip it.
+            self.reader.seek(SeekFrom::Start(position - 1))?;
+            let mut rest_of_line = Vec::new();
+            line_start = position - 1 + self.reader.read_until(b'\n', &mut rest_of_line)? as u64;
+        } else {
+            self.reader.seek(SeekFrom::Start(0))?;
+        }
+
+        let mut line = String::new();
+        if self.reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        Ok(Some((line_start, line)))
+    }
+}
+
+fn sha1_hex(password: &str) -> String {
+    openssl::sha::sha1(password.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect()
+}
+
+/// Warns the user if a new password appears in the file set in `ROOSTER_HIBP_FILE`, if any
+pub fn warn_if_breached(password: &str, io: &mut impl CliInputOutput) {
+    let path = match std::env::var_os(ROOSTER_HIBP_FILE_ENV_VAR) {
+        Some(path) => path,
+        None => return,
+    };
+
+    match HibpFile::open(Path::new(&path)).and_then(|mut file| file.count(password)) {
+        Ok(Some(count)) => io.warning(
+            format!(
+                "This password appeared {} times in data breaches, you should not use it.",
+                count
+            ),
+            OutputType::Standard,
+        ),
+        Ok(None) => {}
+        Err(err) => io.warning(
+            format!(
+                "I couldn't check whether this password appeared in data breaches (reason: {}).",
+                err
+            ),
+            OutputType::Standard,
+        ),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{sha1_hex, HibpFile};
+    use std::io::Write;
+
+    fn hibp_file(passwords: &[(&str, u64)], line_ending: &str) -> tempfile::NamedTempFile {
+        let mut lines: Vec<String> = passwords
+            .iter()
+            .map(|(password, count)| format!("{}:{}", sha1_hex(password), count))
+            .collect();
+        lines.sort();
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for line in lines {
+            write!(file, "{}{}", line, line_ending).unwrap();
+        }
+        file
+    }
+
+    #[test]
+    fn test_sha1_hex() {
+        assert_eq!(
+            sha1_hex("password"),
+            "5BAA61E4C9B93F3F0682250B6CF8331B7EE68FD8"
+        );
+    }
+
+    #[test]
+    fn test_count() {
+        let passwords: Vec<(String, u64)> = (0..500)
+            .map(|i| (format!("password{}", i), i + 1))
+            .collect();
+        let passwords: Vec<(&str, u64)> = passwords.iter().map(|(p, c)| (p.as_str(), *c)).collect();
+
+        for line_ending in ["\n", "\r\n"].iter() {
+            let file = hibp_file(&passwords, line_ending);
+            let mut hibp = HibpFile::open(file.path()).unwrap();
+            for (password, count) in passwords.iter() {
+                assert_eq!(hibp.count(password).unwrap(), Some(*count));
+            }
+            assert_eq!(hibp.count("not breached").unwrap(), None);
+            assert_eq!(hibp.count("").unwrap(), None);
+        }
+    }
+
+    #[test]
+    fn test_count_small_files() {
+        let empty = hibp_file(&[], "\n");
+        assert_eq!(
+            HibpFile::open(empty.path())
+                .unwrap()
+                .count("password")
+                .unwrap(),
+            None
+        );
+
+        let one = hibp_file(&[("password", 42)], "\r\n");
+        let mut hibp = HibpFile::open(one.path()).unwrap();
+        assert_eq!(hibp.count("password").unwrap(), Some(42));
+        assert_eq!(hibp.count("hunter2").unwrap(), None);
+    }
+}
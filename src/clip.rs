@@ -1,116 +1,197 @@
+use crate::generate::generate_hex_token;
 use crate::password;
 use crate::rclio::{CliInputOutput, OutputType};
 use crate::rutil::safe_string::SafeString;
+use serde_json::json;
 
+use std::ffi::OsStr;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Write};
 use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
 
-// On Windows and Mac, we'll use the native solutions provided by the OS libraries
-#[cfg(any(windows, target_os = "macos"))]
-pub fn copy_to_clipboard(s: &SafeString) -> Result<(), ()> {
-    use clipboard::ClipboardContext;
-    use clipboard::ClipboardProvider;
-
-    let mut context: ClipboardContext = ClipboardProvider::new().map_err(|_| ())?;
-    context.set_contents(s.deref().to_owned()).map_err(|_| ())?;
-    Ok(())
-}
-
-// On UNIX, the most stable way to copy to the clipboard is using one of the existing
-// and battle tested tools: xsel and xclip.
-#[cfg(all(unix, not(target_os = "macos")))]
-pub fn copy_to_clipboard(s: &SafeString) -> Result<(), ()> {
-    use crate::quale::which;
-    use crate::shell_escape;
-    use std::env;
-    use std::process::Command;
-
-    let password = SafeString::from_string(shell_escape::escape(s.deref().into()).into());
-
-    fn wayland_clipboards(password: &SafeString) -> Result<(), ()> {
-        match which("wl-copy") {
-            Some(wl_copy) => {
-                let shell = format!(
-                    "printf '%s' {} | {} 2> /dev/null",
-                    password.deref(),
-                    wl_copy.to_string_lossy()
-                );
-                if Command::new("sh")
-                    .args(&["-c", shell.as_str()])
-                    .status()
-                    .map_err(|_| ())?
-                    .success()
-                {
-                    Ok(())
-                } else {
-                    Err(())
-                }
+mod backend;
+
+/// How many seconds a password stays in the clipboard, 0 to keep it there
+pub const ROOSTER_CLIPBOARD_TIMEOUT_ENV_VAR: &str = "ROOSTER_CLIPBOARD_TIMEOUT";
+
+/// Set to "on" to clear the clipboard as soon as the password has been pasted once
+pub const ROOSTER_CLIPBOARD_PASTE_ONCE_ENV_VAR: &str = "ROOSTER_CLIPBOARD_PASTE_ONCE";
+
+const DEFAULT_CLIPBOARD_TIMEOUT: u64 = 30;
+
+/// How many hexadecimal characters of random nonce the digest of a copied password is keyed with
+const CLIPBOARD_NONCE_LEN: usize = 32;
+
+/// What happens to a password once it has been copied to the clipboard
+pub enum Clearing {
+    Never,
+    AfterSeconds(u64),
+    AfterFirstPaste,
+}
+
+/// Where a password was copied and how long it stays there
+pub struct Copied {
+    paste_keys: &'static str,
+    clearing: Clearing,
+}
+
+impl Copied {
+    pub fn describe(&self) -> String {
+        match self.clearing {
+            Clearing::Never => format!("paste with {}", self.paste_keys),
+            Clearing::AfterSeconds(seconds) => format!(
+                "paste with {}, cleared in {} seconds",
+                self.paste_keys, seconds
+            ),
+            Clearing::AfterFirstPaste => {
+                format!("paste with {}, cleared once pasted", self.paste_keys)
             }
-            None => Err(()),
         }
     }
+}
 
-    fn x11_clipboards(password: &SafeString) -> Result<(), ()> {
-        match which("xsel") {
-            Some(xsel) => {
-                let shell = format!(
-                    "printf '%s' {} | {} -ib 2> /dev/null",
-                    password.deref(),
-                    xsel.to_string_lossy()
-                );
-                if Command::new("sh")
-                    .args(&["-c", shell.as_str()])
-                    .status()
-                    .map_err(|_| ())?
-                    .success()
-                {
-                    Ok(())
-                } else {
-                    Err(())
-                }
-            }
-            None => match which("xclip") {
-                Some(xclip) => {
-                    let shell = format!(
-                        "printf '%s' {} | {} -selection clipboard 2> /dev/null",
-                        password.deref(),
-                        xclip.to_string_lossy()
-                    );
-                    if Command::new("sh")
-                        .args(&["-c", shell.as_str()])
-                        .status()
-                        .map_err(|_| ())?
-                        .success()
-                    {
-                        Ok(())
-                    } else {
-                        Err(())
-                    }
-                }
-                None => Err(()),
-            },
-        }
+fn clipboard_timeout() -> u64 {
+    std::env::var(ROOSTER_CLIPBOARD_TIMEOUT_ENV_VAR)
+        .ok()
+        .and_then(|timeout| timeout.parse::<u64>().ok())
+        .unwrap_or(DEFAULT_CLIPBOARD_TIMEOUT)
+}
+
+fn paste_once_requested() -> bool {
+    match std::env::var(ROOSTER_CLIPBOARD_PASTE_ONCE_ENV_VAR) {
+        Ok(value) => value == "on",
+        Err(_) => false,
     }
+}
 
-    match env::var_os("XDG_SESSION_TYPE") {
-        Some(s) if s == "wayland" => {
-            let s = wayland_clipboards(&password);
-            match s {
-                Ok(_) => Ok(()),
-                Err(_) => x11_clipboards(&password),
+/// Copies a password to the clipboard, making sure it doesn't stay there forever
+///
+/// Each available clipboard backend is tried in turn, until one of them works.
+pub fn copy_password_to_clipboard(s: &SafeString) -> Result<Copied, ()> {
+    for backend in backend::from_env() {
+        let clearing = if paste_once_requested() && backend.supports_paste_once() {
+            match backend.copy_once(s) {
+                Ok(_) => Clearing::AfterFirstPaste,
+                Err(_) => continue,
             }
-        }
-        _ => x11_clipboards(&password),
+        } else {
+            if backend.copy(s).is_err() {
+                continue;
+            }
+            match clipboard_timeout() {
+                0 => Clearing::Never,
+                _ if !backend.can_clear_in_background() => Clearing::Never,
+                seconds => match spawn_clipboard_cleaner(backend.name(), s, seconds) {
+                    Ok(_) => Clearing::AfterSeconds(seconds),
+                    Err(_) => Clearing::Never,
+                },
+            }
+        };
+
+        return Ok(Copied {
+            paste_keys: backend.paste_keys(),
+            clearing,
+        });
+    }
+
+    Err(())
+}
+
+/// Hashes clipboard contents with a random nonce, so the cleaner can recognize the password
+/// without knowing it, and the digest can't be looked up in a table of common passwords
+fn clipboard_digest(nonce: &str, s: &str) -> String {
+    let mut hasher = openssl::sha::Sha256::new();
+    hasher.update(nonce.as_bytes());
+    hasher.update(s.as_bytes());
+    hasher
+        .finish()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+/// What the cleaner reads on its stdin: a nonce and the digest of the password with it
+fn cleaner_input(nonce: &str, s: &str) -> String {
+    format!("{} {}\n", nonce, clipboard_digest(nonce, s))
+}
+
+/// Whether the clipboard still contains the password the cleaner was started for
+pub fn still_copied(cleaner_input: &str, contents: &str) -> bool {
+    let mut parts = cleaner_input.split_whitespace();
+    match (parts.next(), parts.next()) {
+        (Some(nonce), Some(digest)) => clipboard_digest(nonce, contents) == digest,
+        _ => false,
     }
 }
 
-#[cfg(target_os = "macos")]
-pub fn paste_keys() -> &'static str {
-    "Cmd+V"
+/// The program to start the cleaner with, which is this one, unless it is a test harness: those
+/// are built in `target/*/deps` and would take `clear-clipboard` for the name of a test.
+fn cleaner_program() -> std::io::Result<PathBuf> {
+    let program = std::env::current_exe()?;
+    if program.parent().and_then(Path::file_name) == Some(OsStr::new("deps")) {
+        return Err(IoError::new(
+            IoErrorKind::Unsupported,
+            "test harnesses can't clear the clipboard",
+        ));
+    }
+    Ok(program)
 }
 
-#[cfg(not(target_os = "macos"))]
-pub fn paste_keys() -> &'static str {
-    "Ctrl+V"
+/// `rooster clear-clipboard`, which gets the digest of the password on its stdin, so that it
+/// isn't visible in its environment or in the process list
+fn cleaner_command(program: &Path, backend_name: &str, seconds: u64) -> Command {
+    let mut command = Command::new(program);
+    command
+        .arg("clear-clipboard")
+        .arg(seconds.to_string())
+        .env(backend::ROOSTER_CLIPBOARD_ENV_VAR, backend_name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    command
+}
+
+/// Starts `rooster clear-clipboard` in the background, which outlives this process
+fn spawn_clipboard_cleaner(
+    backend_name: &str,
+    s: &SafeString,
+    seconds: u64,
+) -> std::io::Result<()> {
+    let mut command = cleaner_command(&cleaner_program()?, backend_name, seconds);
+
+    #[cfg(unix)]
+    unsafe {
+        use std::os::unix::process::CommandExt;
+
+        // Start a new session, so that closing the terminal doesn't stop the cleaner.
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+
+    let mut child = command.spawn()?;
+    let nonce = generate_hex_token(CLIPBOARD_NONCE_LEN);
+    // Dropping stdin closes it, which tells the cleaner it has everything.
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(cleaner_input(nonce.deref(), s.deref()).as_bytes())
+}
+
+/// Returns what the clipboard contains, through the first available backend
+pub fn paste_from_clipboard() -> Result<SafeString, ()> {
+    match backend::from_env().first() {
+        Some(backend) => backend.paste(),
+        None => Err(()),
+    }
+}
+
+/// Empties the clipboard, through the first available backend
+pub fn clear_clipboard() -> Result<(), ()> {
+    match backend::from_env().first() {
+        Some(backend) => backend.clear(),
+        None => Err(()),
+    }
 }
 
 pub fn confirm_password_retrieved(
@@ -119,6 +200,7 @@ pub fn confirm_password_retrieved(
     io: &mut impl CliInputOutput,
 ) {
     if show {
+        io.result(json!({"entry": password.to_json(true), "copied": false}));
         io.success(
             format!("Alright! Here is your password for {}:", password.name),
             OutputType::Standard,
@@ -132,31 +214,88 @@ pub fn confirm_password_retrieved(
             OutputType::Standard,
         );
     } else {
-        if copy_to_clipboard(&password.password).is_err() {
-            io.success(
-                format!(
-                    "Hmm, I tried to copy your new password to your clipboard, but \
-                     something went wrong. You can see it with `rooster get '{}' --show`",
-                    password.name
-                ),
-                OutputType::Standard,
-            );
-        } else {
-            io.success(
-                format!("Alright! Here is your password for {}:", password.name),
-                OutputType::Standard,
-            );
-            io.success(
-                format!("Username: {}", password.username),
-                OutputType::Standard,
-            );
-            io.success(
-                format!(
-                    "Password: ******** (copied to clipboard, paste with {})",
-                    paste_keys()
-                ),
-                OutputType::Standard,
-            );
+        let copied = copy_password_to_clipboard(&password.password);
+        io.result(json!({"entry": password.to_json(false), "copied": copied.is_ok()}));
+        match copied {
+            Err(_) => {
+                io.success(
+                    format!(
+                        "Hmm, I tried to copy your new password to your clipboard, but \
+                         something went wrong. You can see it with `rooster get '{}' --show`",
+                        password.name
+                    ),
+                    OutputType::Standard,
+                );
+            }
+            Ok(copied) => {
+                io.success(
+                    format!("Alright! Here is your password for {}:", password.name),
+                    OutputType::Standard,
+                );
+                io.success(
+                    format!("Username: {}", password.username),
+                    OutputType::Standard,
+                );
+                io.success(
+                    format!(
+                        "Password: ******** (copied to clipboard, {})",
+                        copied.describe()
+                    ),
+                    OutputType::Standard,
+                );
+            }
         }
     }
 }
+
+/// The message to show once a new password has been saved and copied to the clipboard
+pub fn password_saved_message(copied: &Copied) -> String {
+    format!(
+        "Alright! I've saved your new password. You can paste it anywhere ({}).",
+        copied.describe()
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{cleaner_command, cleaner_input, cleaner_program, clipboard_digest, still_copied};
+    use std::ffi::OsStr;
+    use std::path::Path;
+
+    #[test]
+    fn test_clipboard_digest() {
+        let input = cleaner_input("0123abcd", "hunter2");
+        assert!(still_copied(&input, "hunter2"));
+        assert!(!still_copied(&input, "hunter3"));
+        assert!(!still_copied("", "hunter2"));
+        assert!(!still_copied("0123abcd", "hunter2"));
+
+        // The same password gives another digest with another nonce.
+        assert_ne!(
+            clipboard_digest("0123abcd", "hunter2"),
+            clipboard_digest("4567ef01", "hunter2")
+        );
+        assert!(!still_copied(
+            &input.replace("0123abcd", "4567ef01"),
+            "hunter2"
+        ));
+    }
+
+    #[test]
+    fn test_cleaner_command() {
+        let command = cleaner_command(Path::new("/usr/bin/rooster"), "xsel", 30);
+        assert_eq!(command.get_program(), OsStr::new("/usr/bin/rooster"));
+        assert_eq!(
+            command.get_args().collect::<Vec<&OsStr>>(),
+            vec![OsStr::new("clear-clipboard"), OsStr::new("30")]
+        );
+        // Only the backend goes through the environment, the digest goes through stdin.
+        assert_eq!(
+            command.get_envs().collect::<Vec<_>>(),
+            vec![(OsStr::new("ROOSTER_CLIPBOARD"), Some(OsStr::new("xsel")))]
+        );
+
+        // This is a test harness, which must not be started as the cleaner.
+        assert!(cleaner_program().is_err());
+    }
+}
@@ -0,0 +1,391 @@
+#[cfg(unix)]
+use crate::rutil::external_tool::{pipe_to_tool, read_from_tool, run_tool};
+use crate::rutil::safe_string::SafeString;
+#[cfg(unix)]
+use std::io::Write;
+use std::ops::Deref;
+
+/// Forces a clipboard backend instead of detecting one: wl-copy, xsel, xclip, tmux or osc52
+pub const ROOSTER_CLIPBOARD_ENV_VAR: &str = "ROOSTER_CLIPBOARD";
+
+/// A place where Rooster can put passwords for the user to paste them
+pub trait ClipboardBackend {
+    /// The name used to pick this backend in `ROOSTER_CLIPBOARD`
+    fn name(&self) -> &'static str;
+
+    /// The keys to press to paste what was copied
+    fn paste_keys(&self) -> &'static str;
+
+    fn copy(&self, s: &SafeString) -> Result<(), ()>;
+
+    fn paste(&self) -> Result<SafeString, ()>;
+
+    fn clear(&self) -> Result<(), ()>;
+
+    /// Returns true if the backend can serve a password only once
+    fn supports_paste_once(&self) -> bool {
+        false
+    }
+
+    fn copy_once(&self, _s: &SafeString) -> Result<(), ()> {
+        Err(())
+    }
+
+    /// Returns true if `rooster clear-clipboard` can check and clear the clipboard later on,
+    /// after Rooster itself has exited
+    fn can_clear_in_background(&self) -> bool {
+        true
+    }
+}
+
+/// Returns the backends to try, in order of preference
+pub fn from_env() -> Vec<Box<dyn ClipboardBackend>> {
+    match std::env::var(ROOSTER_CLIPBOARD_ENV_VAR) {
+        Ok(ref name) if !name.is_empty() => by_name(name).into_iter().collect(),
+        _ => detect(),
+    }
+}
+
+/// Returns the backend with the given name, if it exists on this platform
+pub fn by_name(name: &str) -> Option<Box<dyn ClipboardBackend>> {
+    all().into_iter().find(|backend| backend.name() == name)
+}
+
+fn all() -> Vec<Box<dyn ClipboardBackend>> {
+    let mut backends = system_backends();
+    backends.extend(terminal_backends());
+    backends
+}
+
+fn detect() -> Vec<Box<dyn ClipboardBackend>> {
+    let mut backends = detect_system_backends();
+    backends.extend(detect_terminal_backends());
+    backends
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+fn system_backends() -> Vec<Box<dyn ClipboardBackend>> {
+    vec![Box::new(Native)]
+}
+
+#[cfg(any(windows, target_os = "macos"))]
+fn detect_system_backends() -> Vec<Box<dyn ClipboardBackend>> {
+    system_backends()
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn system_backends() -> Vec<Box<dyn ClipboardBackend>> {
+    vec![Box::new(WlCopy), Box::new(Xsel), Box::new(Xclip)]
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn detect_system_backends() -> Vec<Box<dyn ClipboardBackend>> {
+    use crate::quale::which;
+
+    let mut backends: Vec<Box<dyn ClipboardBackend>> = Vec::new();
+
+    if is_wayland() && which("wl-copy").is_some() {
+        backends.push(Box::new(WlCopy));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        if which("xsel").is_some() {
+            backends.push(Box::new(Xsel));
+        } else if which("xclip").is_some() {
+            backends.push(Box::new(Xclip));
+        }
+    }
+
+    backends
+}
+
+#[cfg(windows)]
+fn terminal_backends() -> Vec<Box<dyn ClipboardBackend>> {
+    Vec::new()
+}
+
+#[cfg(windows)]
+fn detect_terminal_backends() -> Vec<Box<dyn ClipboardBackend>> {
+    Vec::new()
+}
+
+#[cfg(unix)]
+fn terminal_backends() -> Vec<Box<dyn ClipboardBackend>> {
+    vec![Box::new(Tmux), Box::new(Osc52)]
+}
+
+/// Without a graphical session (e.g. over SSH), passwords go through the terminal instead
+#[cfg(unix)]
+fn detect_terminal_backends() -> Vec<Box<dyn ClipboardBackend>> {
+    use crate::quale::which;
+
+    let mut backends: Vec<Box<dyn ClipboardBackend>> = Vec::new();
+
+    if std::env::var_os("TMUX").is_some() && which("tmux").is_some() {
+        backends.push(Box::new(Tmux));
+    }
+    if std::env::var_os("SSH_TTY").is_some() || std::env::var_os("SSH_CONNECTION").is_some() {
+        backends.push(Box::new(Osc52));
+    }
+
+    backends
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn is_wayland() -> bool {
+    match std::env::var_os("XDG_SESSION_TYPE") {
+        Some(s) => s == "wayland",
+        None => std::env::var_os("WAYLAND_DISPLAY").is_some(),
+    }
+}
+
+// On Windows and Mac, we'll use the native solutions provided by the OS libraries
+#[cfg(any(windows, target_os = "macos"))]
+pub struct Native;
+
+#[cfg(any(windows, target_os = "macos"))]
+impl ClipboardBackend for Native {
+    fn name(&self) -> &'static str {
+        "native"
+    }
+
+    #[cfg(target_os = "macos")]
+    fn paste_keys(&self) -> &'static str {
+        "Cmd+V"
+    }
+
+    #[cfg(windows)]
+    fn paste_keys(&self) -> &'static str {
+        "Ctrl+V"
+    }
+
+    fn copy(&self, s: &SafeString) -> Result<(), ()> {
+        use clipboard::ClipboardContext;
+        use clipboard::ClipboardProvider;
+
+        let mut context: ClipboardContext = ClipboardProvider::new().map_err(|_| ())?;
+        context.set_contents(s.deref().to_owned()).map_err(|_| ())?;
+        Ok(())
+    }
+
+    fn paste(&self) -> Result<SafeString, ()> {
+        use clipboard::ClipboardContext;
+        use clipboard::ClipboardProvider;
+
+        let mut context: ClipboardContext = ClipboardProvider::new().map_err(|_| ())?;
+        Ok(SafeString::from_string(
+            context.get_contents().map_err(|_| ())?,
+        ))
+    }
+
+    fn clear(&self) -> Result<(), ()> {
+        self.copy(&SafeString::new())
+    }
+}
+
+/// The Wayland clipboard, through wl-clipboard
+#[cfg(all(unix, not(target_os = "macos")))]
+pub struct WlCopy;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl ClipboardBackend for WlCopy {
+    fn name(&self) -> &'static str {
+        "wl-copy"
+    }
+
+    fn paste_keys(&self) -> &'static str {
+        "Ctrl+V"
+    }
+
+    fn copy(&self, s: &SafeString) -> Result<(), ()> {
+        pipe_to_tool(&["wl-copy"], s.deref().as_bytes())
+    }
+
+    fn paste(&self) -> Result<SafeString, ()> {
+        read_from_tool(&["wl-paste", "--no-newline"])
+    }
+
+    fn clear(&self) -> Result<(), ()> {
+        run_tool(&["wl-copy", "--clear"])
+    }
+
+    fn supports_paste_once(&self) -> bool {
+        true
+    }
+
+    fn copy_once(&self, s: &SafeString) -> Result<(), ()> {
+        pipe_to_tool(&["wl-copy", "--paste-once"], s.deref().as_bytes())
+    }
+}
+
+/// The X11 clipboard, through xsel
+#[cfg(all(unix, not(target_os = "macos")))]
+pub struct Xsel;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl ClipboardBackend for Xsel {
+    fn name(&self) -> &'static str {
+        "xsel"
+    }
+
+    fn paste_keys(&self) -> &'static str {
+        "Ctrl+V"
+    }
+
+    fn copy(&self, s: &SafeString) -> Result<(), ()> {
+        pipe_to_tool(&["xsel", "-ib"], s.deref().as_bytes())
+    }
+
+    fn paste(&self) -> Result<SafeString, ()> {
+        read_from_tool(&["xsel", "-ob"])
+    }
+
+    fn clear(&self) -> Result<(), ()> {
+        run_tool(&["xsel", "-cb"])
+    }
+}
+
+/// The X11 clipboard, through xclip
+#[cfg(all(unix, not(target_os = "macos")))]
+pub struct Xclip;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+impl ClipboardBackend for Xclip {
+    fn name(&self) -> &'static str {
+        "xclip"
+    }
+
+    fn paste_keys(&self) -> &'static str {
+        "Ctrl+V"
+    }
+
+    fn copy(&self, s: &SafeString) -> Result<(), ()> {
+        pipe_to_tool(&["xclip", "-selection", "clipboard"], s.deref().as_bytes())
+    }
+
+    fn paste(&self) -> Result<SafeString, ()> {
+        read_from_tool(&["xclip", "-selection", "clipboard", "-o"])
+    }
+
+    fn clear(&self) -> Result<(), ()> {
+        pipe_to_tool(&["xclip", "-selection", "clipboard"], b"")
+    }
+
+    fn supports_paste_once(&self) -> bool {
+        true
+    }
+
+    fn copy_once(&self, s: &SafeString) -> Result<(), ()> {
+        pipe_to_tool(
+            &["xclip", "-selection", "clipboard", "-loops", "1"],
+            s.deref().as_bytes(),
+        )
+    }
+}
+
+/// The paste buffer of the tmux session Rooster runs in
+#[cfg(unix)]
+pub struct Tmux;
+
+#[cfg(unix)]
+impl ClipboardBackend for Tmux {
+    fn name(&self) -> &'static str {
+        "tmux"
+    }
+
+    fn paste_keys(&self) -> &'static str {
+        "prefix + ]"
+    }
+
+    fn copy(&self, s: &SafeString) -> Result<(), ()> {
+        pipe_to_tool(&["tmux", "load-buffer", "-"], s.deref().as_bytes())
+    }
+
+    fn paste(&self) -> Result<SafeString, ()> {
+        read_from_tool(&["tmux", "show-buffer"])
+    }
+
+    fn clear(&self) -> Result<(), ()> {
+        run_tool(&["tmux", "delete-buffer"])
+    }
+}
+
+/// The clipboard of the terminal emulator, through the OSC 52 escape sequence
+///
+/// This works over SSH, since the sequence travels along with the rest of the output to the
+/// terminal on the user's machine. Terminals don't let programs read the clipboard back though,
+/// so passwords can't be cleared in the background.
+#[cfg(unix)]
+pub struct Osc52;
+
+#[cfg(unix)]
+impl Osc52 {
+    fn write_to_terminal(&self, sequence: &str) -> Result<(), ()> {
+        let mut tty = std::fs::OpenOptions::new()
+            .write(true)
+            .open("/dev/tty")
+            .map_err(|_| ())?;
+        tty.write_all(sequence.as_bytes()).map_err(|_| ())?;
+        tty.flush().map_err(|_| ())
+    }
+}
+
+/// Builds the escape sequence that puts `s` in the terminal's clipboard
+#[cfg(unix)]
+fn osc52_sequence(s: &str) -> SafeString {
+    SafeString::from_string(format!(
+        "\x1b]52;c;{}\x07",
+        openssl::base64::encode_block(s.as_bytes())
+    ))
+}
+
+/// Any payload that isn't valid base64 makes the terminal clear its clipboard
+#[cfg(unix)]
+const OSC52_CLEAR_SEQUENCE: &str = "\x1b]52;c;!\x07";
+
+#[cfg(unix)]
+impl ClipboardBackend for Osc52 {
+    fn name(&self) -> &'static str {
+        "osc52"
+    }
+
+    fn paste_keys(&self) -> &'static str {
+        "your terminal's paste shortcut"
+    }
+
+    fn copy(&self, s: &SafeString) -> Result<(), ()> {
+        self.write_to_terminal(osc52_sequence(s.deref()).deref())
+    }
+
+    fn paste(&self) -> Result<SafeString, ()> {
+        Err(())
+    }
+
+    fn clear(&self) -> Result<(), ()> {
+        self.write_to_terminal(OSC52_CLEAR_SEQUENCE)
+    }
+
+    fn can_clear_in_background(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::{by_name, osc52_sequence};
+    use std::ops::Deref;
+
+    #[test]
+    fn test_osc52_sequence() {
+        assert_eq!(
+            osc52_sequence("hunter2").deref(),
+            "\x1b]52;c;aHVudGVyMg==\x07"
+        );
+    }
+
+    #[test]
+    fn test_by_name() {
+        assert_eq!(by_name("tmux").unwrap().name(), "tmux");
+        assert_eq!(by_name("osc52").unwrap().name(), "osc52");
+        assert!(by_name("pbcopy").is_none());
+    }
+}
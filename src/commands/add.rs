@@ -1,7 +1,10 @@
-use crate::clip::{copy_to_clipboard, paste_keys};
+use crate::breach;
+use crate::clip::{copy_password_to_clipboard, password_saved_message};
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use crate::strength;
+use serde_json::json;
 use std::ops::Deref;
 
 pub fn callback_exec(
@@ -22,12 +25,18 @@ pub fn callback_exec(
 
     match io.prompt_password(format!("What password do you want for \"{}\"? ", app_name)) {
         Ok(password_as_string) => {
+            strength::report(&strength::estimate(password_as_string.deref()), io);
+            breach::warn_if_breached(password_as_string.deref(), io);
+
             let password_as_string_clipboard = password_as_string.clone();
             let password =
                 password::v2::Password::new(app_name.clone(), username, password_as_string);
+            let show = matches.is_present("show");
+            let entry = password.to_json(show);
             match store.add_password(password) {
                 Ok(_) => {
-                    if matches.is_present("show") {
+                    if show {
+                        io.result(json!({"entry": entry, "copied": false}));
                         io.success(
                             format!(
                                 "Alright! Here is your password: {}",
@@ -38,25 +47,23 @@ pub fn callback_exec(
                         return Ok(());
                     }
 
-                    if copy_to_clipboard(&password_as_string_clipboard).is_err() {
-                        io.success(
-                            format!(
-                                "Hmm, I tried to copy your new password to your clipboard, \
-                                 but something went wrong. Don't worry, it's saved, and you \
-                                 can see it with `rooster get {} --show`",
-                                app_name
-                            ),
-                            OutputType::Standard,
-                        );
-                    } else {
-                        io.success(
-                            format!(
-                                "Alright! I've saved your new password. You can paste it \
-                                 anywhere with {}.",
-                                paste_keys()
-                            ),
-                            OutputType::Standard,
-                        );
+                    let copied = copy_password_to_clipboard(&password_as_string_clipboard);
+                    io.result(json!({"entry": entry, "copied": copied.is_ok()}));
+                    match copied {
+                        Err(_) => {
+                            io.success(
+                                format!(
+                                    "Hmm, I tried to copy your new password to your clipboard, \
+                                     but something went wrong. Don't worry, it's saved, and you \
+                                     can see it with `rooster get {} --show`",
+                                    app_name
+                                ),
+                                OutputType::Standard,
+                            );
+                        }
+                        Ok(copied) => {
+                            io.success(password_saved_message(&copied), OutputType::Standard);
+                        }
                     }
                 }
                 Err(err) => {
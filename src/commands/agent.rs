@@ -0,0 +1,136 @@
+use crate::agent;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use serde_json::json;
+use std::fs::DirBuilder;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub fn callback_exec(matches: &clap::ArgMatches, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    let idle_timeout = match matches.value_of("timeout").unwrap().parse::<u64>() {
+        Ok(0) => None,
+        Ok(seconds) => Some(Duration::from_secs(seconds)),
+        Err(err) => {
+            io.error(
+                format!("Woops, the timeout must be a number of seconds ({}).", err),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    // Without an explicit socket, we use a private directory, like ssh-agent does.
+    let (socket, socket_dir) = match matches.value_of("socket") {
+        Some(socket) => (PathBuf::from(socket), None),
+        None => {
+            let dir = std::env::temp_dir().join(format!("rooster-agent-{}", std::process::id()));
+            DirBuilder::new().mode(0o700).create(&dir).map_err(|err| {
+                io.error(
+                    format!(
+                        "Woops, I couldn't create a directory for the agent socket (reason: {:?}).",
+                        err
+                    ),
+                    OutputType::Error,
+                );
+                1
+            })?;
+            (dir.join("agent.sock"), Some(dir))
+        }
+    };
+
+    let listener = agent::bind(&socket).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I couldn't listen on {} (reason: {:?}).",
+                socket.to_string_lossy(),
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    agent::disable_core_dumps();
+
+    if !matches.is_present("foreground") {
+        match unsafe { libc::fork() } {
+            -1 => {
+                io.error(
+                    format!(
+                        "Woops, I couldn't start the agent in the background (reason: {:?}).",
+                        std::io::Error::last_os_error()
+                    ),
+                    OutputType::Error,
+                );
+                cleanup(&socket, socket_dir.as_deref());
+                return Err(1);
+            }
+            0 => {
+                detach_from_terminal();
+                let result = agent::serve(&listener, idle_timeout);
+                cleanup(&socket, socket_dir.as_deref());
+                std::process::exit(if result.is_ok() { 0 } else { 1 });
+            }
+            pid => {
+                print_environment(&socket, Some(pid), io);
+                return Ok(());
+            }
+        }
+    }
+
+    print_environment(&socket, None, io);
+
+    let result = agent::serve(&listener, idle_timeout);
+    cleanup(&socket, socket_dir.as_deref());
+    result.map_err(|err| {
+        io.error(
+            format!("Woops, the agent stopped (reason: {:?}).", err),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
+/// Prints shell commands that point Rooster to the agent, to be used with `eval`
+fn print_environment(socket: &Path, pid: Option<libc::pid_t>, io: &mut impl CliInputOutput) {
+    io.result(json!({
+        "socket": socket.to_string_lossy(),
+        "pid": pid,
+    }));
+    io.writeln(
+        format!(
+            "{}={}; export {};",
+            agent::ROOSTER_AGENT_SOCK_ENV_VAR,
+            socket.to_string_lossy(),
+            agent::ROOSTER_AGENT_SOCK_ENV_VAR
+        ),
+        OutputType::Standard,
+    );
+    if let Some(pid) = pid {
+        io.writeln(format!("echo Agent pid {};", pid), OutputType::Standard);
+    }
+}
+
+fn detach_from_terminal() {
+    unsafe {
+        libc::setsid();
+
+        let dev_null = libc::open(b"/dev/null\0".as_ptr() as *const libc::c_char, libc::O_RDWR);
+        if dev_null >= 0 {
+            libc::dup2(dev_null, libc::STDIN_FILENO);
+            libc::dup2(dev_null, libc::STDOUT_FILENO);
+            libc::dup2(dev_null, libc::STDERR_FILENO);
+            if dev_null > libc::STDERR_FILENO {
+                libc::close(dev_null);
+            }
+        }
+    }
+}
+
+fn cleanup(socket: &Path, socket_dir: Option<&Path>) {
+    let _ = std::fs::remove_file(socket);
+    if let Some(dir) = socket_dir {
+        let _ = std::fs::remove_dir(dir);
+    }
+}
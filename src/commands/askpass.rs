@@ -0,0 +1,77 @@
+use crate::approval;
+use crate::password;
+use crate::prompt_pattern;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::rutil::safe_string::SafeString;
+use serde_json::json;
+use std::ops::Deref;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let rules = prompt_pattern::configured_rules().map_err(|rule| {
+        io.error(
+            format!(
+                "Woops, \"{}\" in {} should look like PATTERN => APP, for instance alice@db01's password => db01.",
+                rule, prompt_pattern::ROOSTER_ASKPASS_PATTERNS_ENV_VAR
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    // Questions like "Are you sure you want to continue connecting (yes/no)?" go through the same
+    // program. Not answering them is the safe thing to do, so there are no patterns for them.
+    let prompt = matches.value_of("prompt").unwrap();
+    let (rule, name) = match prompt_pattern::app_name(&rules, prompt) {
+        Some(matched) => matched,
+        None => {
+            io.error(
+                format!(
+                    "Woops, I don't know which app \"{}\" is for. You can tell me with {}.",
+                    prompt.trim(),
+                    prompt_pattern::ROOSTER_ASKPASS_PATTERNS_ENV_VAR
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    let password = match store.get_password(&name) {
+        Some(password) => password,
+        None => {
+            io.error(
+                format!("Woops, I can't find an app named \"{}\".", name),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    // Whoever printed the prompt chose the app, so the user has the last word.
+    if rule.names_app_from_prompt()
+        && !approval::approve(&format!(
+            "Give the password of {} to \"{}\"?",
+            password.name,
+            prompt.trim()
+        ))
+    {
+        io.error(
+            format!(
+                "Woops, you didn't let me give the password of {}.",
+                password.name
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    io.result(json!({ "entry": password.to_json(false) }));
+    let reply = SafeString::from_string(format!("{}\n", password.password.deref()));
+    io.write(reply.deref(), OutputType::Standard);
+    Ok(())
+}
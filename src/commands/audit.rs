@@ -0,0 +1,308 @@
+use crate::breach::HibpFile;
+use crate::ffi;
+use crate::password;
+use crate::password::v2::Password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::strength;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::ops::Deref;
+use std::path::Path;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+#[derive(Serialize)]
+pub struct Issue {
+    severity: Severity,
+    app: String,
+    username: String,
+    problem: &'static str,
+    details: String,
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    match matches.subcommand() {
+        Some(("breaches", subcommand_matches)) => audit_breaches(subcommand_matches, store, io),
+        _ => audit_report(matches, store, io),
+    }
+}
+
+fn open_hibp_file(path: &str, io: &mut impl CliInputOutput) -> Result<HibpFile, i32> {
+    HibpFile::open(Path::new(path)).map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't open {} (reason: {}).", path, err),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
+/// Returns the passwords that appear in the Pwned Passwords file, with how many times they do
+fn find_breached<'a>(
+    hibp: &mut HibpFile,
+    path: &str,
+    passwords: &[&'a Password],
+    io: &mut impl CliInputOutput,
+) -> Result<Vec<(&'a Password, u64)>, i32> {
+    let mut breached = Vec::new();
+    for password in passwords.iter() {
+        match hibp.count(password.password.deref()) {
+            Ok(Some(count)) => breached.push((*password, count)),
+            Ok(None) => {}
+            Err(err) => {
+                io.error(
+                    format!("Woops, I couldn't search {} (reason: {}).", path, err),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        }
+    }
+    Ok(breached)
+}
+
+fn audit_breaches(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let path = matches.value_of("hibp-file").unwrap();
+    let mut hibp = open_hibp_file(path, io)?;
+    let breached = find_breached(&mut hibp, path, &store.get_all_passwords(), io)?;
+    io.result(json!({
+        "breached": breached
+            .iter()
+            .map(|(password, count)| json!({
+                "entry": password.to_json(false),
+                "count": count,
+            }))
+            .collect::<Vec<_>>()
+    }));
+
+    if breached.is_empty() {
+        io.success(
+            "None of your passwords appear in data breaches.",
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    io.warning(
+        format!(
+            "{} of your passwords appeared in data breaches, you should change them:",
+            breached.len()
+        ),
+        OutputType::Standard,
+    );
+    for (password, count) in breached {
+        io.info(
+            format!(
+                "{} ({}): seen {} times",
+                password.name, password.username, count
+            ),
+            OutputType::Standard,
+        );
+    }
+    Err(1)
+}
+
+fn issue(severity: Severity, password: &Password, problem: &'static str, details: String) -> Issue {
+    Issue {
+        severity,
+        app: password.name.clone(),
+        username: password.username.clone(),
+        problem,
+        details,
+    }
+}
+
+fn audit_report(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let min_score = matches
+        .value_of("min-score")
+        .and_then(|score| score.parse::<u8>().ok())
+        .unwrap();
+    let max_age_days = matches
+        .value_of("max-age")
+        .and_then(|days| days.parse::<u64>().ok())
+        .unwrap();
+    let passwords = store.get_all_passwords();
+    let mut issues = Vec::new();
+
+    if let Some(path) = matches.value_of("hibp-file") {
+        let mut hibp = open_hibp_file(path, io)?;
+        for (password, count) in find_breached(&mut hibp, path, &passwords, io)? {
+            issues.push(issue(
+                Severity::Critical,
+                password,
+                "breached",
+                format!("seen {} times in data breaches", count),
+            ));
+        }
+    }
+
+    let mut apps_by_password: HashMap<&str, Vec<&str>> = HashMap::new();
+    for password in passwords.iter() {
+        apps_by_password
+            .entry(password.password.deref())
+            .or_default()
+            .push(password.name.as_str());
+    }
+
+    let now = ffi::time() as u64;
+    for password in passwords.iter() {
+        let others: Vec<&str> = apps_by_password[password.password.deref().as_str()]
+            .iter()
+            .cloned()
+            .filter(|name| *name != password.name)
+            .collect();
+        if !others.is_empty() {
+            issues.push(issue(
+                Severity::High,
+                password,
+                "reused",
+                format!("same password as {}", others.join(", ")),
+            ));
+        }
+
+        let estimate = strength::estimate(password.password.deref());
+        if estimate.score < min_score {
+            issues.push(issue(
+                if estimate.score <= 1 {
+                    Severity::High
+                } else {
+                    Severity::Medium
+                },
+                password,
+                "weak",
+                format!(
+                    "{} ({}/4){}",
+                    estimate.describe_score(),
+                    estimate.score,
+                    match estimate.warning() {
+                        Some(warning) => format!(", {}", warning),
+                        None => String::new(),
+                    }
+                ),
+            ));
+        }
+
+        let age_days = now.saturating_sub(password.updated_at as u64) / 86400;
+        if max_age_days > 0 && age_days >= max_age_days {
+            issues.push(issue(
+                Severity::Low,
+                password,
+                "old",
+                format!("not changed in {} days", age_days),
+            ));
+        }
+
+        if password.username.trim().is_empty() {
+            issues.push(issue(
+                Severity::Low,
+                password,
+                "no-username",
+                "the username is empty".to_owned(),
+            ));
+        }
+    }
+
+    issues.sort_by(|a, b| {
+        b.severity
+            .cmp(&a.severity)
+            .then_with(|| a.app.to_lowercase().cmp(&b.app.to_lowercase()))
+    });
+    io.result(json!({ "issues": issues }));
+
+    if issues.is_empty() {
+        io.success(
+            format!("All good! I checked {} passwords.", passwords.len()),
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    print_table(&issues, io);
+    io.nl(OutputType::Standard);
+    io.warning(
+        format!(
+            "I found {} problems in {} passwords.",
+            issues.len(),
+            passwords.len()
+        ),
+        OutputType::Standard,
+    );
+    Err(1)
+}
+
+fn print_table(issues: &[Issue], io: &mut impl CliInputOutput) {
+    let severities: Vec<String> = issues
+        .iter()
+        .map(|issue| format!("{:?}", issue.severity).to_uppercase())
+        .collect();
+    let apps: Vec<String> = issues
+        .iter()
+        .map(|issue| match issue.username.as_str() {
+            "" => issue.app.clone(),
+            username => format!("{} ({})", issue.app, username),
+        })
+        .collect();
+
+    let severity_width = severities.iter().map(|s| s.len()).max().unwrap_or(0).max(8);
+    let app_width = apps
+        .iter()
+        .map(|s| s.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(3);
+    let problem_width = issues
+        .iter()
+        .map(|issue| issue.problem.len())
+        .max()
+        .unwrap_or(0)
+        .max(7);
+
+    io.title(
+        format!(
+            "{:severity_width$}  {:app_width$}  {:problem_width$}  DETAILS",
+            "SEVERITY",
+            "APP",
+            "PROBLEM",
+            severity_width = severity_width,
+            app_width = app_width,
+            problem_width = problem_width,
+        ),
+        OutputType::Standard,
+    );
+    for (i, issue) in issues.iter().enumerate() {
+        io.info(
+            format!(
+                "{:severity_width$}  {:app_width$}  {:problem_width$}  {}",
+                severities[i],
+                apps[i],
+                issue.problem,
+                issue.details,
+                severity_width = severity_width,
+                app_width = app_width,
+                problem_width = problem_width,
+            ),
+            OutputType::Standard,
+        );
+    }
+}
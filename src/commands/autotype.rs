@@ -0,0 +1,81 @@
+use crate::autotype;
+use crate::list;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use serde_json::json;
+use std::time::Duration;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.value_of("app").unwrap();
+    let delay = matches
+        .value_of("delay")
+        .unwrap()
+        .parse::<u64>()
+        .map_err(|_| 1)?;
+
+    let typist = match autotype::typist_from_env() {
+        Some(typist) => typist,
+        None => {
+            io.error(
+                "Woops, I need xdotool (X11) or ydotool (Wayland) to type passwords.",
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::Selection::from_matches(matches),
+        list::WITH_NUMBERS,
+        "Which password would you like to type? ",
+        io,
+    )
+    .ok_or(1)?;
+
+    let sequence = password
+        .fields
+        .get(autotype::AUTOTYPE_FIELD)
+        .map(|sequence| sequence.as_str())
+        .unwrap_or(autotype::DEFAULT_SEQUENCE);
+    let keystrokes = autotype::parse_sequence(sequence, password).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, the auto-type sequence for {} is invalid: {}.",
+                password.name, err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    io.info(
+        format!(
+            "I'll type your password for {} in {} seconds, click where it should go.",
+            password.name, delay
+        ),
+        OutputType::Standard,
+    );
+    std::thread::sleep(Duration::from_secs(delay));
+
+    if autotype::type_keystrokes(typist.as_ref(), &keystrokes).is_err() {
+        io.error(
+            "Woops, something went wrong while typing your password.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    io.result(json!({ "entry": password.to_json(false) }));
+    io.success(
+        format!("Done! I've typed your password for {}.", password.name),
+        OutputType::Standard,
+    );
+    Ok(())
+}
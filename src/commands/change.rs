@@ -1,9 +1,12 @@
+use crate::breach;
 use crate::clip;
 use crate::ffi;
 use crate::list;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use crate::strength;
+use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
@@ -15,6 +18,7 @@ pub fn callback_exec(
     let password = list::search_and_choose_password(
         store,
         query,
+        list::Selection::from_matches(matches),
         list::WITH_NUMBERS,
         "Which password would like to update?",
         io,
@@ -35,6 +39,9 @@ pub fn callback_exec(
             1
         })?;
 
+    strength::report(&strength::estimate(password_as_string.deref()), io);
+    breach::warn_if_breached(password_as_string.deref(), io);
+
     let password = store
         .change_password(&password.name, &|old_password: password::v2::Password| {
             password::v2::Password {
@@ -43,6 +50,9 @@ pub fn callback_exec(
                 password: password_as_string.clone(),
                 created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                fields: old_password.fields,
+                rotation_days: old_password.rotation_days,
+                expires_at: old_password.expires_at,
             }
         })
         .map_err(|err| {
@@ -0,0 +1,27 @@
+use crate::clip;
+use crate::rclio::CliInputOutput;
+use std::ops::Deref;
+use std::time::Duration;
+
+/// Clears the clipboard after a while, if it still contains the password we put there
+///
+/// A digest of the password is passed on stdin, see `clip::copy_password_to_clipboard`.
+pub fn callback_exec(matches: &clap::ArgMatches, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    let seconds = matches
+        .value_of("seconds")
+        .unwrap()
+        .parse::<u64>()
+        .map_err(|_| 1)?;
+
+    let input = io.read_stdin().map_err(|_| 1)?;
+
+    std::thread::sleep(Duration::from_secs(seconds));
+
+    // If something else was copied in the meantime, the user wants to keep it.
+    let contents = clip::paste_from_clipboard().map_err(|_| 1)?;
+    if !clip::still_copied(&input, contents.deref()) {
+        return Ok(());
+    }
+
+    clip::clear_clipboard().map_err(|_| 1)
+}
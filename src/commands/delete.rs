@@ -2,6 +2,7 @@ use crate::list;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use serde_json::json;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
@@ -13,6 +14,7 @@ pub fn callback_exec(
     let password = list::search_and_choose_password(
         store,
         query,
+        list::Selection::from_matches(matches),
         list::WITH_NUMBERS,
         "Which password would you like me to delete?",
         io,
@@ -31,6 +33,7 @@ pub fn callback_exec(
         return Err(1);
     }
 
+    io.result(json!({ "entry": password.to_json(false) }));
     io.success(
         format!("Done! I've deleted the password for \"{}\".", password.name),
         OutputType::Standard,
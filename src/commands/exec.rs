@@ -0,0 +1,314 @@
+use crate::password;
+use crate::password::v2::PasswordStore;
+use crate::prompt_pattern;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::rutil::pty;
+use crate::rutil::safe_string::SafeString;
+use serde_json::json;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// How long to wait for a program that printed a password prompt to turn off echo
+const ECHO_CHECK_INTERVAL_MS: libc::c_int = 50;
+
+/// Only the end of the output can be a prompt, there's no need to keep more than this
+const MAX_LINE_LEN: usize = 1024;
+
+static WINDOW_RESIZED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_window_resized(_: libc::c_int) {
+    WINDOW_RESIZED.store(true, Ordering::SeqCst);
+}
+
+/// What we do with the output of the program, until we have typed a password
+enum Watch {
+    /// Waiting for a prompt that matches a pattern
+    Prompt,
+    /// A prompt for this app matched, waiting for the program to turn echo off so that its
+    /// password isn't shown
+    Echo(String, SafeString),
+    /// The terminal belongs to the user now
+    Done,
+}
+
+/// Spawns the command with the slave side of the pseudo-terminal as its terminal
+fn spawn_in_pty(command: &[&str], slave: File) -> io::Result<Child> {
+    let mut child = Command::new(command[0]);
+    child
+        .args(&command[1..])
+        .stdin(Stdio::from(slave.try_clone()?))
+        .stdout(Stdio::from(slave.try_clone()?))
+        .stderr(Stdio::from(slave));
+    unsafe {
+        child.pre_exec(pty::make_controlling_terminal);
+    }
+    child.spawn()
+}
+
+/// Keeps the last line of the output, which is where prompts are
+fn push_output(line: &mut Vec<u8>, output: &[u8]) {
+    match output.iter().rposition(|&b| b == b'\n' || b == b'\r') {
+        Some(i) => {
+            line.clear();
+            line.extend_from_slice(&output[i + 1..]);
+        }
+        None => line.extend_from_slice(output),
+    }
+    if line.len() > MAX_LINE_LEN {
+        line.drain(..line.len() - MAX_LINE_LEN);
+    }
+}
+
+/// What to do next, given the last line of the output
+fn watch_prompt(
+    store: &PasswordStore,
+    rules: &[prompt_pattern::Rule],
+    line: &[u8],
+    io: &mut impl CliInputOutput,
+) -> Watch {
+    let name = match prompt_pattern::app_name(rules, &String::from_utf8_lossy(line)) {
+        Some((_, name)) => name,
+        None => return Watch::Prompt,
+    };
+    match store.get_password(&name) {
+        Some(password) => Watch::Echo(name, password.password),
+        None => {
+            io.nl(OutputType::Error);
+            io.warning(
+                format!("I can't find an app named \"{}\", over to you.", name),
+                OutputType::Error,
+            );
+            Watch::Done
+        }
+    }
+}
+
+/// Copies between our terminal and the program's until the program is done, typing the password
+/// at the first prompt that matches
+fn relay(
+    master: &mut File,
+    store: &PasswordStore,
+    rules: &[prompt_pattern::Rule],
+    io: &mut impl CliInputOutput,
+) -> io::Result<Option<String>> {
+    let stdin = libc::STDIN_FILENO;
+    let mut stdin_open = true;
+    let mut line = Vec::new();
+    let mut watch = Watch::Prompt;
+    let mut answered = None;
+    let mut buffer = [0u8; 4096];
+
+    loop {
+        if WINDOW_RESIZED.swap(false, Ordering::SeqCst) {
+            let _ = pty::copy_window_size(stdin, master);
+        }
+
+        if let Watch::Echo(ref name, ref password) = watch {
+            if !pty::echo_enabled(master)? {
+                master.write_all(password.as_bytes())?;
+                master.write_all(b"\r")?;
+                answered = Some(name.clone());
+                watch = Watch::Done;
+            }
+        }
+
+        let mut fds = [
+            libc::pollfd {
+                fd: master.as_raw_fd(),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+            libc::pollfd {
+                // Negative file descriptors are ignored
+                fd: if stdin_open { stdin } else { -1 },
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        let timeout = match watch {
+            Watch::Echo(_, _) => ECHO_CHECK_INTERVAL_MS,
+            _ => -1,
+        };
+        if unsafe { libc::poll(fds.as_mut_ptr(), fds.len() as libc::nfds_t, timeout) } < 0 {
+            let err = io::Error::last_os_error();
+            if err.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(err);
+        }
+
+        if fds[0].revents != 0 {
+            let len = match master.read(&mut buffer) {
+                Ok(len) => len,
+                // The program and everything it started are done with the terminal.
+                Err(ref err) if err.raw_os_error() == Some(libc::EIO) => 0,
+                Err(err) => return Err(err),
+            };
+            if len == 0 {
+                return Ok(answered);
+            }
+            let stdout = io::stdout();
+            let mut stdout = stdout.lock();
+            stdout.write_all(&buffer[..len])?;
+            stdout.flush()?;
+
+            if let Watch::Prompt | Watch::Echo(_, _) = watch {
+                push_output(&mut line, &buffer[..len]);
+                watch = watch_prompt(store, rules, &line, io);
+            }
+        }
+
+        if fds[1].revents != 0 {
+            let len = unsafe {
+                libc::read(
+                    stdin,
+                    buffer.as_mut_ptr() as *mut libc::c_void,
+                    buffer.len(),
+                )
+            };
+            if len <= 0 {
+                stdin_open = false;
+            } else {
+                master.write_all(&buffer[..len as usize])?;
+            }
+        }
+    }
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    // Only the patterns given here apply, and they name their app: the program chooses what its
+    // prompts say, and a remote host could otherwise pick which password gets typed.
+    let mut rules = Vec::new();
+    for answer in matches.values_of("answer").into_iter().flatten() {
+        let parsed = prompt_pattern::parse_rules(answer).map_err(|rule| {
+            io.error(
+                format!(
+                    "Woops, \"{}\" should look like PATTERN => APP, for instance \"Enter password => prod-db\".",
+                    rule
+                ),
+                OutputType::Error,
+            );
+            1
+        })?;
+        if parsed
+            .iter()
+            .any(prompt_pattern::Rule::names_app_from_prompt)
+        {
+            io.error(
+                format!(
+                    "Woops, \"{}\" names the app after the prompt. Name the app itself, for instance \"{{user}}@db01's password => db01\".",
+                    answer.trim()
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+        rules.extend(parsed);
+    }
+
+    let command: Vec<&str> = matches.values_of("command").unwrap().collect();
+    let (mut master, slave) = pty::open_pty().map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I couldn't open a pseudo-terminal (reason: {}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    // Without a terminal of our own, keys come from a pipe and there's nothing to resize.
+    let stdin_is_tty = unsafe { libc::isatty(libc::STDIN_FILENO) } == 1;
+    if stdin_is_tty {
+        let _ = pty::copy_window_size(libc::STDIN_FILENO, &master);
+    }
+
+    let mut child = spawn_in_pty(&command, slave).map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't run {} (reason: {}).", command[0], err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    let raw_mode = if stdin_is_tty {
+        pty::RawMode::enable(libc::STDIN_FILENO).ok()
+    } else {
+        None
+    };
+    let previous_handler = unsafe {
+        libc::signal(
+            libc::SIGWINCH,
+            on_window_resized as extern "C" fn(libc::c_int) as libc::sighandler_t,
+        )
+    };
+    let answered = relay(&mut master, store, &rules, io);
+    unsafe {
+        libc::signal(libc::SIGWINCH, previous_handler);
+    }
+    drop(raw_mode);
+
+    // Closing our side hangs up the terminal, in case the program is still running.
+    drop(master);
+    let status = child.wait();
+
+    let answered = answered.map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I lost the terminal of {} (reason: {}).",
+                command[0], err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+    let status = status.map_err(|err| {
+        io.error(
+            format!("Woops, I lost track of {} (reason: {}).", command[0], err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    // Like shells do, a child killed by a signal exits with 128 + the signal number.
+    let exit_code = status
+        .code()
+        .unwrap_or_else(|| 128 + status.signal().unwrap_or(0));
+    io.result(json!({
+        "answered": answered,
+        "exit_code": exit_code,
+    }));
+
+    match exit_code {
+        0 => Ok(()),
+        code => Err(code),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::push_output;
+
+    #[test]
+    fn test_push_output() {
+        let mut line = Vec::new();
+        push_output(&mut line, b"Welcome!\r\nEnter pass");
+        assert_eq!(line, b"Enter pass");
+        push_output(&mut line, b"word: ");
+        assert_eq!(line, b"Enter password: ");
+        push_output(&mut line, b"\r\n");
+        assert_eq!(line, b"");
+        push_output(&mut line, &[b'x'; 2000]);
+        assert_eq!(line.len(), 1024);
+    }
+}
@@ -0,0 +1,106 @@
+use crate::expiry;
+use crate::ffi;
+use crate::list;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use serde_json::json;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.value_of("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::Selection::from_matches(matches),
+        list::WITH_NUMBERS,
+        "Which password would you like to see or change the expiry of? ",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    let never = matches.is_present("never");
+    let rotation_days = matches
+        .value_of("every")
+        .and_then(|days| days.parse::<u32>().ok());
+    let expires_at = match matches.value_of("on") {
+        Some(date) => match expiry::parse_date(date) {
+            Some(expires_at) => Some(expires_at as ffi::time_t),
+            None => {
+                io.error(
+                    format!(
+                        "Woops, \"{}\" is not a valid date. Dates look like 2030-01-31.",
+                        date
+                    ),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        },
+        None => None,
+    };
+
+    if !never && rotation_days.is_none() && expires_at.is_none() {
+        io.result(json!({ "entry": password.to_json(false) }));
+        match expiry::describe_policy(&password) {
+            Some(policy) => io.info(
+                format!("{} must be {}.", password.name, policy),
+                OutputType::Standard,
+            ),
+            None => io.info(
+                format!("{} doesn't expire.", password.name),
+                OutputType::Standard,
+            ),
+        }
+        return Ok(());
+    }
+
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            password::v2::Password {
+                name: old_password.name.clone(),
+                username: old_password.username.clone(),
+                password: old_password.password.clone(),
+                created_at: old_password.created_at,
+                // The password itself didn't change, so its rotation date doesn't either.
+                updated_at: old_password.updated_at,
+                fields: old_password.fields.clone(),
+                rotation_days: if never {
+                    None
+                } else {
+                    rotation_days.or(old_password.rotation_days)
+                },
+                expires_at: if never {
+                    None
+                } else {
+                    expires_at.or(old_password.expires_at)
+                },
+            }
+        });
+
+    match change_result {
+        Ok(password) => {
+            io.result(json!({ "entry": password.to_json(false) }));
+            io.success(
+                match expiry::describe_policy(&password) {
+                    Some(policy) => format!("Done! {} must now be {}.", password.name, policy),
+                    None => format!("Done! {} doesn't expire anymore.", password.name),
+                },
+                OutputType::Standard,
+            );
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't save the expiry (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
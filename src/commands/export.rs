@@ -2,12 +2,19 @@ use crate::password;
 use crate::password::v2::Password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use crate::rutil::private_file::write_private_file;
 use crate::rutil::safe_string::SafeString;
+use crate::search::Query;
 use csv::Writer;
 use serde::{Deserialize, Serialize};
 use serde_json;
+use serde_json::json;
+use std::collections::HashMap;
+use std::fs::DirBuilder;
 use std::io::Cursor;
 use std::ops::Deref;
+use std::os::unix::fs::DirBuilderExt;
+use std::path::Path;
 
 #[derive(Serialize, Deserialize)]
 pub struct JsonExport {
@@ -28,6 +35,12 @@ pub fn callback_exec(
         export_to_csv(subcommand_matches, store, io)
     } else if subcommand_name == "1password" {
         export_to_csv(subcommand_matches, store, io)
+    } else if subcommand_name == "env" {
+        export_to_env(subcommand_matches, store, io)
+    } else if subcommand_name == "k8s-secret" {
+        export_to_k8s_secret(subcommand_matches, store, io)
+    } else if subcommand_name == "systemd-creds" {
+        export_to_systemd_creds(subcommand_matches, store, io)
     } else {
         unimplemented!("Invalid export destination")
     }
@@ -51,10 +64,9 @@ fn export_to_csv(
             Err(_) => return Err(1),
         }
     }
-    io.write(
-        String::from_utf8(csv_writer.into_inner().unwrap().into_inner()).unwrap(),
-        OutputType::Standard,
-    );
+    let csv = String::from_utf8(csv_writer.into_inner().unwrap().into_inner()).unwrap();
+    io.result(json!({ "format": "csv", "content": csv }));
+    io.write(csv, OutputType::Standard);
 
     return Ok(());
 }
@@ -86,6 +98,286 @@ fn export_to_json(
     };
 
     let passwords = SafeString::from_string(passwords_json);
+    io.result(json!({ "format": "json", "content": passwords.deref() }));
     io.write(format!("{}", passwords.deref()), OutputType::Standard);
     return Ok(());
 }
+
+/// Name of the variable, Kubernetes key or systemd credential for a field of an app, like
+/// `PROD_DB_PASSWORD` for the password of "prod-db"
+fn credential_name(app: &str, field: &str) -> String {
+    let mut name: String = format!("{}_{}", app, field)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
+
+/// The username and password of the apps matching `--query`, or of all apps, by name
+///
+/// Unlike searches, queries don't match names fuzzily: exporting "db" must not export "Dropbox".
+fn credentials(
+    matches: &clap::ArgMatches,
+    store: &password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<Vec<(String, SafeString)>, i32> {
+    let mut passwords = store.get_all_passwords();
+    if let Some(query) = matches.value_of("query") {
+        let query = Query::parse(query);
+        passwords.retain(|password| query.matches_name_prefix(password));
+    }
+    passwords.sort_by_key(|password| password.name.to_lowercase());
+
+    let mut credentials: Vec<(String, SafeString)> = Vec::new();
+    let mut apps: HashMap<String, &str> = HashMap::new();
+    for password in passwords {
+        for field in ["username", "password"].iter() {
+            let name = credential_name(&password.name, field);
+            if let Some(other_app) = apps.insert(name.clone(), &password.name) {
+                io.error(
+                    format!(
+                        "Woops, {} and {} would both be exported as {}. Rename one of them or use --query.",
+                        other_app, password.name, name
+                    ),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+            credentials.push((name, password.field_value(field).unwrap()));
+        }
+    }
+    Ok(credentials)
+}
+
+/// Quotes a value for a `.env` file, in single quotes
+///
+/// Shells, Docker Compose and dotenv libraries don't agree on escapes in double quotes, but they
+/// all take what's between single quotes as is. There is no way to put a single quote or a line
+/// break there that they all read the same, so values with those can't be quoted.
+fn dotenv_quote(value: &str) -> Option<String> {
+    if value.contains(&['\'', '\n', '\r'][..]) {
+        return None;
+    }
+    Some(format!("'{}'", value))
+}
+
+fn export_to_env(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let mut env = SafeString::new();
+    for (name, value) in credentials(matches, store, io)? {
+        let quoted = match dotenv_quote(value.deref()) {
+            Some(quoted) => SafeString::from_string(quoted),
+            None => {
+                io.error(
+                    format!(
+                        "Woops, {} has a single quote or a line break, which programs don't read the same way in .env files. Try another format, like k8s-secret or systemd-creds.",
+                        name
+                    ),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        };
+        let line = SafeString::from_string(format!("{}={}\n", name, quoted.deref()));
+        env.push_str(line.deref());
+    }
+
+    io.result(json!({ "format": "env", "content": env.deref() }));
+    io.write(env.deref(), OutputType::Standard);
+    Ok(())
+}
+
+/// Whether this is a valid Kubernetes object name, which is a DNS subdomain, or a valid
+/// namespace, which is a single DNS label
+fn is_dns_name(name: &str, allow_dots: bool) -> bool {
+    if name.is_empty() || name.len() > 253 || (!allow_dots && name.contains('.')) {
+        return false;
+    }
+    name.split('.').all(|label| {
+        !label.is_empty()
+            && label.len() <= 63
+            && label
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+    })
+}
+
+fn export_to_k8s_secret(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let name = matches.value_of("name").unwrap();
+    if !is_dns_name(name, true) {
+        io.error(
+            format!(
+                "Woops, \"{}\" isn't a valid Kubernetes name. Use lowercase letters, digits, - and . only.",
+                name
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+    let namespace = matches.value_of("namespace");
+    if let Some(namespace) = namespace {
+        if !is_dns_name(namespace, false) {
+            io.error(
+                format!(
+                    "Woops, \"{}\" isn't a valid Kubernetes namespace. Use lowercase letters, digits and - only.",
+                    namespace
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    }
+    let credentials = credentials(matches, store, io)?;
+
+    // Names are checked and keys are made of safe characters, so they need no YAML quoting.
+    let mut manifest = SafeString::from_string(format!(
+        "apiVersion: v1\nkind: Secret\nmetadata:\n  name: {}\n",
+        name
+    ));
+    if let Some(namespace) = namespace {
+        manifest.push_str(&format!("  namespace: {}\n", namespace));
+    }
+    manifest.push_str("type: Opaque\ndata:");
+    if credentials.is_empty() {
+        manifest.push_str(" {}");
+    }
+    manifest.push('\n');
+    for (key, value) in credentials {
+        let line = SafeString::from_string(format!(
+            "  {}: {}\n",
+            key,
+            openssl::base64::encode_block(value.as_bytes())
+        ));
+        manifest.push_str(line.deref());
+    }
+
+    io.result(json!({ "format": "k8s-secret", "content": manifest.deref() }));
+    io.write(manifest.deref(), OutputType::Standard);
+    Ok(())
+}
+
+fn export_to_systemd_creds(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let directory = Path::new(matches.value_of("directory").unwrap());
+    let credentials = credentials(matches, store, io)?;
+
+    if let Err(err) = DirBuilder::new().mode(0o700).create(directory) {
+        if err.kind() != std::io::ErrorKind::AlreadyExists {
+            io.error(
+                format!(
+                    "Woops, I couldn't create {} (reason: {}).",
+                    directory.to_string_lossy(),
+                    err
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    }
+
+    for (name, value) in credentials.iter() {
+        let path = directory.join(name);
+        if let Err(err) = write_private_file(&path, value.deref()) {
+            io.error(
+                format!(
+                    "Woops, I couldn't write {} (reason: {}).",
+                    path.to_string_lossy(),
+                    err
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    }
+
+    io.result(json!({
+        "format": "systemd-creds",
+        "directory": directory.to_string_lossy(),
+        "credentials": credentials.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+    }));
+    io.success(
+        format!(
+            "Done! I've written {} credentials in {}, one file each.",
+            credentials.len(),
+            directory.to_string_lossy()
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{credential_name, dotenv_quote, is_dns_name};
+
+    #[test]
+    fn test_credential_name() {
+        assert_eq!(credential_name("prod-db", "password"), "PROD_DB_PASSWORD");
+        assert_eq!(
+            credential_name("Gigahertz Shop", "username"),
+            "GIGAHERTZ_SHOP_USERNAME"
+        );
+        assert_eq!(
+            credential_name("1password.com", "password"),
+            "_1PASSWORD_COM_PASSWORD"
+        );
+    }
+
+    /// Reads a variable back the way shells do
+    fn source(line: &str) -> String {
+        let output = std::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("{}\nprintf %s \"$VALUE\"", line))
+            .output()
+            .unwrap();
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    #[test]
+    fn test_dotenv_quote() {
+        assert_eq!(dotenv_quote("abcd"), Some("'abcd'".to_owned()));
+        for value in [
+            "a$b", "$(id)", "${HOME}", "a\"b", "a\\b\\", "`id`", "a b\tc",
+        ]
+        .iter()
+        {
+            let quoted = dotenv_quote(value).unwrap();
+            assert_eq!(source(&format!("VALUE={}", quoted)), *value);
+        }
+        assert_eq!(dotenv_quote("a'b"), None);
+        assert_eq!(dotenv_quote("a\nb"), None);
+        assert_eq!(dotenv_quote("a\rb"), None);
+    }
+
+    #[test]
+    fn test_is_dns_name() {
+        assert!(is_dns_name("app-creds", true));
+        assert!(is_dns_name("app.creds", true));
+        assert!(!is_dns_name("app.creds", false));
+        assert!(!is_dns_name("App", true));
+        assert!(!is_dns_name("-app", true));
+        assert!(!is_dns_name("app..creds", true));
+        assert!(!is_dns_name("", true));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::list;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use serde_json::json;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let query = matches.value_of("app").unwrap();
+
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::Selection::from_matches(matches),
+        list::WITH_NUMBERS,
+        "Which password would you like to see or change the fields of? ",
+        io,
+    )
+    .ok_or(1)?
+    .clone();
+
+    io.result(json!({ "entry": password.to_json(false) }));
+
+    let name = match matches.value_of("name") {
+        Some(name) => name.to_owned(),
+        None => {
+            if password.fields.is_empty() {
+                io.info(
+                    format!("{} doesn't have any fields yet.", password.name),
+                    OutputType::Standard,
+                );
+            }
+            for (name, value) in password.fields.iter() {
+                io.info(format!("{}: {}", name, value), OutputType::Standard);
+            }
+            return Ok(());
+        }
+    };
+
+    let value = match (matches.value_of("value"), matches.is_present("unset")) {
+        (Some(value), _) => Some(value.to_owned()),
+        (None, true) => None,
+        (None, false) => {
+            return match password.fields.get(&name) {
+                Some(value) => {
+                    io.info(value, OutputType::Standard);
+                    Ok(())
+                }
+                None => {
+                    io.error(
+                        format!(
+                            "Woops, {} doesn't have a \"{}\" field.",
+                            password.name, name
+                        ),
+                        OutputType::Error,
+                    );
+                    Err(1)
+                }
+            };
+        }
+    };
+
+    let change_result =
+        store.change_password(&password.name, &|old_password: password::v2::Password| {
+            let mut fields = old_password.fields.clone();
+            match value {
+                Some(ref value) => fields.insert(name.clone(), value.clone()),
+                None => fields.remove(&name),
+            };
+            password::v2::Password {
+                name: old_password.name.clone(),
+                username: old_password.username.clone(),
+                password: old_password.password.clone(),
+                created_at: old_password.created_at,
+                // Fields aren't the password, which is what stale and audit check the age of
+                updated_at: old_password.updated_at,
+                fields,
+                rotation_days: old_password.rotation_days,
+                expires_at: old_password.expires_at,
+            }
+        });
+
+    match change_result {
+        Ok(password) => {
+            io.result(json!({ "entry": password.to_json(false) }));
+            io.success(
+                match value {
+                    Some(_) => format!(
+                        "Done! I've set the \"{}\" field of {}.",
+                        name, password.name
+                    ),
+                    None => format!(
+                        "Done! I've removed the \"{}\" field of {}.",
+                        name, password.name
+                    ),
+                },
+                OutputType::Standard,
+            );
+            Ok(())
+        }
+        Err(err) => {
+            io.error(
+                format!("Woops, I couldn't save the field (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            Err(1)
+        }
+    }
+}
@@ -0,0 +1,32 @@
+use crate::generate::Generator;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use serde_json::json;
+
+pub fn callback_exec(matches: &clap::ArgMatches, io: &mut impl CliInputOutput) -> Result<(), i32> {
+    let generator = Generator::from_matches(matches, io).ok_or(1)?;
+
+    let secret = match generator.generate() {
+        Ok(secret) => secret,
+        Err(io_err) => {
+            io.error(
+                format!(
+                    "Woops, I could not generate the secret (reason: {:?}).",
+                    io_err
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    // Only the secret goes to stdout, so it can be piped or redirected to a file.
+    generator.report(&secret, OutputType::Error, io);
+    io.result(json!({
+        "secret": secret.value.trim_end(),
+        "fields": secret.fields,
+    }));
+    io.writeln(secret.value.trim_end(), OutputType::Standard);
+
+    Ok(())
+}
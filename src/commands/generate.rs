@@ -1,8 +1,9 @@
-use crate::clip::{copy_to_clipboard, paste_keys};
-use crate::generate::{check_password_len, PasswordSpec};
+use crate::clip::{copy_password_to_clipboard, password_saved_message};
+use crate::generate::Generator;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use serde_json::json;
 
 use std::ops::Deref;
 
@@ -22,15 +23,10 @@ pub fn callback_exec(
         return Err(1);
     }
 
-    let pwspec = PasswordSpec::new(
-        matches.is_present("alnum"),
-        matches
-            .value_of("length")
-            .and_then(|len| check_password_len(len.parse::<usize>().ok(), io)),
-    );
+    let generator = Generator::from_matches(matches, io).ok_or(1)?;
 
-    let password_as_string = match pwspec.generate_hard_password() {
-        Ok(password_as_string) => password_as_string,
+    let secret = match generator.generate() {
+        Ok(secret) => secret,
         Err(io_err) => {
             io.error(
                 format!(
@@ -42,14 +38,19 @@ pub fn callback_exec(
             return Err(1);
         }
     };
+    generator.report(&secret, OutputType::Standard, io);
 
     // Read the master password and try to save the new password.
-    let password_as_string_clipboard = password_as_string.clone();
-    let password = password::v2::Password::new(app_name.clone(), username, password_as_string);
+    let password_as_string_clipboard = secret.value.clone();
+    let mut password = password::v2::Password::new(app_name.clone(), username, secret.value);
+    password.fields = secret.fields;
 
+    let show = matches.is_present("show");
+    let entry = password.to_json(show);
     match store.add_password(password) {
         Ok(_) => {
-            if matches.is_present("show") {
+            if show {
+                io.result(json!({"entry": entry, "copied": false}));
                 io.success(
                     format!(
                         "Alright! Here is your password: {}",
@@ -60,24 +61,23 @@ pub fn callback_exec(
                 return Ok(());
             }
 
-            if copy_to_clipboard(&password_as_string_clipboard).is_err() {
-                io.success(
-                    format!(
-                        "Hmm, I tried to copy your new password to your clipboard, but \
-                         something went wrong. Don't worry, it's saved, and you can see it \
-                         with `rooster get {} --show`",
-                        app_name
-                    ),
-                    OutputType::Standard,
-                );
-            } else {
-                io.success(
-                    format!(
-                        "Alright! I've saved your new password. You can paste it anywhere with {}.",
-                        paste_keys()
-                    ),
-                    OutputType::Standard,
-                );
+            let copied = copy_password_to_clipboard(&password_as_string_clipboard);
+            io.result(json!({"entry": entry, "copied": copied.is_ok()}));
+            match copied {
+                Err(_) => {
+                    io.success(
+                        format!(
+                            "Hmm, I tried to copy your new password to your clipboard, but \
+                             something went wrong. Don't worry, it's saved, and you can see it \
+                             with `rooster get {} --show`",
+                            app_name
+                        ),
+                        OutputType::Standard,
+                    );
+                }
+                Ok(copied) => {
+                    io.success(password_saved_message(&copied), OutputType::Standard);
+                }
             }
 
             Ok(())
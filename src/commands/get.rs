@@ -1,8 +1,12 @@
 use crate::clip;
-
+use crate::expiry;
+use crate::ffi;
 use crate::list;
 use crate::password;
 use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use serde_json::json;
+use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
@@ -20,10 +24,60 @@ pub fn callback_exec(
             "to copy to your clipboard"
         },
     );
-    let password =
-        list::search_and_choose_password(store, query, list::WITH_NUMBERS, &prompt, io).ok_or(1)?;
+    let password = list::search_and_choose_password(
+        store,
+        query,
+        list::Selection::from_matches(matches),
+        list::WITH_NUMBERS,
+        &prompt,
+        io,
+    )
+    .ok_or(1)?;
+
+    if let Some(field) = matches.value_of("print") {
+        print_field(password, field, io)?;
+    } else {
+        clip::confirm_password_retrieved(show, &password, io);
+    }
+
+    if expiry::is_overdue(password, ffi::time() as i64) {
+        io.warning(
+            format!(
+                "This password needed to be changed {}. You can change it with `rooster regenerate '{}'`.",
+                expiry::describe_due(expiry::due_at(password).unwrap(), ffi::time() as i64),
+                password.name
+            ),
+            OutputType::Error,
+        );
+    }
+
+    Ok(())
+}
 
-    clip::confirm_password_retrieved(show, &password, io);
+/// Writes the raw value of a field to stdout, without a new line, for `$(rooster get ...)`
+fn print_field(
+    password: &password::v2::Password,
+    field: &str,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let value = match password.field_value(field) {
+        Some(value) => value,
+        None => {
+            io.error(
+                format!(
+                    "Woops, {} doesn't have a field named \"{}\".",
+                    password.name, field
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
 
+    io.result(json!({
+        "entry": password.to_json(field == "password"),
+        "value": value.deref(),
+    }));
+    io.write(value.deref(), OutputType::Standard);
     Ok(())
 }
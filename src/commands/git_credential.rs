@@ -0,0 +1,210 @@
+use crate::ffi;
+use crate::origin::{url_matches, DEFAULT_PROTOCOL, URL_FIELD};
+use crate::password;
+use crate::password::v2::{Password, PasswordStore};
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::rutil::safe_string::SafeString;
+use serde_json::json;
+use std::collections::HashMap;
+use std::ops::Deref;
+
+/// Parses the `key=value` lines git sends to credential helpers, up to an empty line
+fn parse_request(input: &str) -> HashMap<String, String> {
+    input
+        .lines()
+        .take_while(|line| !line.is_empty())
+        .filter_map(|line| {
+            let equal = line.find('=')?;
+            Some((line[..equal].to_owned(), line[equal + 1..].to_owned()))
+        })
+        .collect()
+}
+
+/// The name of the app for this request, from a scheme like `{host}` or `git/{username}@{host}`,
+/// unless the scheme needs something git didn't tell us
+fn app_name(scheme: &str, request: &HashMap<String, String>) -> Option<String> {
+    let mut name = scheme.to_owned();
+    for key in ["protocol", "host", "username", "path"].iter() {
+        let placeholder = format!("{{{}}}", key);
+        if name.contains(&placeholder) {
+            name = name.replace(&placeholder, request.get(*key)?);
+        }
+    }
+    Some(name)
+}
+
+/// Finds the password for this request: first by URL field, then by name if git talks https.
+/// Other protocols only get passwords whose URL field names them.
+fn find_password(
+    store: &PasswordStore,
+    scheme: &str,
+    request: &HashMap<String, String>,
+) -> Option<Password> {
+    let protocol = request.get("protocol").map(|p| p.as_str()).unwrap_or("");
+    let host = request.get("host").map(|h| h.as_str()).unwrap_or("");
+    let username = request.get("username");
+    let username_matches =
+        |password: &Password| username.is_none() || username == Some(&password.username);
+
+    let mut by_url: Vec<&Password> = store
+        .get_all_passwords()
+        .into_iter()
+        .filter(|password| url_matches(password, protocol, host) && username_matches(password))
+        .collect();
+    by_url.sort_by_key(|password| password.name.to_lowercase());
+    if let Some(password) = by_url.first() {
+        return Some((*password).clone());
+    }
+
+    if protocol != DEFAULT_PROTOCOL {
+        return None;
+    }
+    app_name(scheme, request)
+        .and_then(|name| store.get_password(&name))
+        .filter(|password| username_matches(password))
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let input = io.read_stdin().map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't read what git sent (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })?;
+    let request = parse_request(&input);
+    let scheme = matches.value_of("name").unwrap();
+
+    match matches.value_of("operation").unwrap() {
+        "get" => {
+            // Finding nothing is fine, git then asks the next helper or the user.
+            if let Some(password) = find_password(store, scheme, &request) {
+                io.result(json!({ "entry": password.to_json(false) }));
+                let reply = SafeString::from_string(format!(
+                    "username={}\npassword={}\n",
+                    password.username,
+                    password.password.deref()
+                ));
+                io.write(reply.deref(), OutputType::Standard);
+            }
+            Ok(())
+        }
+        "store" => store_credential(store, scheme, &request, io),
+        // Git erases credentials it believes are wrong, which happens with expired tokens but
+        // also when a server has a hiccup. We'd rather keep passwords, only the user deletes them.
+        _ => Ok(()),
+    }
+}
+
+fn store_credential(
+    store: &mut PasswordStore,
+    scheme: &str,
+    request: &HashMap<String, String>,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let (username, new_password) = match (request.get("username"), request.get("password")) {
+        (Some(username), Some(password)) => (username, SafeString::from_string(password.clone())),
+        _ => return Ok(()),
+    };
+
+    if let Some(password) = find_password(store, scheme, request) {
+        // Git stores credentials after every successful use, most of the time they haven't changed.
+        if password.password == new_password {
+            return Ok(());
+        }
+        let password = store
+            .change_password(&password.name, &|old_password: Password| Password {
+                name: old_password.name,
+                username: old_password.username,
+                password: new_password.clone(),
+                created_at: old_password.created_at,
+                updated_at: ffi::time(),
+                fields: old_password.fields,
+                rotation_days: old_password.rotation_days,
+                expires_at: old_password.expires_at,
+            })
+            .map_err(|err| {
+                io.error(
+                    format!(
+                        "Woops, I couldn't save the new password (reason: {:?}).",
+                        err
+                    ),
+                    OutputType::Error,
+                );
+                1
+            })?;
+        io.result(json!({ "entry": password.to_json(false) }));
+        return Ok(());
+    }
+
+    let name = match app_name(scheme, request) {
+        Some(name) => name,
+        None => {
+            io.error(
+                format!(
+                    "Woops, git didn't tell me enough to name the app with \"{}\".",
+                    scheme
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+    if store.has_password(&name) {
+        io.error(
+            format!(
+                "Woops, there is already an app named \"{}\", for another username. Add {{username}} to --name to keep both.",
+                name
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    let mut password = Password::new(name, username.as_str(), new_password);
+    if let (Some(protocol), Some(host)) = (request.get("protocol"), request.get("host")) {
+        password
+            .fields
+            .insert(URL_FIELD.to_owned(), format!("{}://{}", protocol, host));
+    }
+    io.result(json!({ "entry": password.to_json(false) }));
+    store.add_password(password).map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't add the password (reason: {:?}).", err),
+            OutputType::Error,
+        );
+        1
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{app_name, parse_request};
+
+    #[test]
+    fn test_parse_request() {
+        let request = parse_request("protocol=https\nhost=git.example.com\n\nignored=1\n");
+        assert_eq!(request.len(), 2);
+        assert_eq!(request["protocol"], "https");
+        assert_eq!(request["host"], "git.example.com");
+    }
+
+    #[test]
+    fn test_app_name() {
+        let request = parse_request("protocol=https\nhost=git.example.com\n");
+        assert_eq!(
+            app_name("{host}", &request),
+            Some("git.example.com".to_owned())
+        );
+        assert_eq!(
+            app_name("git/{protocol}/{host}", &request),
+            Some("git/https/git.example.com".to_owned())
+        );
+        assert_eq!(app_name("{username}@{host}", &request), None);
+    }
+}
@@ -4,6 +4,8 @@ use crate::password::v2::{Password, PasswordStore};
 use crate::rclio::{CliInputOutput, OutputType};
 use serde::{Deserialize, Serialize};
 use serde_json;
+use serde_json::json;
+use std::collections::BTreeMap;
 use std::fs::File;
 
 #[derive(Serialize, Deserialize)]
@@ -41,12 +43,16 @@ fn import_passwords(
     let mut errors = 0;
     let mut warnings = 0;
     let mut successes = 0;
+    let mut imported = Vec::new();
+    let mut skipped = Vec::new();
+    let mut failed = Vec::new();
     for password in invalid {
         io.error(
             format!("{}, invalid format, skipping", password.name),
             OutputType::Error,
         );
         errors += 1;
+        failed.push(password.name);
     }
     for password in valid {
         if let Some(_) = store.get_password(&password.name) {
@@ -55,6 +61,7 @@ fn import_passwords(
                 OutputType::Error,
             );
             warnings += 1;
+            skipped.push(password.name);
             continue;
         }
 
@@ -64,12 +71,20 @@ fn import_passwords(
                 OutputType::Error,
             );
             errors += 1;
+            failed.push(password.name);
             continue;
         }
 
         successes += 1;
+        imported.push(password.name);
     }
 
+    io.result(json!({
+        "imported": imported,
+        "skipped": skipped,
+        "failed": failed,
+    }));
+
     io.success(format!("Imported: {}", successes), OutputType::Standard);
     io.warning(format!("Warnings: {}", warnings), OutputType::Error);
     io.error(format!("Errors: {}", errors), OutputType::Error);
@@ -101,6 +116,9 @@ fn create_imported_passwords_from_csv(
                 password: record[2].into(),
                 created_at: ffi::time(),
                 updated_at: ffi::time(),
+                fields: BTreeMap::new(),
+                rotation_days: None,
+                expires_at: None,
             });
         } else {
             return Err(1);
@@ -135,6 +153,9 @@ fn create_imported_passwords_from_1password(
                     password: record[1].into(),
                     created_at: ffi::time(),
                     updated_at: ffi::time(),
+                    fields: BTreeMap::new(),
+                    rotation_days: None,
+                    expires_at: None,
                 });
                 continue;
             }
@@ -146,6 +167,9 @@ fn create_imported_passwords_from_1password(
                 password: record[1].into(),
                 created_at: ffi::time(),
                 updated_at: ffi::time(),
+                fields: BTreeMap::new(),
+                rotation_days: None,
+                expires_at: None,
             });
         } else {
             return Err(1);
@@ -1,5 +1,8 @@
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use crate::strength;
+use serde_json::json;
+use std::ops::Deref;
 use std::path::PathBuf;
 
 pub fn callback_exec(
@@ -26,12 +29,15 @@ pub fn callback_exec(
     io.nl(OutputType::Standard);
     io.info("Rooster is a simple password manager for geeks. Let's get started! Type ENTER to continue.", OutputType::Standard);
 
-    if let Err(err) = io.read_line() {
-        io.error(
-            format!("Woops, I didn't see the ENTER key (reason: {:?}).", err),
-            OutputType::Error,
-        );
-        return Err(1);
+    // Scripts have nobody to press ENTER.
+    if io.is_interactive() {
+        if let Err(err) = io.read_line() {
+            io.error(
+                format!("Woops, I didn't see the ENTER key (reason: {:?}).", err),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
     }
 
     io.title("The master password", OutputType::Standard);
@@ -58,6 +64,32 @@ pub fn callback_exec(
         return Err(1);
     }
 
+    let master_password_confirmation = io
+        .prompt_password("Type your master password once more: ")
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't read the master passwords ({:?}).", err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    if master_password != master_password_confirmation {
+        io.error(
+            "The master password confirmation did not match. Aborting.",
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    // Tests don't need to come up with strong master passwords.
+    let min_score = if matches.is_present("force-for-tests") {
+        0
+    } else {
+        strength::min_master_password_score()
+    };
+    strength::check_master_password(master_password.deref(), min_score, io)?;
+
     let store = match crate::password::v2::PasswordStore::new(master_password) {
         Ok(store) => store,
         Err(err) => {
@@ -113,6 +145,7 @@ pub fn callback_exec(
     io.nl(OutputType::Standard);
     io.title("All done and ready to rock", OutputType::Standard);
     io.nl(OutputType::Standard);
+    io.result(json!({ "path": filename_as_string }));
     io.success("You passwords will be saved in:", OutputType::Standard);
     io.success(format!("    {}", filename_as_string), OutputType::Standard);
     io.nl(OutputType::Standard);
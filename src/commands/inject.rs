@@ -0,0 +1,77 @@
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::rutil::private_file::write_private_file;
+use crate::rutil::safe_string::SafeString;
+use crate::template;
+use serde_json::json;
+use std::fs;
+use std::ops::Deref;
+use std::path::Path;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let input = matches.value_of("input").unwrap();
+    let template = fs::read_to_string(input).map_err(|err| {
+        io.error(
+            format!("Woops, I couldn't read {} (reason: {}).", input, err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    let mut references = 0;
+    let rendered = template::render(&template, |reference| {
+        references += 1;
+        store
+            .get_password(&reference.app)
+            .and_then(|password| password.field_value(&reference.field))
+    });
+    let rendered: SafeString = match rendered {
+        Ok(rendered) => rendered,
+        Err(unresolved) => {
+            io.result(json!({ "unresolved": unresolved }));
+            io.error(
+                format!(
+                    "Woops, I couldn't find these passwords or fields, so I didn't write anything:\n{}",
+                    unresolved
+                        .iter()
+                        .map(|reference| format!("    {}", reference))
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    match matches.value_of("output-file") {
+        Some(output) => {
+            write_private_file(Path::new(output), rendered.deref()).map_err(|err| {
+                io.error(
+                    format!("Woops, I couldn't write {} (reason: {}).", output, err),
+                    OutputType::Error,
+                );
+                1
+            })?;
+            io.result(json!({ "references": references, "output": output }));
+            io.success(
+                format!(
+                    "Done! I've written {} with {} passwords or fields.",
+                    output, references
+                ),
+                OutputType::Error,
+            );
+        }
+        None => {
+            io.result(json!({ "references": references, "content": rendered.deref() }));
+            io.write(rendered.deref(), OutputType::Standard);
+        }
+    }
+
+    Ok(())
+}
@@ -2,6 +2,7 @@ use crate::list;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use serde_json::json;
 
 pub fn callback_exec(
     _matches: &clap::ArgMatches,
@@ -9,6 +10,12 @@ pub fn callback_exec(
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
     let passwords = store.get_all_passwords();
+    io.result(json!({
+        "entries": passwords
+            .iter()
+            .map(|password| password.to_json(false))
+            .collect::<Vec<_>>()
+    }));
 
     if passwords.len() == 0 {
         io.info(
@@ -0,0 +1,38 @@
+use crate::key_cache;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use std::path::Path;
+
+pub fn callback_exec(
+    _matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    let key_caches = key_cache::from_env();
+    if key_caches.is_empty() {
+        io.info(
+            "Your master password is not cached, so there is nothing to lock.",
+            OutputType::Standard,
+        );
+        return Ok(());
+    }
+
+    for key_cache in key_caches.iter() {
+        if let Err(err) = key_cache.lock(rooster_file_path) {
+            io.error(
+                format!(
+                    "Woops, I couldn't forget your cached master password (reason: {:?}).",
+                    err
+                ),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    }
+
+    io.success(
+        "Alright! Your master password will be asked again next time.",
+        OutputType::Standard,
+    );
+    Ok(())
+}
@@ -1,14 +1,32 @@
 pub mod add;
+pub mod agent;
+pub mod askpass;
+pub mod audit;
+pub mod autotype;
 pub mod change;
+pub mod clear_clipboard;
 pub mod delete;
+pub mod exec;
+pub mod expiry;
 pub mod export;
+pub mod field;
+pub mod gen;
 pub mod generate;
 pub mod get;
+pub mod git_credential;
 pub mod import;
 pub mod init;
+pub mod inject;
 pub mod list;
+pub mod lock;
+pub mod native_host;
 pub mod regenerate;
 pub mod rename;
+pub mod run;
+pub mod secret_service;
+pub mod serve;
 pub mod set_master_password;
 pub mod set_scrypt_params;
+pub mod stale;
 pub mod transfer;
+pub mod ui;
@@ -0,0 +1,445 @@
+use crate::approval;
+use crate::ffi;
+use crate::origin::{protocol_and_host, url_matches, DEFAULT_PROTOCOL, URL_FIELD};
+use crate::password;
+use crate::password::v2::{Password, PasswordStore};
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::rutil::file_lock::FileLock;
+use crate::rutil::private_file::write_private_file;
+use crate::rutil::safe_string::SafeString;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::ErrorKind;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// The name browsers know us by, in manifests and in `runtime.connectNative`
+const HOST_NAME: &str = "rooster";
+
+/// What browsers accept from native hosts, which is more than enough for what they send us
+const MAX_MESSAGE_LEN: u32 = 1024 * 1024;
+
+/// Reads a message from the browser: its length, as 4 bytes in native byte order, then JSON. Returns
+/// `None` once the browser is gone.
+fn read_message(io: &mut impl CliInputOutput) -> Result<Option<Vec<u8>>, String> {
+    let mut len = [0u8; 4];
+    match io.read_stdin_exact(&mut len) {
+        Ok(()) => {}
+        Err(ref err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.to_string()),
+    }
+    let len = u32::from_ne_bytes(len);
+    if len > MAX_MESSAGE_LEN {
+        return Err(format!("a message of {} bytes is too big", len));
+    }
+
+    let mut message = vec![0u8; len as usize];
+    io.read_stdin_exact(&mut message)
+        .map_err(|err| err.to_string())?;
+    Ok(Some(message))
+}
+
+fn write_message(io: &mut impl CliInputOutput, message: &Value) -> std::io::Result<()> {
+    let message = SafeString::from_string(message.to_string());
+    io.write_stdout(&(message.len() as u32).to_ne_bytes())?;
+    io.write_stdout(message.as_bytes())
+}
+
+/// Whether the password is for this origin, like `https://example.com`: by its URL field, or by
+/// its name if it is named after the host and the origin is https
+fn origin_matches(password: &Password, origin: &str) -> bool {
+    match protocol_and_host(origin) {
+        (Some(protocol), host) if !host.is_empty() => {
+            url_matches(password, protocol, host)
+                || (protocol == DEFAULT_PROTOCOL && password.name.eq_ignore_ascii_case(host))
+        }
+        _ => false,
+    }
+}
+
+/// The string parameter of a request
+fn param<'a>(request: &'a Value, name: &str) -> Result<&'a str, String> {
+    request
+        .get(name)
+        .and_then(|value| value.as_str())
+        .ok_or_else(|| format!("\"{}\" is missing", name))
+}
+
+fn lookup(store: &PasswordStore, origin: &str) -> Vec<Password> {
+    let mut passwords: Vec<Password> = store
+        .get_all_passwords()
+        .into_iter()
+        .filter(|password| origin_matches(password, origin))
+        .cloned()
+        .collect();
+    passwords.sort_by_key(|password| password.name.to_lowercase());
+    passwords
+}
+
+/// Answers a request, returning whether the store changed along with the answer
+fn handle(request: &Value, store: &mut PasswordStore) -> Result<(Value, bool), String> {
+    let origin = param(request, "origin")?;
+
+    match param(request, "action")? {
+        "lookup" => {
+            let entries: Vec<Value> = lookup(store, origin)
+                .iter()
+                .map(|password| password.to_json(false))
+                .collect();
+            Ok((json!({ "entries": entries }), false))
+        }
+        "get" => {
+            let name = param(request, "name")?;
+            let password = match store.get_password(name) {
+                Some(ref password) if origin_matches(password, origin) => password.clone(),
+                _ => return Err(format!("there is no app named \"{}\" for {}", name, origin)),
+            };
+            if !approval::approve(&format!(
+                "Give the password of {} to {} in your browser?",
+                password.name, origin
+            )) {
+                return Err("the user said no".to_owned());
+            }
+            Ok((json!({ "entry": password.to_json(true) }), false))
+        }
+        "save" => {
+            let username = param(request, "username")?;
+            let new_password = SafeString::from_string(param(request, "password")?.to_owned());
+            save(
+                store,
+                origin,
+                request.get("name").and_then(|name| name.as_str()),
+                username,
+                new_password,
+            )
+        }
+        action => Err(format!("there is no action named \"{}\"", action)),
+    }
+}
+
+fn save(
+    store: &mut PasswordStore,
+    origin: &str,
+    name: Option<&str>,
+    username: &str,
+    new_password: SafeString,
+) -> Result<(Value, bool), String> {
+    let existing = lookup(store, origin).into_iter().find(|password| {
+        name.map_or(password.username == username, |name| {
+            password.name.eq_ignore_ascii_case(name)
+        })
+    });
+
+    if let Some(password) = existing {
+        if password.password == new_password && password.username == username {
+            return Ok((json!({ "entry": password.to_json(false) }), false));
+        }
+        if !approval::approve(&format!(
+            "Change the password of {} from {} in your browser?",
+            password.name, origin
+        )) {
+            return Err("the user said no".to_owned());
+        }
+        let password = store
+            .change_password(&password.name, &|old_password: Password| Password {
+                name: old_password.name,
+                username: username.to_owned(),
+                password: new_password.clone(),
+                created_at: old_password.created_at,
+                updated_at: ffi::time(),
+                fields: old_password.fields,
+                rotation_days: old_password.rotation_days,
+                expires_at: old_password.expires_at,
+            })
+            .map_err(|err| format!("I couldn't change the password ({:?})", err))?;
+        return Ok((json!({ "entry": password.to_json(false) }), true));
+    }
+
+    let (protocol, host) = protocol_and_host(origin);
+    let name = name.unwrap_or(host);
+    if store.has_password(name) {
+        return Err(format!(
+            "there is already an app named \"{}\", for another website or username",
+            name
+        ));
+    }
+    let mut password = Password::new(name, username, new_password);
+    password.fields.insert(
+        URL_FIELD.to_owned(),
+        format!("{}://{}", protocol.unwrap_or("https"), host),
+    );
+    let entry = password.to_json(false);
+    store
+        .add_password(password)
+        .map_err(|err| format!("I couldn't add the password ({:?})", err))?;
+    Ok((json!({ "entry": entry }), true))
+}
+
+/// Answers a request on top of the password file as it is now, since other Rooster commands may
+/// have changed it, and saves the changes before anyone else can
+fn handle_with_file(
+    request: &Value,
+    store: &mut PasswordStore,
+    file: &mut File,
+) -> Result<Value, String> {
+    let _lock = FileLock::lock(file)
+        .map_err(|err| format!("I couldn't lock the password file ({})", err))?;
+    store
+        .reload(file)
+        .map_err(|err| format!("I couldn't read the password file again ({:?})", err))?;
+    let (response, changed) = handle(request, store)?;
+    if changed {
+        store
+            .sync(file)
+            .map_err(|err| format!("I couldn't save the password file ({:?})", err))?;
+    }
+    Ok(response)
+}
+
+/// Answers the browser until it goes away, saving changes as they are made
+pub fn callback_exec(
+    _matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    file: &mut File,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    loop {
+        let message = match read_message(io) {
+            Ok(Some(message)) => message,
+            Ok(None) => return Ok(()),
+            Err(err) => {
+                io.error(
+                    format!("Woops, I couldn't read what the browser sent ({}).", err),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        };
+
+        let request: Value = serde_json::from_slice(&message).unwrap_or(Value::Null);
+        let mut response = match handle_with_file(&request, store, file) {
+            Ok(response) => response,
+            Err(err) => json!({ "ok": false, "error": err }),
+        };
+        if response.get("ok").is_none() {
+            response["ok"] = Value::Bool(true);
+        }
+        // Requests can be answered out of order by other hosts, so extensions tell them apart.
+        if let Some(id) = request.get("id") {
+            response["id"] = id.clone();
+        }
+
+        if let Err(err) = write_message(io, &response) {
+            io.error(
+                format!("Woops, I couldn't answer the browser ({}).", err),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    }
+}
+
+/// Where browsers look for native host manifests
+fn manifest_dir(browser: &str) -> Option<PathBuf> {
+    let mut dir = dirs::home_dir()?;
+    #[cfg(target_os = "macos")]
+    dir.push(match browser {
+        "chrome" => "Library/Application Support/Google/Chrome/NativeMessagingHosts",
+        "chromium" => "Library/Application Support/Chromium/NativeMessagingHosts",
+        _ => "Library/Application Support/Mozilla/NativeMessagingHosts",
+    });
+    #[cfg(not(target_os = "macos"))]
+    dir.push(match browser {
+        "chrome" => ".config/google-chrome/NativeMessagingHosts",
+        "chromium" => ".config/chromium/NativeMessagingHosts",
+        _ => ".mozilla/native-messaging-hosts",
+    });
+    Some(dir)
+}
+
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// Browsers start native hosts without arguments or the environment of our shell, so a script
+/// holds both
+fn wrapper_script(rooster: &Path, rooster_file_path: &Path) -> String {
+    let mut script = String::from("#!/bin/sh\n");
+    let mut vars: Vec<(String, String)> = std::env::vars()
+        .filter(|(name, _)| name.starts_with("ROOSTER_") && name != "ROOSTER_FILE")
+        .collect();
+    vars.push((
+        "ROOSTER_FILE".to_owned(),
+        rooster_file_path.to_string_lossy().into_owned(),
+    ));
+    vars.sort();
+    for (name, value) in vars {
+        script.push_str(&format!("export {}={}\n", name, shell_quote(&value)));
+    }
+    script.push_str(&format!(
+        "exec {} native-host \"$@\"\n",
+        shell_quote(&rooster.to_string_lossy())
+    ));
+    script
+}
+
+fn manifest(browser: &str, extension_id: &str, wrapper: &Path) -> Value {
+    let mut manifest = json!({
+        "name": HOST_NAME,
+        "description": "Rooster, the simple password manager for geeks",
+        "path": wrapper,
+        "type": "stdio",
+    });
+    if browser == "firefox" {
+        manifest["allowed_extensions"] = json!([extension_id]);
+    } else {
+        manifest["allowed_origins"] = json!([format!("chrome-extension://{}/", extension_id)]);
+    }
+    manifest
+}
+
+/// Writes the manifest that tells the browser how to start us, and the script it starts
+pub fn install(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &Path,
+) -> Result<(), i32> {
+    let browser = matches.value_of("browser").unwrap();
+    let extension_id = matches.value_of("extension-id").unwrap();
+    let dir = match matches.value_of("manifest-dir") {
+        Some(dir) => PathBuf::from(dir),
+        None => manifest_dir(browser).ok_or_else(|| {
+            io.error(
+                "Woops, I couldn't find your home directory.",
+                OutputType::Error,
+            );
+            1
+        })?,
+    };
+
+    let rooster = std::env::current_exe().map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I couldn't find where Rooster is installed (reason: {}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+    let rooster_file_path = rooster_file_path
+        .canonicalize()
+        .unwrap_or_else(|_| rooster_file_path.to_path_buf());
+
+    let wrapper = dir.join(format!("{}-native-host", HOST_NAME));
+    let manifest_path = dir.join(format!("{}.json", HOST_NAME));
+    let manifest = manifest(browser, extension_id, &wrapper);
+    let written = std::fs::create_dir_all(&dir)
+        .and_then(|_| write_private_file(&wrapper, &wrapper_script(&rooster, &rooster_file_path)))
+        .and_then(|_| std::fs::set_permissions(&wrapper, std::fs::Permissions::from_mode(0o700)))
+        .and_then(|_| {
+            std::fs::write(
+                &manifest_path,
+                serde_json::to_string_pretty(&manifest).unwrap() + "\n",
+            )
+        });
+    if let Err(err) = written {
+        io.error(
+            format!(
+                "Woops, I couldn't install the native host in {} (reason: {}).",
+                dir.to_string_lossy(),
+                err
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+
+    io.result(json!({ "manifest": manifest_path, "wrapper": wrapper }));
+    io.success(
+        format!(
+            "Done! The extension {} can now talk to Rooster, see {}.",
+            extension_id,
+            manifest_path.to_string_lossy()
+        ),
+        OutputType::Standard,
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{handle_with_file, origin_matches, shell_quote};
+    use crate::origin::URL_FIELD;
+    use crate::password::v2::{Password, PasswordStore};
+    use crate::rutil::safe_string::SafeString;
+    use crate::rutil::safe_vec::SafeVec;
+    use serde_json::{json, Value};
+    use std::fs::File;
+    use std::io::{Read, Seek, SeekFrom};
+
+    fn save_request(name: &str) -> Value {
+        json!({
+            "action": "save",
+            "origin": "https://example.com",
+            "name": name,
+            "username": "alice",
+            "password": "p@ssw0rd",
+        })
+    }
+
+    /// What another Rooster command reads from the file
+    fn read_store(store: &PasswordStore, file: &mut File) -> PasswordStore {
+        let mut input = SafeVec::new(Vec::new());
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_to_end(input.inner_mut()).unwrap();
+        PasswordStore::from_input_with_key(store.key().clone(), input).unwrap()
+    }
+
+    #[test]
+    fn test_handle_with_file_keeps_changes_from_other_commands() {
+        let mut store = PasswordStore::new(SafeString::from_string("xxxx".to_owned())).unwrap();
+        let mut file = tempfile::tempfile().unwrap();
+        store.sync(&mut file).unwrap();
+
+        handle_with_file(&save_request("first"), &mut store, &mut file).unwrap();
+
+        // Meanwhile, `rooster add` saves a password of its own.
+        let mut other = read_store(&store, &mut file);
+        other
+            .add_password(Password::new("from-cli", "bob", "hunter2"))
+            .unwrap();
+        other.sync(&mut file).unwrap();
+
+        handle_with_file(&save_request("second"), &mut store, &mut file).unwrap();
+
+        let saved = read_store(&store, &mut file);
+        assert!(saved.has_password("first"));
+        assert!(saved.has_password("from-cli"));
+        assert!(saved.has_password("second"));
+    }
+
+    #[test]
+    fn test_origin_matches() {
+        let mut password = Password::new("Example", "alice", "p@ssw0rd");
+        password.fields.insert(
+            URL_FIELD.to_owned(),
+            "https://login.example.com/".to_owned(),
+        );
+        assert!(origin_matches(&password, "https://login.example.com"));
+        assert!(!origin_matches(&password, "http://login.example.com"));
+        assert!(!origin_matches(&password, "https://example.com"));
+
+        let password = Password::new("example.com", "alice", "p@ssw0rd");
+        assert!(origin_matches(&password, "https://example.com"));
+        assert!(!origin_matches(&password, "http://example.com"));
+        assert!(!origin_matches(&password, "example.com"));
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("/home/alice"), "'/home/alice'");
+        assert_eq!(shell_quote("it's $HOME"), "'it'\\''s $HOME'");
+    }
+}
@@ -1,10 +1,12 @@
+use crate::breach;
 use crate::clip;
 use crate::ffi;
-use crate::generate::{check_password_len, PasswordSpec};
+use crate::generate::{Generator, PUBLIC_KEY_FIELD};
 use crate::list;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use std::ops::Deref;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
@@ -16,6 +18,7 @@ pub fn callback_exec(
     let password = list::search_and_choose_password(
         store,
         query,
+        list::Selection::from_matches(matches),
         list::WITH_NUMBERS,
         "Which password would you like to regenerate?",
         io,
@@ -23,15 +26,10 @@ pub fn callback_exec(
     .ok_or(1)?
     .clone();
 
-    let pwspec = PasswordSpec::new(
-        matches.is_present("alnum"),
-        matches
-            .value_of("length")
-            .and_then(|len| check_password_len(len.parse::<usize>().ok(), io)),
-    );
+    let generator = Generator::from_matches(matches, io).ok_or(1)?;
 
-    let password_as_string = match pwspec.generate_hard_password() {
-        Ok(password_as_string) => password_as_string,
+    let secret = match generator.generate() {
+        Ok(secret) => secret,
         Err(io_err) => {
             io.error(
                 format!(
@@ -43,15 +41,24 @@ pub fn callback_exec(
             return Err(1);
         }
     };
+    generator.report(&secret, OutputType::Standard, io);
+    breach::warn_if_breached(secret.value.deref(), io);
 
     let change_result =
         store.change_password(&password.name, &|old_password: password::v2::Password| {
+            // A public key that was generated along with the old secret doesn't match the new one.
+            let mut fields = old_password.fields.clone();
+            fields.remove(PUBLIC_KEY_FIELD);
+            fields.extend(secret.fields.clone());
             password::v2::Password {
                 name: old_password.name.clone(),
                 username: old_password.username.clone(),
-                password: password_as_string.clone(),
+                password: secret.value.clone(),
                 created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                fields,
+                rotation_days: old_password.rotation_days,
+                expires_at: old_password.expires_at,
             }
         });
 
@@ -3,6 +3,7 @@ use crate::list;
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use serde_json::json;
 
 pub fn callback_exec(
     matches: &clap::ArgMatches,
@@ -15,6 +16,7 @@ pub fn callback_exec(
     let password = list::search_and_choose_password(
         store,
         query,
+        list::Selection::from_matches(matches),
         list::WITH_NUMBERS,
         "Which password would you like to rename?",
         io,
@@ -30,11 +32,15 @@ pub fn callback_exec(
                 password: old_password.password.clone(),
                 created_at: old_password.created_at,
                 updated_at: ffi::time(),
+                fields: old_password.fields.clone(),
+                rotation_days: old_password.rotation_days,
+                expires_at: old_password.expires_at,
             }
         });
 
     match change_result {
-        Ok(_) => {
+        Ok(password) => {
+            io.result(json!({ "entry": password.to_json(false) }));
             io.success(
                 format!("Done! I've renamed {} to {}", password.name, new_name),
                 OutputType::Standard,
@@ -0,0 +1,139 @@
+use crate::list;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::rutil::safe_string::SafeString;
+use serde_json::json;
+use std::ops::Deref;
+use std::os::unix::process::ExitStatusExt;
+use std::process::Command;
+
+/// Splits `VAR=app:field` into its variable, app and field. App names may contain colons, the
+/// field is whatever follows the last one.
+fn parse_env_reference(reference: &str) -> Option<(&str, &str, &str)> {
+    let equal = reference.find('=')?;
+    let (var, target) = (&reference[..equal], &reference[equal + 1..]);
+    let colon = target.rfind(':')?;
+    let (app, field) = (&target[..colon], &target[colon + 1..]);
+    if var.is_empty() || app.is_empty() || field.is_empty() {
+        return None;
+    }
+    Some((var, app, field))
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let mut env: Vec<(&str, SafeString)> = Vec::new();
+    for reference in matches.values_of("env").unwrap() {
+        let (var, app, field) = match parse_env_reference(reference) {
+            Some(parsed) => parsed,
+            None => {
+                io.error(
+                    format!(
+                        "Woops, \"{}\" should look like VAR=app:field, for instance DB_PASS=prod-db:password.",
+                        reference
+                    ),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        };
+
+        let password = list::search_and_choose_password(
+            store,
+            app,
+            list::Selection::from_matches(matches),
+            list::WITH_NUMBERS,
+            &format!("Which password would you like in {}? ", var),
+            io,
+        )
+        .ok_or(1)?;
+
+        match password.field_value(field) {
+            Some(value) => env.push((var, value)),
+            None => {
+                io.error(
+                    format!(
+                        "Woops, {} doesn't have a field named \"{}\".",
+                        password.name, field
+                    ),
+                    OutputType::Error,
+                );
+                return Err(1);
+            }
+        }
+    }
+
+    let command: Vec<&str> = matches.values_of("command").unwrap().collect();
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .envs(env.iter().map(|(var, value)| (var, value.deref())))
+        .spawn()
+        .map_err(|err| {
+            io.error(
+                format!("Woops, I couldn't run {} (reason: {}).", command[0], err),
+                OutputType::Error,
+            );
+            1
+        })?;
+
+    // Ctrl-C and friends are for the child, which gets them too since it shares our terminal. We
+    // ignore them only once the child has started, so that it doesn't inherit that.
+    let previous_handlers = unsafe {
+        [
+            libc::signal(libc::SIGINT, libc::SIG_IGN),
+            libc::signal(libc::SIGQUIT, libc::SIG_IGN),
+        ]
+    };
+    let status = child.wait();
+    unsafe {
+        libc::signal(libc::SIGINT, previous_handlers[0]);
+        libc::signal(libc::SIGQUIT, previous_handlers[1]);
+    }
+
+    let status = status.map_err(|err| {
+        io.error(
+            format!("Woops, I lost track of {} (reason: {}).", command[0], err),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    // Like shells do, a child killed by a signal exits with 128 + the signal number.
+    let exit_code = status
+        .code()
+        .unwrap_or_else(|| 128 + status.signal().unwrap_or(0));
+    io.result(json!({
+        "env": env.iter().map(|(var, _)| var).collect::<Vec<_>>(),
+        "exit_code": exit_code,
+    }));
+
+    match exit_code {
+        0 => Ok(()),
+        code => Err(code),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_env_reference;
+
+    #[test]
+    fn test_parse_env_reference() {
+        assert_eq!(
+            parse_env_reference("DB_PASS=prod-db:password"),
+            Some(("DB_PASS", "prod-db", "password"))
+        );
+        assert_eq!(
+            parse_env_reference("URL=name:http://example.com:url"),
+            Some(("URL", "name:http://example.com", "url"))
+        );
+        assert_eq!(parse_env_reference("DB_PASS=prod-db"), None);
+        assert_eq!(parse_env_reference("=prod-db:password"), None);
+        assert_eq!(parse_env_reference("DB_PASS=:password"), None);
+        assert_eq!(parse_env_reference("DB_PASS=prod-db:"), None);
+    }
+}
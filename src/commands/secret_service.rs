@@ -0,0 +1,61 @@
+use crate::agent;
+use crate::approval;
+use crate::dbus::Connection;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::secret_service::{self, SecretService, SERVICE_NAME};
+use std::fs::File;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    file: &mut File,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let connection = match matches.value_of("address") {
+        Some(address) => Connection::open(address),
+        None => Connection::session(),
+    };
+    let mut connection = connection.map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I couldn't connect to the session bus (reason: {}).",
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    connection.request_name(SERVICE_NAME).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I couldn't serve passwords as {} (reason: {}). Is another keyring running?",
+                SERVICE_NAME, err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    // The passwords stay in memory for as long as we serve them.
+    agent::disable_core_dumps();
+
+    io.success(
+        format!(
+            "Rooster is serving your passwords on the session bus, as {}.",
+            SERVICE_NAME
+        ),
+        OutputType::Standard,
+    );
+
+    let mut service = SecretService::new(approval::approve);
+    secret_service::serve(&mut connection, &mut service, store, file).map_err(|err| {
+        io.error(
+            format!("Woops, I lost the session bus (reason: {}).", err),
+            OutputType::Error,
+        );
+        1
+    })
+}
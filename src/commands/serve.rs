@@ -0,0 +1,388 @@
+use crate::agent;
+use crate::approval;
+use crate::ffi;
+use crate::generate::{PasswordSpec, MAX_PASSWORD_LEN, MIN_PASSWORD_LEN};
+use crate::password;
+use crate::password::v2::{Password, PasswordStore};
+use crate::password::PasswordError;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::rutil::file_lock::FileLock;
+use crate::rutil::peer_cred;
+use crate::rutil::safe_string::SafeString;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+/// Clients that keep quiet for longer are disconnected, so that others can connect
+const IDLE_TIMEOUT: Duration = Duration::from_secs(60);
+
+// From the JSON-RPC 2.0 specification
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+const INTERNAL_ERROR: i64 = -32603;
+
+// Our own errors
+const NO_SUCH_APP: i64 = 1;
+const APP_EXISTS: i64 = 2;
+const DENIED: i64 = 3;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl ToString) -> RpcError {
+        RpcError {
+            code,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<PasswordError> for RpcError {
+    fn from(err: PasswordError) -> RpcError {
+        match err {
+            PasswordError::NoSuchAppError => RpcError::new(NO_SUCH_APP, "there is no such app"),
+            PasswordError::AppExistsError => {
+                RpcError::new(APP_EXISTS, "there is already an app with that name")
+            }
+            PasswordError::EmptyPasswordError => {
+                RpcError::new(INVALID_PARAMS, "the password is empty")
+            }
+            err => RpcError::new(INTERNAL_ERROR, format!("{:?}", err)),
+        }
+    }
+}
+
+/// The string parameter of a call
+fn param<'a>(params: &'a Value, name: &str) -> Result<&'a str, RpcError> {
+    optional_param(params, name)?
+        .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("\"{}\" is missing", name)))
+}
+
+fn optional_param<'a>(params: &'a Value, name: &str) -> Result<Option<&'a str>, RpcError> {
+    match params.get(name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(_) => Err(RpcError::new(
+            INVALID_PARAMS,
+            format!("\"{}\" must be a string", name),
+        )),
+    }
+}
+
+/// What the user is asked with `--confirm`
+fn confirmation_question(method: &str, params: &Value) -> String {
+    match (param(params, "name"), param(params, "query")) {
+        (Ok(name), _) => format!("Allow a program to {} the password of {}?", method, name),
+        (_, Ok(query)) => format!("Allow a program to search for \"{}\"?", query),
+        _ => format!("Allow a program to {} passwords?", method),
+    }
+}
+
+/// Calls a method, returning whether the store changed along with the result
+fn call(
+    store: &mut PasswordStore,
+    method: &str,
+    params: &Value,
+) -> Result<(Value, bool), RpcError> {
+    match method {
+        "search" => {
+            let entries: Vec<Value> = store
+                .search_passwords(param(params, "query")?)
+                .iter()
+                .map(|password| password.to_json(false))
+                .collect();
+            Ok((json!({ "entries": entries }), false))
+        }
+        "get" => {
+            let password = store
+                .get_password(param(params, "name")?)
+                .ok_or(PasswordError::NoSuchAppError)?;
+            Ok((json!({ "entry": password.to_json(true) }), false))
+        }
+        "add" => {
+            let mut password = Password::new(
+                param(params, "name")?,
+                param(params, "username")?,
+                SafeString::from_string(param(params, "password")?.to_owned()),
+            );
+            if let Some(fields) = params.get("fields") {
+                password.fields =
+                    serde_json::from_value::<BTreeMap<String, String>>(fields.clone()).map_err(
+                        |_| RpcError::new(INVALID_PARAMS, "\"fields\" must map names to strings"),
+                    )?;
+            }
+            let entry = password.to_json(false);
+            store.add_password(password)?;
+            Ok((json!({ "entry": entry }), true))
+        }
+        "change" => {
+            let username = optional_param(params, "username")?;
+            let new_password = optional_param(params, "password")?
+                .map(|password| SafeString::from_string(password.to_owned()));
+            if new_password.as_ref().map(|password| password.is_empty()) == Some(true) {
+                return Err(PasswordError::EmptyPasswordError.into());
+            }
+            let password =
+                store.change_password(param(params, "name")?, &|old_password: Password| {
+                    Password {
+                        name: old_password.name,
+                        username: username
+                            .map(|username| username.to_owned())
+                            .unwrap_or(old_password.username),
+                        password: new_password.clone().unwrap_or(old_password.password),
+                        created_at: old_password.created_at,
+                        updated_at: ffi::time(),
+                        fields: old_password.fields,
+                        rotation_days: old_password.rotation_days,
+                        expires_at: old_password.expires_at,
+                    }
+                })?;
+            Ok((json!({ "entry": password.to_json(false) }), true))
+        }
+        "delete" => {
+            let password = store.delete_password(param(params, "name")?)?;
+            Ok((json!({ "entry": password.to_json(false) }), true))
+        }
+        "generate" => {
+            let length = match params.get("length") {
+                None | Some(Value::Null) => None,
+                Some(length) => Some(
+                    length
+                        .as_u64()
+                        .filter(|length| {
+                            (MIN_PASSWORD_LEN as u64..=MAX_PASSWORD_LEN as u64).contains(length)
+                        })
+                        .ok_or_else(|| {
+                            RpcError::new(
+                                INVALID_PARAMS,
+                                format!(
+                                    "\"length\" must be a number from {} to {}",
+                                    MIN_PASSWORD_LEN, MAX_PASSWORD_LEN
+                                ),
+                            )
+                        })? as usize,
+                ),
+            };
+            let alnum = params
+                .get("alnum")
+                .and_then(|alnum| alnum.as_bool())
+                .unwrap_or(false);
+            let generated = PasswordSpec::new(alnum, length)
+                .generate_hard_password()
+                .map_err(|err| RpcError::new(INVALID_PARAMS, err))?;
+            let password = Password::new(
+                param(params, "name")?,
+                param(params, "username")?,
+                generated,
+            );
+            let entry = password.to_json(true);
+            store.add_password(password)?;
+            Ok((json!({ "entry": entry }), true))
+        }
+        method => Err(RpcError::new(
+            METHOD_NOT_FOUND,
+            format!("there is no method named \"{}\"", method),
+        )),
+    }
+}
+
+/// Calls a method on top of the password file as it is now, since other Rooster commands may
+/// have changed it, and saves the changes before anyone else can
+fn call_with_file(
+    store: &mut PasswordStore,
+    file: &mut File,
+    method: &str,
+    params: &Value,
+) -> Result<Value, RpcError> {
+    let _lock = FileLock::lock(file).map_err(|err| {
+        RpcError::new(
+            INTERNAL_ERROR,
+            format!("I couldn't lock the password file ({})", err),
+        )
+    })?;
+    store.reload(file).map_err(|err| {
+        RpcError::new(
+            INTERNAL_ERROR,
+            format!("I couldn't read the password file again ({:?})", err),
+        )
+    })?;
+    let (result, changed) = call(store, method, params)?;
+    if changed {
+        store.sync(file).map_err(|err| {
+            RpcError::new(
+                INTERNAL_ERROR,
+                format!("I couldn't save the password file ({:?})", err),
+            )
+        })?;
+    }
+    Ok(result)
+}
+
+/// Answers a request, or returns `None` for notifications, which get no answer
+fn handle_request(
+    request: &str,
+    store: &mut PasswordStore,
+    file: &mut File,
+    confirm: bool,
+) -> Option<Value> {
+    let request: Value = match serde_json::from_str(request) {
+        Ok(request) => request,
+        Err(err) => return Some(response(Value::Null, Err(RpcError::new(PARSE_ERROR, err)))),
+    };
+    let id = request.get("id").cloned();
+    let method = match (request.get("jsonrpc"), request.get("method")) {
+        (Some(Value::String(version)), Some(Value::String(method))) if version == "2.0" => method,
+        _ => {
+            return Some(response(
+                id.unwrap_or(Value::Null),
+                Err(RpcError::new(
+                    INVALID_REQUEST,
+                    "this is not a JSON-RPC 2.0 request",
+                )),
+            ))
+        }
+    };
+    let params = request.get("params").cloned().unwrap_or_else(|| json!({}));
+
+    let result = if confirm && !approval::approve(&confirmation_question(method, &params)) {
+        Err(RpcError::new(DENIED, "the user said no"))
+    } else {
+        call_with_file(store, file, method, &params)
+    };
+
+    id.map(|id| response(id, result))
+}
+
+fn response(id: Value, result: Result<Value, RpcError>) -> Value {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(err) => json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": err.code, "message": err.message },
+        }),
+    }
+}
+
+/// A request of a client, and where to send the answer
+struct Job {
+    request: SafeString,
+    answer: Sender<Option<Value>>,
+}
+
+/// Passes the requests of a client on, one JSON-RPC request per line, until it disconnects
+fn handle_client(stream: UnixStream, jobs: Sender<Job>) -> std::io::Result<()> {
+    stream.set_read_timeout(Some(IDLE_TIMEOUT))?;
+    let mut reader = BufReader::new(&stream);
+    let mut writer = &stream;
+    loop {
+        let mut request = SafeString::new();
+        if reader.read_line(&mut request)? == 0 {
+            return Ok(());
+        }
+        if request.trim().is_empty() {
+            continue;
+        }
+        let (answer, answers) = mpsc::channel();
+        if jobs.send(Job { request, answer }).is_err() {
+            return Ok(());
+        }
+        if let Ok(Some(response)) = answers.recv() {
+            let response = SafeString::from_string(response.to_string());
+            writer.write_all(response.as_bytes())?;
+            writer.write_all(b"\n")?;
+        }
+    }
+}
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    file: &mut File,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let socket = Path::new(matches.value_of("socket").unwrap());
+    let confirm = matches.is_present("confirm");
+
+    let listener = agent::bind(socket).map_err(|err| {
+        io.error(
+            format!(
+                "Woops, I couldn't listen on {} (reason: {:?}).",
+                socket.to_string_lossy(),
+                err
+            ),
+            OutputType::Error,
+        );
+        1
+    })?;
+
+    // The passwords stay in memory for as long as we serve them.
+    agent::disable_core_dumps();
+
+    io.success(
+        format!("Rooster is listening on {}.", socket.to_string_lossy()),
+        OutputType::Standard,
+    );
+
+    // Each client gets a thread, so that a quiet one doesn't keep the others waiting, but
+    // requests are answered one at a time, here.
+    let (jobs, requests) = mpsc::channel::<Job>();
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(_) => continue,
+            };
+
+            // Only processes of the user who started us get an answer.
+            if !peer_cred::is_same_user(&stream) {
+                continue;
+            }
+
+            // A client going away or staying idle for too long is only a problem for that client.
+            let jobs = jobs.clone();
+            thread::spawn(move || handle_client(stream, jobs));
+        }
+    });
+
+    for job in requests.iter() {
+        let response = handle_request(job.request.trim(), store, file, confirm);
+        let _ = job.answer.send(response);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::confirmation_question;
+    use serde_json::json;
+
+    #[test]
+    fn test_confirmation_question() {
+        assert_eq!(
+            confirmation_question("get", &json!({ "name": "prod-db" })),
+            "Allow a program to get the password of prod-db?"
+        );
+        assert_eq!(
+            confirmation_question("search", &json!({ "query": "db" })),
+            "Allow a program to search for \"db\"?"
+        );
+        assert_eq!(
+            confirmation_question("list", &json!({})),
+            "Allow a program to list passwords?"
+        );
+    }
+}
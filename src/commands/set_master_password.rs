@@ -1,6 +1,7 @@
 use crate::password;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use crate::strength;
 use std::ops::Deref;
 
 pub fn callback_exec(
@@ -33,6 +34,12 @@ pub fn callback_exec(
                 return Err(1);
             }
 
+            strength::check_master_password(
+                master_password.deref(),
+                strength::min_master_password_score(),
+                io,
+            )?;
+
             store.change_master_password(master_password.deref());
         }
         Err(err) => {
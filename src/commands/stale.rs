@@ -0,0 +1,58 @@
+use crate::expiry;
+use crate::ffi;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use serde_json::json;
+
+pub fn callback_exec(
+    matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let within_days = matches
+        .value_of("within")
+        .and_then(|days| days.parse::<u32>().ok())
+        .unwrap_or(0);
+    let now = ffi::time() as i64;
+    let cutoff = now.saturating_add(i64::from(within_days) * 86400);
+
+    let mut stale: Vec<(i64, &password::v2::Password)> = store
+        .get_all_passwords()
+        .into_iter()
+        .filter_map(|password| expiry::due_at(password).map(|due_at| (due_at, password)))
+        .filter(|(due_at, _)| *due_at <= cutoff)
+        .collect();
+    stale.sort_by_key(|(due_at, _)| *due_at);
+    io.result(json!({
+        "stale": stale
+            .iter()
+            .map(|(due_at, password)| json!({
+                "entry": password.to_json(false),
+                "due_at": due_at,
+            }))
+            .collect::<Vec<_>>()
+    }));
+
+    if stale.is_empty() {
+        io.success("No password needs to be changed.", OutputType::Standard);
+        return Ok(());
+    }
+
+    for (due_at, password) in stale {
+        let message = format!(
+            "{} ({}): due {} ({})",
+            password.name,
+            password.username,
+            expiry::describe_due(due_at, now),
+            expiry::describe_policy(password).unwrap(),
+        );
+        if due_at <= now {
+            io.warning(message, OutputType::Standard);
+        } else {
+            io.info(message, OutputType::Standard);
+        }
+    }
+
+    Err(1)
+}
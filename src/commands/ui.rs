@@ -0,0 +1,75 @@
+use crate::clip;
+use crate::password;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use crate::ui::terminal::Terminal;
+use crate::ui::{App, Effect};
+
+/// How often the screen is redrawn when nothing happens, to follow terminal resizes
+const REDRAW_INTERVAL_MS: i32 = 250;
+
+pub fn callback_exec(
+    _matches: &clap::ArgMatches,
+    store: &mut password::v2::PasswordStore,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let mut terminal = match Terminal::open() {
+        Ok(terminal) => terminal,
+        Err(err) => {
+            io.error(
+                format!("Woops, `rooster ui` needs a terminal (reason: {}).", err),
+                OutputType::Error,
+            );
+            return Err(1);
+        }
+    };
+
+    let mut app = App::new(store);
+    let mut last_size = None;
+    let mut needs_redraw = true;
+    let result = 'ui: loop {
+        let size = terminal.size();
+        if needs_redraw || last_size != Some(size) {
+            let lines = app.render(store, size.0, size.1);
+            if let Err(err) = terminal.draw(&lines) {
+                break 'ui Err(err);
+            }
+            last_size = Some(size);
+        }
+
+        let keys = match terminal.read_keys(REDRAW_INTERVAL_MS) {
+            Ok(keys) => keys,
+            Err(err) => break 'ui Err(err),
+        };
+        needs_redraw = !keys.is_empty();
+        for key in keys {
+            match app.handle_key(key, store) {
+                Effect::Nothing => {}
+                Effect::Quit => break 'ui Ok(()),
+                Effect::Copy(what, value) => {
+                    app.set_status(match clip::copy_password_to_clipboard(&value) {
+                        Ok(copied) => format!(
+                            "Copied the {} to the clipboard ({}).",
+                            what,
+                            copied.describe()
+                        ),
+                        Err(_) => format!("Woops, I couldn't copy the {} to the clipboard.", what),
+                    });
+                }
+            }
+        }
+    };
+    drop(terminal);
+
+    // Whatever happened to the terminal, changes made so far are still saved.
+    if let Err(err) = result {
+        io.error(
+            format!("Woops, I lost the terminal (reason: {}).", err),
+            OutputType::Error,
+        );
+    }
+    if app.changed {
+        io.success("Done! I've saved your changes.", OutputType::Standard);
+    }
+    Ok(())
+}
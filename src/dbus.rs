@@ -0,0 +1,505 @@
+pub mod marshal;
+
+use self::marshal::{aligned, Reader, Value, Writer};
+use std::collections::VecDeque;
+use std::io::{
+    BufRead, BufReader, Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Write,
+};
+use std::os::unix::io::FromRawFd;
+use std::os::unix::net::UnixStream;
+
+/// Where programs find the session bus
+pub const DBUS_SESSION_BUS_ADDRESS_ENV_VAR: &str = "DBUS_SESSION_BUS_ADDRESS";
+
+const BUS_NAME: &str = "org.freedesktop.DBus";
+const BUS_PATH: &str = "/org/freedesktop/DBus";
+
+/// The bus refuses bigger messages anyway
+const MAX_MESSAGE_LEN: usize = 128 * 1024 * 1024;
+
+/// Set on calls that don't want an answer
+pub const NO_REPLY_EXPECTED: u8 = 0x1;
+
+// Header fields, from the D-Bus specification
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SENDER: u8 = 7;
+const FIELD_SIGNATURE: u8 = 8;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MessageType {
+    MethodCall = 1,
+    MethodReturn = 2,
+    Error = 3,
+    Signal = 4,
+}
+
+/// A message on the bus, which only knows about the header fields we use
+#[derive(Clone, Debug, PartialEq)]
+pub struct Message {
+    pub message_type: MessageType,
+    pub flags: u8,
+    pub serial: u32,
+    pub path: Option<String>,
+    pub interface: Option<String>,
+    pub member: Option<String>,
+    pub error_name: Option<String>,
+    pub reply_serial: Option<u32>,
+    pub destination: Option<String>,
+    pub sender: Option<String>,
+    pub body: Vec<Value>,
+}
+
+impl Message {
+    fn new(message_type: MessageType, body: Vec<Value>) -> Message {
+        Message {
+            message_type,
+            flags: 0,
+            serial: 0,
+            path: None,
+            interface: None,
+            member: None,
+            error_name: None,
+            reply_serial: None,
+            destination: None,
+            sender: None,
+            body,
+        }
+    }
+
+    pub fn method_call(
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> Message {
+        Message {
+            path: Some(path.to_owned()),
+            interface: Some(interface.to_owned()),
+            member: Some(member.to_owned()),
+            destination: Some(destination.to_owned()),
+            ..Message::new(MessageType::MethodCall, body)
+        }
+    }
+
+    pub fn signal(path: &str, interface: &str, member: &str, body: Vec<Value>) -> Message {
+        Message {
+            path: Some(path.to_owned()),
+            interface: Some(interface.to_owned()),
+            member: Some(member.to_owned()),
+            ..Message::new(MessageType::Signal, body)
+        }
+    }
+
+    /// The answer to this call
+    pub fn method_return(&self, body: Vec<Value>) -> Message {
+        Message {
+            reply_serial: Some(self.serial),
+            destination: self.sender.clone(),
+            ..Message::new(MessageType::MethodReturn, body)
+        }
+    }
+
+    /// The error answering this call, like `org.freedesktop.DBus.Error.InvalidArgs`
+    pub fn error(&self, name: &str, message: &str) -> Message {
+        Message {
+            error_name: Some(name.to_owned()),
+            reply_serial: Some(self.serial),
+            destination: self.sender.clone(),
+            ..Message::new(MessageType::Error, vec![Value::Str(message.to_owned())])
+        }
+    }
+
+    pub fn signature(&self) -> String {
+        self.body.iter().map(|value| value.signature()).collect()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut fields = Vec::new();
+        let mut field = |code: u8, value: Value| {
+            fields.push(Value::Struct(vec![
+                Value::Byte(code),
+                Value::Variant(Box::new(value)),
+            ]));
+        };
+        if let Some(ref path) = self.path {
+            field(FIELD_PATH, Value::ObjectPath(path.clone()));
+        }
+        if let Some(ref interface) = self.interface {
+            field(FIELD_INTERFACE, Value::Str(interface.clone()));
+        }
+        if let Some(ref member) = self.member {
+            field(FIELD_MEMBER, Value::Str(member.clone()));
+        }
+        if let Some(ref error_name) = self.error_name {
+            field(FIELD_ERROR_NAME, Value::Str(error_name.clone()));
+        }
+        if let Some(reply_serial) = self.reply_serial {
+            field(FIELD_REPLY_SERIAL, Value::Uint32(reply_serial));
+        }
+        if let Some(ref destination) = self.destination {
+            field(FIELD_DESTINATION, Value::Str(destination.clone()));
+        }
+        if !self.body.is_empty() {
+            field(FIELD_SIGNATURE, Value::Signature(self.signature()));
+        }
+
+        let mut body = Writer::new();
+        for value in self.body.iter() {
+            body.write(value);
+        }
+
+        let mut message = Writer::new();
+        message
+            .buf
+            .extend_from_slice(&[b'l', self.message_type as u8, self.flags, 1]);
+        message.write_u32(body.buf.len() as u32);
+        message.write_u32(self.serial);
+        message.write(&Value::Array("(yv)".to_owned(), fields));
+        // The body starts on 8 bytes, so it's aligned the same on its own as in the message.
+        message.align(8);
+        message.buf.extend_from_slice(&body.buf);
+        message.buf
+    }
+
+    fn read_from(stream: &mut impl Read) -> IoResult<Message> {
+        let mut fixed = [0u8; 16];
+        stream.read_exact(&mut fixed)?;
+        let big_endian = match fixed[0] {
+            b'l' => false,
+            b'B' => true,
+            _ => return Err(invalid("the bus sent a message we can't read")),
+        };
+        let number = |bytes: &[u8]| {
+            let mut number = [0u8; 4];
+            number.copy_from_slice(bytes);
+            if big_endian {
+                u32::from_be_bytes(number) as usize
+            } else {
+                u32::from_le_bytes(number) as usize
+            }
+        };
+        let body_len = number(&fixed[4..8]);
+        let fields_len = number(&fixed[12..16]);
+        let header_len = aligned(16 + fields_len, 8);
+        if header_len + body_len > MAX_MESSAGE_LEN {
+            return Err(invalid("the bus sent a message that is too big"));
+        }
+
+        let mut buf = fixed.to_vec();
+        buf.resize(header_len + body_len, 0);
+        stream.read_exact(&mut buf[16..])?;
+
+        let message_type = match fixed[1] {
+            1 => MessageType::MethodCall,
+            2 => MessageType::MethodReturn,
+            3 => MessageType::Error,
+            4 => MessageType::Signal,
+            _ => return Err(invalid("the bus sent a message of an unknown type")),
+        };
+        let mut message = Message {
+            flags: fixed[2],
+            serial: number(&fixed[8..12]) as u32,
+            ..Message::new(message_type, Vec::new())
+        };
+
+        let mut signature = String::new();
+        let mut reader = Reader::new(&buf[..16 + fields_len], 12, big_endian);
+        if let Value::Array(_, fields) = reader.read(b"a(yv)")? {
+            for field in fields {
+                let (code, value) = match field {
+                    Value::Struct(ref code_and_value) => {
+                        match (&code_and_value[0], code_and_value[1].unwrap_variant()) {
+                            (Value::Byte(code), value) => (*code, value.clone()),
+                            _ => continue,
+                        }
+                    }
+                    _ => continue,
+                };
+                let string = value.as_str().map(String::from);
+                match code {
+                    FIELD_PATH => message.path = string,
+                    FIELD_INTERFACE => message.interface = string,
+                    FIELD_MEMBER => message.member = string,
+                    FIELD_ERROR_NAME => message.error_name = string,
+                    FIELD_REPLY_SERIAL => {
+                        if let Value::Uint32(serial) = value {
+                            message.reply_serial = Some(serial);
+                        }
+                    }
+                    FIELD_DESTINATION => message.destination = string,
+                    FIELD_SENDER => message.sender = string,
+                    FIELD_SIGNATURE => signature = string.unwrap_or_default(),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut reader = Reader::new(&buf, header_len, big_endian);
+        message.body = reader.read_all(&signature)?;
+        Ok(message)
+    }
+}
+
+fn invalid(message: &str) -> IoError {
+    IoError::new(IoErrorKind::InvalidData, message.to_owned())
+}
+
+/// Connects to a socket in the abstract namespace, which `std` can't do
+fn connect_abstract(name: &str) -> IoResult<UnixStream> {
+    let mut address: libc::sockaddr_un = unsafe { std::mem::zeroed() };
+    address.sun_family = libc::AF_UNIX as libc::sa_family_t;
+    // The name starts after a nul byte.
+    if name.len() >= address.sun_path.len() {
+        return Err(IoError::new(
+            IoErrorKind::InvalidInput,
+            "the bus address is too long",
+        ));
+    }
+    for (i, b) in name.bytes().enumerate() {
+        address.sun_path[i + 1] = b as libc::c_char;
+    }
+    let address_len = std::mem::size_of::<libc::sa_family_t>() + 1 + name.len();
+
+    unsafe {
+        // Not `SOCK_CLOEXEC`, which macOS doesn't have.
+        let fd = libc::socket(libc::AF_UNIX, libc::SOCK_STREAM, 0);
+        if fd < 0 {
+            return Err(IoError::last_os_error());
+        }
+        let stream = UnixStream::from_raw_fd(fd);
+        if libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) < 0 {
+            return Err(IoError::last_os_error());
+        }
+        if libc::connect(
+            fd,
+            &address as *const libc::sockaddr_un as *const libc::sockaddr,
+            address_len as libc::socklen_t,
+        ) < 0
+        {
+            return Err(IoError::last_os_error());
+        }
+        Ok(stream)
+    }
+}
+
+/// Connects to the first address we know how to, among addresses like
+/// `unix:path=/run/user/1000/bus;unix:abstract=/tmp/dbus-XXXX,guid=...`
+fn connect(addresses: &str) -> IoResult<UnixStream> {
+    for address in addresses.split(';') {
+        let mut parts = address.splitn(2, ':');
+        if parts.next() != Some("unix") {
+            continue;
+        }
+        for key_value in parts.next().unwrap_or("").split(',') {
+            if let Some(path) = key_value.strip_prefix("path=") {
+                return UnixStream::connect(unescape_address(path));
+            }
+            if let Some(name) = key_value.strip_prefix("abstract=") {
+                return connect_abstract(&unescape_address(name));
+            }
+        }
+    }
+    Err(IoError::new(
+        IoErrorKind::InvalidInput,
+        format!("I don't know how to connect to \"{}\"", addresses),
+    ))
+}
+
+/// Addresses escape bytes as `%XX`
+fn unescape_address(value: &str) -> String {
+    let mut bytes = Vec::new();
+    let mut rest = value.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        let escaped = std::str::from_utf8(tail.get(..2).unwrap_or(&[]))
+            .ok()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (b, escaped) {
+            (b'%', Some(escaped)) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            _ => {
+                bytes.push(b);
+                rest = tail;
+            }
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// A connection to a message bus, as a single unique name
+pub struct Connection {
+    stream: BufReader<UnixStream>,
+    next_serial: u32,
+    /// Messages that came while we waited for an answer
+    pending: VecDeque<Message>,
+    pub unique_name: String,
+}
+
+impl Connection {
+    /// Connects to the session bus of the environment
+    pub fn session() -> IoResult<Connection> {
+        match std::env::var(DBUS_SESSION_BUS_ADDRESS_ENV_VAR) {
+            Ok(ref address) if !address.is_empty() => Connection::open(address),
+            _ => Err(IoError::new(
+                IoErrorKind::NotFound,
+                format!("{} is not set", DBUS_SESSION_BUS_ADDRESS_ENV_VAR),
+            )),
+        }
+    }
+
+    /// Connects to a bus, proving who we are with our user ID
+    pub fn open(address: &str) -> IoResult<Connection> {
+        let stream = connect(address)?;
+        let mut connection = Connection {
+            stream: BufReader::new(stream),
+            next_serial: 1,
+            pending: VecDeque::new(),
+            unique_name: String::new(),
+        };
+        connection.authenticate()?;
+
+        let reply = connection.call(BUS_NAME, BUS_PATH, BUS_NAME, "Hello", Vec::new())?;
+        connection.unique_name = reply
+            .body
+            .first()
+            .and_then(|name| name.as_str())
+            .unwrap_or("")
+            .to_owned();
+        Ok(connection)
+    }
+
+    fn authenticate(&mut self) -> IoResult<()> {
+        let uid = unsafe { libc::getuid() }.to_string();
+        let hex_uid: String = uid.bytes().map(|b| format!("{:02x}", b)).collect();
+        let stream = self.stream.get_mut();
+        stream.write_all(b"\0")?;
+        stream.write_all(format!("AUTH EXTERNAL {}\r\n", hex_uid).as_bytes())?;
+
+        let mut line = String::new();
+        self.stream.read_line(&mut line)?;
+        if !line.starts_with("OK ") {
+            return Err(IoError::new(
+                IoErrorKind::PermissionDenied,
+                "the bus didn't let us in",
+            ));
+        }
+        self.stream.get_mut().write_all(b"BEGIN\r\n")
+    }
+
+    /// Sends a message, returning its serial
+    pub fn send(&mut self, mut message: Message) -> IoResult<u32> {
+        message.serial = self.next_serial;
+        self.next_serial = self.next_serial.wrapping_add(1).max(1);
+        self.stream.get_mut().write_all(&message.to_bytes())?;
+        Ok(message.serial)
+    }
+
+    /// Waits for the next message
+    pub fn receive(&mut self) -> IoResult<Message> {
+        match self.pending.pop_front() {
+            Some(message) => Ok(message),
+            None => Message::read_from(&mut self.stream),
+        }
+    }
+
+    /// Calls a method and waits for the answer, which may be an error
+    pub fn call(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> IoResult<Message> {
+        let serial = self.send(Message::method_call(
+            destination,
+            path,
+            interface,
+            member,
+            body,
+        ))?;
+        loop {
+            let message = Message::read_from(&mut self.stream)?;
+            if message.reply_serial == Some(serial)
+                && (message.message_type == MessageType::MethodReturn
+                    || message.message_type == MessageType::Error)
+            {
+                return Ok(message);
+            }
+            self.pending.push_back(message);
+        }
+    }
+
+    /// Asks the bus for a well-known name, failing if someone else has it
+    pub fn request_name(&mut self, name: &str) -> IoResult<()> {
+        // DBUS_NAME_FLAG_DO_NOT_QUEUE
+        let flags = 0x4;
+        let reply = self.call(
+            BUS_NAME,
+            BUS_PATH,
+            BUS_NAME,
+            "RequestName",
+            vec![Value::Str(name.to_owned()), Value::Uint32(flags)],
+        )?;
+        match reply.body.first() {
+            // DBUS_REQUEST_NAME_REPLY_PRIMARY_OWNER and DBUS_REQUEST_NAME_REPLY_ALREADY_OWNER
+            Some(Value::Uint32(1)) | Some(Value::Uint32(4)) => Ok(()),
+            _ => Err(IoError::new(
+                IoErrorKind::AddrInUse,
+                format!("another program owns {}", name),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::marshal::Value;
+    use super::{unescape_address, Message, MessageType};
+    use std::io::Cursor;
+
+    #[test]
+    fn test_unescape_address() {
+        assert_eq!(unescape_address("/run/user/1000/bus"), "/run/user/1000/bus");
+        assert_eq!(unescape_address("/tmp/a%20b%2"), "/tmp/a b%2");
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let mut call = Message::method_call(
+            "org.freedesktop.secrets",
+            "/org/freedesktop/secrets",
+            "org.freedesktop.Secret.Service",
+            "OpenSession",
+            vec![
+                Value::Str("plain".to_owned()),
+                Value::Variant(Box::new(Value::Str(String::new()))),
+            ],
+        );
+        call.serial = 3;
+        call.sender = Some(":1.7".to_owned());
+
+        let bytes = call.to_bytes();
+        let read = Message::read_from(&mut Cursor::new(&bytes)).unwrap();
+        // We never send a sender, the bus adds it.
+        assert_eq!(
+            read,
+            Message {
+                sender: None,
+                ..call.clone()
+            }
+        );
+
+        let reply = call.method_return(vec![Value::ObjectPath("/".to_owned())]);
+        assert_eq!(reply.message_type, MessageType::MethodReturn);
+        assert_eq!(reply.reply_serial, Some(3));
+        assert_eq!(reply.destination, Some(":1.7".to_owned()));
+    }
+}
@@ -0,0 +1,448 @@
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+
+/// A value as D-Bus sends it, along with enough to know its type
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Byte(u8),
+    Bool(bool),
+    Int16(i16),
+    Uint16(u16),
+    Int32(i32),
+    Uint32(u32),
+    Int64(i64),
+    Uint64(u64),
+    Double(f64),
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+    Variant(Box<Value>),
+    /// `ay`, which is how secrets are sent
+    Bytes(Vec<u8>),
+    /// The signature of the elements, which empty arrays need too, and the elements
+    Array(String, Vec<Value>),
+    Struct(Vec<Value>),
+    DictEntry(Box<Value>, Box<Value>),
+}
+
+impl Value {
+    pub fn signature(&self) -> String {
+        match self {
+            Value::Byte(_) => "y".to_owned(),
+            Value::Bool(_) => "b".to_owned(),
+            Value::Int16(_) => "n".to_owned(),
+            Value::Uint16(_) => "q".to_owned(),
+            Value::Int32(_) => "i".to_owned(),
+            Value::Uint32(_) => "u".to_owned(),
+            Value::Int64(_) => "x".to_owned(),
+            Value::Uint64(_) => "t".to_owned(),
+            Value::Double(_) => "d".to_owned(),
+            Value::Str(_) => "s".to_owned(),
+            Value::ObjectPath(_) => "o".to_owned(),
+            Value::Signature(_) => "g".to_owned(),
+            Value::Variant(_) => "v".to_owned(),
+            Value::Bytes(_) => "ay".to_owned(),
+            Value::Array(signature, _) => format!("a{}", signature),
+            Value::Struct(fields) => format!(
+                "({})",
+                fields
+                    .iter()
+                    .map(|field| field.signature())
+                    .collect::<String>()
+            ),
+            Value::DictEntry(key, value) => format!("{{{}{}}}", key.signature(), value.signature()),
+        }
+    }
+
+    /// A dictionary, like `a{sv}`, from its entries
+    pub fn dict(key_signature: &str, value_signature: &str, entries: Vec<(Value, Value)>) -> Value {
+        Value::Array(
+            format!("{{{}{}}}", key_signature, value_signature),
+            entries
+                .into_iter()
+                .map(|(key, value)| Value::DictEntry(Box::new(key), Box::new(value)))
+                .collect(),
+        )
+    }
+
+    /// An array of object paths, `ao`
+    pub fn object_paths<S: ToString>(paths: &[S]) -> Value {
+        Value::Array(
+            "o".to_owned(),
+            paths
+                .iter()
+                .map(|path| Value::ObjectPath(path.to_string()))
+                .collect(),
+        )
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Str(s) | Value::ObjectPath(s) | Value::Signature(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// The entries of a dictionary, like `a{ss}` or `a{sv}`
+    pub fn as_dict(&self) -> Option<Vec<(&Value, &Value)>> {
+        match self {
+            Value::Array(_, entries) => entries
+                .iter()
+                .map(|entry| match entry {
+                    Value::DictEntry(key, value) => Some((key.as_ref(), value.as_ref())),
+                    _ => None,
+                })
+                .collect(),
+            _ => None,
+        }
+    }
+
+    /// What a variant holds, or the value itself
+    pub fn unwrap_variant(&self) -> &Value {
+        match self {
+            Value::Variant(value) => value.unwrap_variant(),
+            value => value,
+        }
+    }
+}
+
+fn invalid(message: &str) -> IoError {
+    IoError::new(IoErrorKind::InvalidData, message.to_owned())
+}
+
+/// How many bytes values of this type are aligned on
+fn alignment(signature: u8) -> usize {
+    match signature {
+        b'y' | b'g' | b'v' => 1,
+        b'n' | b'q' => 2,
+        b'x' | b't' | b'd' | b'(' | b'{' => 8,
+        _ => 4,
+    }
+}
+
+/// Rounds up to a multiple of an alignment, which is always a power of two
+pub fn aligned(len: usize, alignment: usize) -> usize {
+    (len + alignment - 1) & !(alignment - 1)
+}
+
+/// The length of the first complete type of a signature, like `a{sv}` in `a{sv}s`
+pub fn first_type_len(signature: &[u8]) -> IoResult<usize> {
+    match signature.first() {
+        None => Err(invalid("the signature ended early")),
+        Some(b'a') => Ok(1 + first_type_len(&signature[1..])?),
+        Some(&open @ b'(') | Some(&open @ b'{') => {
+            let close = if open == b'(' { b')' } else { b'}' };
+            let mut len = 1;
+            while signature.get(len) != Some(&close) {
+                len += first_type_len(&signature[len..])?;
+            }
+            Ok(len + 1)
+        }
+        Some(b')') | Some(b'}') => Err(invalid("the signature has an unexpected bracket")),
+        Some(_) => Ok(1),
+    }
+}
+
+/// Splits a signature like `sa{sv}` into its complete types, `s` and `a{sv}`
+pub fn split_signature(signature: &str) -> IoResult<Vec<&str>> {
+    let mut types = Vec::new();
+    let mut rest = signature;
+    while !rest.is_empty() {
+        let len = first_type_len(rest.as_bytes())?;
+        types.push(&rest[..len]);
+        rest = &rest[len..];
+    }
+    Ok(types)
+}
+
+/// Marshals values in little endian, aligned from the start of the message
+pub struct Writer {
+    pub buf: Vec<u8>,
+}
+
+impl Writer {
+    pub fn new() -> Writer {
+        Writer { buf: Vec::new() }
+    }
+
+    pub fn align(&mut self, alignment: usize) {
+        let len = aligned(self.buf.len(), alignment);
+        self.buf.resize(len, 0);
+    }
+
+    pub fn write_u32(&mut self, n: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&n.to_le_bytes());
+    }
+
+    fn write_str(&mut self, s: &str) {
+        self.write_u32(s.len() as u32);
+        self.buf.extend_from_slice(s.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn write_signature(&mut self, s: &str) {
+        self.buf.push(s.len() as u8);
+        self.buf.extend_from_slice(s.as_bytes());
+        self.buf.push(0);
+    }
+
+    /// Writes an array, whose length is only known once its elements are written
+    fn write_array<F: FnOnce(&mut Writer)>(&mut self, element_signature: &str, write_elements: F) {
+        self.write_u32(0);
+        let len_pos = self.buf.len() - 4;
+        self.align(alignment(element_signature.as_bytes()[0]));
+        let start = self.buf.len();
+        write_elements(self);
+        let len = (self.buf.len() - start) as u32;
+        self.buf[len_pos..len_pos + 4].copy_from_slice(&len.to_le_bytes());
+    }
+
+    pub fn write(&mut self, value: &Value) {
+        match value {
+            Value::Byte(n) => self.buf.push(*n),
+            Value::Bool(b) => self.write_u32(*b as u32),
+            Value::Int16(n) => {
+                self.align(2);
+                self.buf.extend_from_slice(&n.to_le_bytes());
+            }
+            Value::Uint16(n) => {
+                self.align(2);
+                self.buf.extend_from_slice(&n.to_le_bytes());
+            }
+            Value::Int32(n) => {
+                self.align(4);
+                self.buf.extend_from_slice(&n.to_le_bytes());
+            }
+            Value::Uint32(n) => self.write_u32(*n),
+            Value::Int64(n) => {
+                self.align(8);
+                self.buf.extend_from_slice(&n.to_le_bytes());
+            }
+            Value::Uint64(n) => {
+                self.align(8);
+                self.buf.extend_from_slice(&n.to_le_bytes());
+            }
+            Value::Double(n) => {
+                self.align(8);
+                self.buf.extend_from_slice(&n.to_bits().to_le_bytes());
+            }
+            Value::Str(s) | Value::ObjectPath(s) => self.write_str(s),
+            Value::Signature(s) => self.write_signature(s),
+            Value::Variant(value) => {
+                self.write_signature(&value.signature());
+                self.write(value);
+            }
+            Value::Bytes(bytes) => {
+                self.write_array("y", |writer| writer.buf.extend_from_slice(bytes));
+            }
+            Value::Array(signature, elements) => {
+                self.write_array(signature, |writer| {
+                    for element in elements {
+                        writer.write(element);
+                    }
+                });
+            }
+            Value::Struct(fields) => {
+                self.align(8);
+                for field in fields {
+                    self.write(field);
+                }
+            }
+            Value::DictEntry(key, value) => {
+                self.align(8);
+                self.write(key);
+                self.write(value);
+            }
+        }
+    }
+}
+
+/// Unmarshals values, aligned from the start of the message
+pub struct Reader<'a> {
+    buf: &'a [u8],
+    pub pos: usize,
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(buf: &'a [u8], pos: usize, big_endian: bool) -> Reader<'a> {
+        Reader {
+            buf,
+            pos,
+            big_endian,
+        }
+    }
+
+    pub fn align(&mut self, alignment: usize) -> IoResult<()> {
+        let pos = aligned(self.pos, alignment);
+        if pos > self.buf.len() {
+            return Err(invalid("the message ended early"));
+        }
+        self.pos = pos;
+        Ok(())
+    }
+
+    fn take(&mut self, len: usize) -> IoResult<&'a [u8]> {
+        if self.buf.len() - self.pos < len {
+            return Err(invalid("the message ended early"));
+        }
+        let bytes = &self.buf[self.pos..self.pos + len];
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    /// Reads a number of `N` bytes, aligned on its size
+    fn read_bytes<const N: usize>(&mut self) -> IoResult<[u8; N]> {
+        self.align(N)?;
+        let mut bytes = [0u8; N];
+        bytes.copy_from_slice(self.take(N)?);
+        if self.big_endian {
+            bytes.reverse();
+        }
+        Ok(bytes)
+    }
+
+    pub fn read_u32(&mut self) -> IoResult<u32> {
+        Ok(u32::from_le_bytes(self.read_bytes::<4>()?))
+    }
+
+    fn read_str(&mut self, len: usize) -> IoResult<String> {
+        let bytes = self.take(len)?;
+        if self.take(1)? != [0] {
+            return Err(invalid("a string isn't terminated"));
+        }
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("a string isn't UTF-8"))
+    }
+
+    fn read_signature(&mut self) -> IoResult<String> {
+        let len = self.take(1)?[0] as usize;
+        self.read_str(len)
+    }
+
+    /// Reads the values of a signature like `sa{sv}`
+    pub fn read_all(&mut self, signature: &str) -> IoResult<Vec<Value>> {
+        split_signature(signature)?
+            .into_iter()
+            .map(|signature| self.read(signature.as_bytes()))
+            .collect()
+    }
+
+    /// Reads a value of a single complete type
+    pub fn read(&mut self, signature: &[u8]) -> IoResult<Value> {
+        let value = match signature[0] {
+            b'y' => Value::Byte(self.take(1)?[0]),
+            b'b' => Value::Bool(self.read_u32()? != 0),
+            b'n' => Value::Int16(i16::from_le_bytes(self.read_bytes::<2>()?)),
+            b'q' => Value::Uint16(u16::from_le_bytes(self.read_bytes::<2>()?)),
+            b'i' => Value::Int32(i32::from_le_bytes(self.read_bytes::<4>()?)),
+            b'u' => Value::Uint32(self.read_u32()?),
+            b'x' => Value::Int64(i64::from_le_bytes(self.read_bytes::<8>()?)),
+            b't' => Value::Uint64(u64::from_le_bytes(self.read_bytes::<8>()?)),
+            b'd' => Value::Double(f64::from_bits(u64::from_le_bytes(self.read_bytes::<8>()?))),
+            b's' => {
+                let len = self.read_u32()? as usize;
+                Value::Str(self.read_str(len)?)
+            }
+            b'o' => {
+                let len = self.read_u32()? as usize;
+                Value::ObjectPath(self.read_str(len)?)
+            }
+            b'g' => Value::Signature(self.read_signature()?),
+            b'v' => {
+                let signature = self.read_signature()?;
+                if split_signature(&signature)?.len() != 1 {
+                    return Err(invalid("a variant must hold a single value"));
+                }
+                Value::Variant(Box::new(self.read(signature.as_bytes())?))
+            }
+            b'a' => {
+                let len = self.read_u32()? as usize;
+                let element_signature = &signature[1..];
+                self.align(alignment(element_signature[0]))?;
+                if element_signature == b"y" {
+                    return Ok(Value::Bytes(self.take(len)?.to_vec()));
+                }
+                let end = self.pos + len;
+                if end > self.buf.len() {
+                    return Err(invalid("the message ended early"));
+                }
+                let mut elements = Vec::new();
+                while self.pos < end {
+                    elements.push(self.read(element_signature)?);
+                }
+                Value::Array(
+                    String::from_utf8_lossy(element_signature).into_owned(),
+                    elements,
+                )
+            }
+            b'(' => {
+                self.align(8)?;
+                let signature = std::str::from_utf8(&signature[1..signature.len() - 1])
+                    .map_err(|_| invalid("a signature isn't ASCII"))?;
+                Value::Struct(self.read_all(signature)?)
+            }
+            b'{' => {
+                self.align(8)?;
+                let key_len = first_type_len(&signature[1..])?;
+                let key = self.read(&signature[1..1 + key_len])?;
+                let value = self.read(&signature[1 + key_len..signature.len() - 1])?;
+                Value::DictEntry(Box::new(key), Box::new(value))
+            }
+            _ => return Err(invalid("the message has a type we don't know")),
+        };
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{split_signature, Reader, Value, Writer};
+
+    #[test]
+    fn test_split_signature() {
+        assert_eq!(
+            split_signature("sa{sv}(oayays)ao").unwrap(),
+            vec!["s", "a{sv}", "(oayays)", "ao"]
+        );
+        assert!(split_signature("a{sv").is_err());
+        assert!(split_signature("s)").is_err());
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let values = vec![
+            Value::Byte(7),
+            Value::Str("hello".to_owned()),
+            Value::dict(
+                "s",
+                "v",
+                vec![(
+                    Value::Str("Label".to_owned()),
+                    Value::Variant(Box::new(Value::Str("Example".to_owned()))),
+                )],
+            ),
+            Value::Struct(vec![
+                Value::ObjectPath("/session/1".to_owned()),
+                Value::Bytes(Vec::new()),
+                Value::Bytes(b"p@ssw0rd".to_vec()),
+                Value::Str("text/plain".to_owned()),
+            ]),
+            Value::Uint64(1 << 40),
+            Value::object_paths::<&str>(&[]),
+            Value::Bool(true),
+        ];
+        let signature: String = values.iter().map(|value| value.signature()).collect();
+        assert_eq!(signature, "ysa{sv}(oayays)taob");
+
+        let mut writer = Writer::new();
+        for value in values.iter() {
+            writer.write(value);
+        }
+        // Dictionary entries and structs are aligned on 8 bytes
+        assert_eq!(&writer.buf[..16], b"\x07\0\0\0\x05\0\0\0hello\0\0\0");
+
+        let mut reader = Reader::new(&writer.buf, 0, false);
+        assert_eq!(reader.read_all(&signature).unwrap(), values);
+        assert_eq!(reader.pos, writer.buf.len());
+    }
+}
@@ -0,0 +1,148 @@
+use crate::password::v2::Password;
+
+const SECONDS_PER_DAY: i64 = 86400;
+
+/// When a password needs to be changed, according to its rotation interval and expiry date
+pub fn due_at(password: &Password) -> Option<i64> {
+    let rotation_due_at = password
+        .rotation_days
+        .map(|days| password.updated_at as i64 + days as i64 * SECONDS_PER_DAY);
+    let expires_at = password.expires_at.map(|expires_at| expires_at as i64);
+    match (rotation_due_at, expires_at) {
+        (Some(rotation_due_at), Some(expires_at)) => Some(rotation_due_at.min(expires_at)),
+        (rotation_due_at, expires_at) => rotation_due_at.or(expires_at),
+    }
+}
+
+pub fn is_overdue(password: &Password, now: i64) -> bool {
+    match due_at(password) {
+        Some(due_at) => due_at <= now,
+        None => false,
+    }
+}
+
+/// Describes the expiry policy of a password, like "changed every 90 days, expires on 2030-01-31"
+pub fn describe_policy(password: &Password) -> Option<String> {
+    let mut policy = Vec::new();
+    if let Some(days) = password.rotation_days {
+        policy.push(format!("changed every {} days", days));
+    }
+    if let Some(expires_at) = password.expires_at {
+        policy.push(format!("expires on {}", format_date(expires_at as i64)));
+    }
+    if policy.is_empty() {
+        None
+    } else {
+        Some(policy.join(", "))
+    }
+}
+
+/// Describes when a password is due, like "12 days ago", "today" or "in 3 days"
+pub fn describe_due(due_at: i64, now: i64) -> String {
+    let days = ((due_at - now).abs() + SECONDS_PER_DAY / 2) / SECONDS_PER_DAY;
+    let plural = if days == 1 { "" } else { "s" };
+    if days == 0 {
+        "today".to_owned()
+    } else if due_at < now {
+        format!("{} day{} ago", days, plural)
+    } else {
+        format!("in {} day{}", days, plural)
+    }
+}
+
+/// Parses a date like "2030-01-31", as midnight UTC
+pub fn parse_date(date: &str) -> Option<i64> {
+    let parts: Vec<&str> = date.trim().split('-').collect();
+    if parts.len() != 3 || parts[0].len() != 4 || parts[1].len() != 2 || parts[2].len() != 2 {
+        return None;
+    }
+    let year = parts[0].parse::<i64>().ok()?;
+    let month = parts[1].parse::<i64>().ok()?;
+    let day = parts[2].parse::<i64>().ok()?;
+    if year < 1970 || !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return None;
+    }
+    Some(days_from_civil(year, month, day) * SECONDS_PER_DAY)
+}
+
+/// Formats a timestamp as a date like "2030-01-31", in UTC
+pub fn format_date(timestamp: i64) -> String {
+    let (year, month, day) = civil_from_days(timestamp.div_euclid(SECONDS_PER_DAY));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01, see http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{describe_due, due_at, format_date, parse_date};
+    use crate::password::v2::Password;
+
+    #[test]
+    fn test_dates() {
+        assert_eq!(parse_date("1970-01-01"), Some(0));
+        assert_eq!(parse_date("2000-03-01"), Some(951868800));
+        assert_eq!(parse_date("2024-02-29"), Some(1709164800));
+        assert_eq!(parse_date("2023-02-29"), None);
+        assert_eq!(parse_date("2023-13-01"), None);
+        assert_eq!(parse_date("31/01/2030"), None);
+        for date in ["1970-01-01", "2000-02-29", "2024-12-31", "2100-03-01"].iter() {
+            assert_eq!(format_date(parse_date(date).unwrap()), *date);
+        }
+    }
+
+    #[test]
+    fn test_due_at() {
+        let mut password = Password::new("Bank", "alice", "hunter2");
+        password.updated_at = 1000;
+        assert_eq!(due_at(&password), None);
+
+        password.rotation_days = Some(90);
+        assert_eq!(due_at(&password), Some(1000 + 90 * 86400));
+
+        password.expires_at = Some(2000);
+        assert_eq!(due_at(&password), Some(2000));
+    }
+
+    #[test]
+    fn test_describe_due() {
+        assert_eq!(describe_due(0, 12 * 86400), "12 days ago");
+        assert_eq!(describe_due(86400 + 10, 0), "in 1 day");
+        assert_eq!(describe_due(100, 0), "today");
+    }
+}
@@ -1,44 +1,79 @@
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
 use crate::rutil::safe_string::SafeString;
-use rand::{rngs::OsRng, Rng};
-use std::io::Result as IoResult;
-
-fn generate_password(alnum: bool, len: usize) -> IoResult<SafeString> {
-    let mut password_as_string = String::new();
-    let mut rng = OsRng::default();
-    for _ in 0..len {
-        if alnum {
-            match rng.gen_range(0, 3) {
-                // Numbers 0-9
-                0 => password_as_string.push(rng.gen_range(48, 58) as u8 as char),
-                // Uppercase A-Z
-                1 => password_as_string.push(rng.gen_range(65, 91) as u8 as char),
-                // Lowercase a-z
-                2 => password_as_string.push(rng.gen_range(97, 123) as u8 as char),
-                _ => unreachable!(),
-            }
+use crate::rutil::safe_vec::SafeVec;
+use rand::{rngs::OsRng, Rng, RngCore};
+use std::collections::BTreeMap;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+use std::path::Path;
+
+mod ssh_key;
+
+/// Passwords need room for a lowercase letter, an uppercase letter, a digit and a symbol
+pub const MIN_PASSWORD_LEN: usize = 4;
+
+/// Longer than any website accepts, and short enough that nobody can make us run out of memory
+pub const MAX_PASSWORD_LEN: usize = 4096;
+
+/// Characters that are easy to mistake for one another
+pub const AMBIGUOUS_CHARACTERS: &str = "Il1|O0o";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CharClass {
+    Lowercase,
+    Uppercase,
+    Digit,
+    Symbol,
+}
+
+const CHAR_CLASSES: [CharClass; 4] = [
+    CharClass::Lowercase,
+    CharClass::Uppercase,
+    CharClass::Digit,
+    CharClass::Symbol,
+];
+
+impl CharClass {
+    pub fn of(c: char) -> CharClass {
+        if c.is_lowercase() {
+            CharClass::Lowercase
+        } else if c.is_uppercase() {
+            CharClass::Uppercase
+        } else if c.is_numeric() {
+            CharClass::Digit
         } else {
-            password_as_string.push(rng.gen_range(33, 127) as u8 as char);
+            CharClass::Symbol
         }
     }
-    Ok(SafeString::from_string(password_as_string))
-}
 
-/// Returns true if the password contains at least one digit, one uppercase letter and one
-/// lowercase letter.
-fn password_is_hard(password: &str, alnum: bool) -> bool {
-    let is_punctuation = |c| -> bool { "!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~".find(c).is_some() };
+    fn describe(self) -> &'static str {
+        match self {
+            CharClass::Lowercase => "lowercase letters",
+            CharClass::Uppercase => "uppercase letters",
+            CharClass::Digit => "digits",
+            CharClass::Symbol => "symbols",
+        }
+    }
 
-    password.find(char::is_numeric).is_some()
-        && password.find(char::is_lowercase).is_some()
-        && password.find(char::is_uppercase).is_some()
-        && (alnum || password.find(is_punctuation).is_some())
+    fn is_letter(self) -> bool {
+        self == CharClass::Lowercase || self == CharClass::Uppercase
+    }
 }
 
 pub struct PasswordSpec {
     pub alnum: bool,
     pub len: usize,
+    /// Characters to use on top of the default ones
+    pub include: String,
+    /// Characters to never use
+    pub exclude: String,
+    pub avoid_ambiguous: bool,
+    /// How many characters of each class the password needs at least, in the order of
+    /// `CHAR_CLASSES`; by default, one of each class that has characters available
+    pub min_counts: [Option<usize>; 4],
+    pub letter_first: bool,
+    /// How many times in a row the same character may appear
+    pub max_repeat: Option<usize>,
 }
 
 impl PasswordSpec {
@@ -46,16 +81,255 @@ impl PasswordSpec {
         PasswordSpec {
             alnum: alnum,
             len: password_len.unwrap_or(32),
+            include: String::new(),
+            exclude: String::new(),
+            avoid_ambiguous: false,
+            min_counts: [None; 4],
+            letter_first: false,
+            max_repeat: None,
+        }
+    }
+
+    /// Reads the options added by `generator_args`, telling the user what's wrong with them
+    pub fn from_matches(
+        matches: &clap::ArgMatches,
+        io: &mut impl CliInputOutput,
+    ) -> Option<PasswordSpec> {
+        let len = check_password_len(
+            match matches.value_of("length") {
+                Some(len) => len.parse::<usize>().ok(),
+                None => Some(32),
+            },
+            io,
+        )?;
+
+        let mut spec = PasswordSpec::new(matches.is_present("alnum"), Some(len));
+        spec.include = matches.value_of("include").unwrap_or("").to_owned();
+        spec.exclude = matches.value_of("exclude").unwrap_or("").to_owned();
+        spec.avoid_ambiguous = matches.is_present("no-ambiguous");
+        spec.letter_first = matches.is_present("letter-first");
+        spec.max_repeat = matches
+            .value_of("max-repeat")
+            .and_then(|n| n.parse::<usize>().ok());
+        for (i, option) in ["min-lower", "min-upper", "min-digits", "min-symbols"]
+            .iter()
+            .enumerate()
+        {
+            spec.min_counts[i] = matches
+                .value_of(option)
+                .and_then(|n| n.parse::<usize>().ok());
+        }
+
+        if let Err(err) = spec.check_rules() {
+            io.error(format!("Woops! {}", err), OutputType::Error);
+            return None;
+        }
+        Some(spec)
+    }
+
+    /// Returns the characters passwords are made of, without duplicates
+    pub fn charset(&self) -> Vec<char> {
+        let mut charset: Vec<char> = if self.alnum {
+            ('0'..='9').chain('A'..='Z').chain('a'..='z').collect()
+        } else {
+            (33u8..127).map(|b| b as char).collect()
+        };
+        charset.extend(self.include.chars());
+        charset.sort_unstable();
+        charset.dedup();
+        charset.retain(|c| !self.exclude.contains(*c));
+        if self.avoid_ambiguous {
+            charset.retain(|c| !AMBIGUOUS_CHARACTERS.contains(*c));
+        }
+        charset
+    }
+
+    /// How many characters of a class the password needs at least
+    fn min_count(&self, class: CharClass, charset: &[char]) -> usize {
+        let i = CHAR_CLASSES.iter().position(|c| *c == class).unwrap();
+        match self.min_counts[i] {
+            Some(min) => min,
+            None if charset.iter().any(|c| CharClass::of(*c) == class) => 1,
+            None => 0,
         }
     }
 
+    /// Makes sure some password can satisfy all the rules at once
+    pub fn check_rules(&self) -> Result<(), String> {
+        let charset = self.charset();
+        if charset.is_empty() {
+            return Err("There are no characters left to make a password with.".to_owned());
+        }
+
+        let mut total = 0;
+        let mut non_letters = 0;
+        for class in CHAR_CLASSES.iter() {
+            let min = self.min_count(*class, &charset);
+            if min > 0 && !charset.iter().any(|c| CharClass::of(*c) == *class) {
+                return Err(format!(
+                    "The password needs {}, but none of them are allowed.",
+                    class.describe()
+                ));
+            }
+            total += min;
+            if !class.is_letter() {
+                non_letters += min;
+            }
+        }
+
+        if total > self.len {
+            return Err(format!(
+                "A password of {} characters can't contain {} required characters.",
+                self.len, total
+            ));
+        }
+        if self.letter_first {
+            if !charset.iter().any(|c| CharClass::of(*c).is_letter()) {
+                return Err(
+                    "The password must start with a letter, but none are allowed.".to_owned(),
+                );
+            }
+            if non_letters > self.len.saturating_sub(1) {
+                return Err(format!(
+                    "A password of {} characters can't start with a letter and contain {} digits and symbols.",
+                    self.len, non_letters
+                ));
+            }
+        }
+        if self.max_repeat == Some(0) {
+            return Err("Characters must be allowed to appear at least once in a row.".to_owned());
+        }
+
+        Ok(())
+    }
+
+    /// Returns the first rule the password breaks, if any
+    pub fn check_password(&self, password: &str) -> Result<(), String> {
+        let charset = self.charset();
+        let chars: Vec<char> = password.chars().collect();
+
+        if chars.len() != self.len {
+            return Err(format!("it must be {} characters long", self.len));
+        }
+        if let Some(c) = chars.iter().find(|c| !charset.contains(c)) {
+            return Err(format!("it must not contain \"{}\"", c));
+        }
+        for class in CHAR_CLASSES.iter() {
+            let min = self.min_count(*class, &charset);
+            if chars
+                .iter()
+                .filter(|c| CharClass::of(**c) == *class)
+                .count()
+                < min
+            {
+                return Err(format!(
+                    "it must contain at least {} {}",
+                    min,
+                    class.describe()
+                ));
+            }
+        }
+        let starts_with_letter = match chars.first() {
+            Some(c) => CharClass::of(*c).is_letter(),
+            None => false,
+        };
+        if self.letter_first && !starts_with_letter {
+            return Err("it must start with a letter".to_owned());
+        }
+        if let Some(max_repeat) = self.max_repeat {
+            if repeat_run(&chars) > max_repeat {
+                return Err(format!(
+                    "it must not repeat a character more than {} times in a row",
+                    max_repeat
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generates a password that follows all the rules
+    ///
+    /// Characters are picked one at a time among those that keep the rules satisfiable, so the
+    /// password never needs to be thrown away and generated again.
     pub fn generate_hard_password(&self) -> IoResult<SafeString> {
-        loop {
-            let password = generate_password(self.alnum, self.len)?;
-            if password_is_hard(password.as_ref(), self.alnum) {
-                return Ok(password);
+        self.check_rules()
+            .map_err(|err| IoError::new(IoErrorKind::InvalidInput, err))?;
+
+        let charset = self.charset();
+        let mut missing: Vec<usize> = CHAR_CLASSES
+            .iter()
+            .map(|class| self.min_count(*class, &charset))
+            .collect();
+        let mut chars: Vec<char> = Vec::with_capacity(self.len);
+        let mut rng = OsRng::default();
+
+        for i in 0..self.len {
+            let slots_left = self.len - i;
+            let missing_total: usize = missing.iter().sum();
+            let previous_is_capped = match (self.max_repeat, chars.last()) {
+                (Some(max_repeat), Some(_)) => repeat_run_at_end(&chars) >= max_repeat,
+                _ => false,
+            };
+
+            let allowed: Vec<char> = charset
+                .iter()
+                .cloned()
+                .filter(|c| {
+                    let class_index = CHAR_CLASSES
+                        .iter()
+                        .position(|class| *class == CharClass::of(*c))
+                        .unwrap();
+                    // Once there is just enough room left for the required characters, only
+                    // those will do.
+                    (missing_total < slots_left || missing[class_index] > 0)
+                        && (!self.letter_first || i > 0 || CharClass::of(*c).is_letter())
+                        && !(previous_is_capped && chars.last() == Some(c))
+                })
+                .collect();
+            if allowed.is_empty() {
+                return Err(IoError::new(
+                    IoErrorKind::InvalidInput,
+                    "the rules can't be satisfied with the allowed characters",
+                ));
             }
+
+            let c = allowed[rng.gen_range(0, allowed.len())];
+            let class_index = CHAR_CLASSES
+                .iter()
+                .position(|class| *class == CharClass::of(c))
+                .unwrap();
+            missing[class_index] = missing[class_index].saturating_sub(1);
+            chars.push(c);
         }
+
+        let password = SafeString::from_string(chars.into_iter().collect());
+        self.check_password(password.as_ref())
+            .map_err(|err| IoError::new(IoErrorKind::InvalidData, err))?;
+        Ok(password)
+    }
+}
+
+/// Returns the longest number of times a character is repeated in a row
+fn repeat_run(chars: &[char]) -> usize {
+    let mut longest = 0;
+    let mut current = 0;
+    for (i, c) in chars.iter().enumerate() {
+        if i > 0 && chars[i - 1] == *c {
+            current += 1;
+        } else {
+            current = 1;
+        }
+        longest = longest.max(current);
+    }
+    longest
+}
+
+/// Returns how many times the last character is repeated at the end
+fn repeat_run_at_end(chars: &[char]) -> usize {
+    match chars.last() {
+        Some(last) => chars.iter().rev().take_while(|c| *c == last).count(),
+        None => 0,
     }
 }
 
@@ -67,9 +341,18 @@ pub fn check_password_len(opt: Option<usize>, io: &mut impl CliInputOutput) -> O
             // This checks makes sure we don't run into an infinite loop trying to generate
             // a password of length < 4 with 4 different kinds of characters (uppercase,
             // lowercase, numeric, punctuation).
-            if len < 4 {
+            if len < MIN_PASSWORD_LEN {
                 io.error("Woops! The length of the password must be at least 4. This allows us to make sure your password is secure.", OutputType::Error);
                 None
+            } else if len > MAX_PASSWORD_LEN {
+                io.error(
+                    format!(
+                        "Woops! The length of the password must be at most {}.",
+                        MAX_PASSWORD_LEN
+                    ),
+                    OutputType::Error,
+                );
+                None
             } else {
                 Some(len)
             }
@@ -84,9 +367,379 @@ pub fn check_password_len(opt: Option<usize>, io: &mut impl CliInputOutput) -> O
     }
 }
 
+/// The words passphrases are made of by default: the English BIP-0039 wordlist, 2048 short
+/// and distinct words (CC0, as distributed with the `bip39` crate), so 11 bits per word
+const DEFAULT_WORDLIST: &str = include_str!("wordlists/bip39_english.txt");
+
+/// Passphrases get enough words for this much entropy unless asked otherwise, which is what six
+/// words of the EFF long wordlist (7776 words, 12.9 bits each) give
+const DEFAULT_ENTROPY_BITS: f64 = 77.0;
+
+pub struct PassphraseSpec {
+    pub words: usize,
+    pub separator: String,
+    pub capitalize: bool,
+    pub add_digit: bool,
+    pub wordlist: Vec<String>,
+}
+
+impl PassphraseSpec {
+    pub fn new(words: usize) -> PassphraseSpec {
+        PassphraseSpec {
+            words,
+            separator: "-".to_owned(),
+            capitalize: false,
+            add_digit: false,
+            wordlist: parse_wordlist(DEFAULT_WORDLIST),
+        }
+    }
+
+    pub fn from_matches(
+        matches: &clap::ArgMatches,
+        io: &mut impl CliInputOutput,
+    ) -> Option<PassphraseSpec> {
+        let words = match matches.value_of("words").map(|n| n.parse::<usize>()) {
+            None => None,
+            Some(Ok(words)) if words > 0 => Some(words),
+            Some(_) => {
+                io.error(
+                    "Woops! A passphrase needs at least one word.",
+                    OutputType::Error,
+                );
+                return None;
+            }
+        };
+
+        let mut spec = PassphraseSpec::new(words.unwrap_or(0));
+        if let Some(separator) = matches.value_of("separator") {
+            spec.separator = separator.to_owned();
+        }
+        spec.capitalize = matches.is_present("capitalize");
+        spec.add_digit = matches.is_present("add-digit");
+
+        if let Some(path) = matches.value_of("wordlist") {
+            spec.wordlist = match load_wordlist(Path::new(path)) {
+                Ok(wordlist) => wordlist,
+                Err(err) => {
+                    io.error(
+                        format!("Woops, I couldn't read the wordlist (reason: {}).", err),
+                        OutputType::Error,
+                    );
+                    return None;
+                }
+            };
+        }
+        if spec.wordlist.len() < 2 {
+            io.error(
+                "Woops! The wordlist needs at least two different words.",
+                OutputType::Error,
+            );
+            return None;
+        }
+        if words.is_none() {
+            spec.words = spec.default_words();
+        }
+
+        Some(spec)
+    }
+
+    /// The fewest words that give passphrases `DEFAULT_ENTROPY_BITS`, with this wordlist
+    pub fn default_words(&self) -> usize {
+        (DEFAULT_ENTROPY_BITS / (self.wordlist.len() as f64).log2()).ceil() as usize
+    }
+
+    pub fn generate_passphrase(&self) -> IoResult<SafeString> {
+        if self.words == 0 || self.wordlist.is_empty() {
+            return Err(IoError::new(
+                IoErrorKind::InvalidInput,
+                "a passphrase needs at least one word",
+            ));
+        }
+
+        let mut rng = OsRng;
+        let mut words: Vec<String> = (0..self.words)
+            .map(|_| {
+                let word = &self.wordlist[rng.gen_range(0, self.wordlist.len())];
+                if self.capitalize {
+                    capitalize(word)
+                } else {
+                    word.clone()
+                }
+            })
+            .collect();
+
+        if self.add_digit {
+            let i = rng.gen_range(0, words.len());
+            let digit = rng.gen_range(0, 10);
+            words[i].push_str(digit.to_string().as_str());
+        }
+
+        let passphrase = SafeString::from_string(words.join(self.separator.as_str()));
+        for word in words.iter_mut() {
+            word.clear();
+        }
+        Ok(passphrase)
+    }
+
+    /// How many bits of entropy the passphrases have, assuming the wordlist is public
+    pub fn entropy_bits(&self) -> f64 {
+        let mut bits = self.words as f64 * (self.wordlist.len() as f64).log2();
+        if self.add_digit {
+            // Which of the 10 digits, and which word it is added to.
+            bits += 10f64.log2() + (self.words as f64).log2();
+        }
+        bits
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Reads a wordlist, with one word per line
+///
+/// Lines may start with dice rolls, like in the EFF wordlists (e.g. `11111\tabacus`): only the
+/// last word of each line is kept.
+pub fn load_wordlist(path: &Path) -> IoResult<Vec<String>> {
+    Ok(parse_wordlist(std::fs::read_to_string(path)?.as_str()))
+}
+
+fn parse_wordlist(contents: &str) -> Vec<String> {
+    let mut words: Vec<String> = contents
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .map(|word| word.to_owned())
+        .collect();
+    // Duplicates would make some words more likely than others.
+    words.sort_unstable();
+    words.dedup();
+    words
+}
+
+/// Generates a PIN made of `len` random digits
+pub fn generate_pin(len: usize) -> SafeString {
+    let mut rng = OsRng;
+    SafeString::from_string(
+        (0..len)
+            .map(|_| std::char::from_digit(rng.gen_range(0, 10), 10).unwrap())
+            .collect(),
+    )
+}
+
+/// Generates a token of `len` lowercase hexadecimal characters
+pub fn generate_hex_token(len: usize) -> SafeString {
+    let mut bytes = SafeVec::new(vec![0u8; len / 2 + 1]);
+    OsRng.fill_bytes(&mut bytes);
+    let mut token = SafeString::from_string(String::with_capacity(bytes.len() * 2));
+    for byte in bytes.iter() {
+        token.push_str(format!("{:02x}", byte).as_str());
+    }
+    token.truncate(len);
+    token
+}
+
+/// Generates a token of `len` characters from the URL-safe base64 alphabet (A-Z, a-z, 0-9, - and _)
+pub fn generate_base64url_token(len: usize) -> SafeString {
+    let mut bytes = SafeVec::new(vec![0u8; len / 4 * 3 + 3]);
+    OsRng.fill_bytes(&mut bytes);
+    let encoded = SafeString::from_string(openssl::base64::encode_block(&bytes));
+    // The URL-safe alphabet only differs from the standard one by these two characters.
+    let mut token = SafeString::from_string(String::with_capacity(len));
+    for c in encoded.chars().filter(|c| *c != '=').take(len) {
+        token.push(match c {
+            '+' => '-',
+            '/' => '_',
+            c => c,
+        });
+    }
+    token
+}
+
+/// Generates a random (version 4) UUID, such as `0b7a2c1e-93f4-4d8e-a1c2-5f6e7d8c9b0a`
+pub fn generate_uuid() -> SafeString {
+    let mut bytes = [0u8; 16];
+    OsRng.fill_bytes(&mut bytes);
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    let mut uuid = SafeString::from_string(String::with_capacity(36));
+    for (i, byte) in bytes.iter().enumerate() {
+        if i == 4 || i == 6 || i == 8 || i == 10 {
+            uuid.push('-');
+        }
+        uuid.push_str(format!("{:02x}", byte).as_str());
+    }
+    for byte in bytes.iter_mut() {
+        *byte = 0;
+    }
+    uuid
+}
+
+/// The kinds of secrets Rooster generates, as passed to `--kind`
+pub const KINDS: [&str; 7] = [
+    "password",
+    "passphrase",
+    "pin",
+    "hex",
+    "base64url",
+    "uuid",
+    "ssh-ed25519",
+];
+
+/// The field that holds the public half of a generated SSH key
+pub const PUBLIC_KEY_FIELD: &str = "public-key";
+
+/// Options that only make sense for some kinds of secrets
+const PASSWORD_OPTIONS: [&str; 10] = [
+    "alnum",
+    "include",
+    "exclude",
+    "no-ambiguous",
+    "min-lower",
+    "min-upper",
+    "min-digits",
+    "min-symbols",
+    "letter-first",
+    "max-repeat",
+];
+const PASSPHRASE_OPTIONS: [&str; 5] = ["words", "separator", "capitalize", "add-digit", "wordlist"];
+const OTHER_OPTIONS: [&str; 2] = ["length", "comment"];
+
+/// What `rooster generate`, `rooster regenerate` and `rooster gen` produce
+pub enum Generator {
+    Password(PasswordSpec),
+    Passphrase(PassphraseSpec),
+    Pin(usize),
+    Hex(usize),
+    Base64Url(usize),
+    Uuid,
+    SshEd25519 { comment: String },
+}
+
+/// A generated secret, along with what should be stored next to it
+pub struct Secret {
+    pub value: SafeString,
+    /// Fields of the password entry, such as the public half of an SSH key
+    pub fields: BTreeMap<String, String>,
+}
+
+impl Generator {
+    pub fn from_matches(
+        matches: &clap::ArgMatches,
+        io: &mut impl CliInputOutput,
+    ) -> Option<Generator> {
+        let kind = if matches.is_present("passphrase") {
+            "passphrase"
+        } else {
+            matches.value_of("kind").unwrap_or("password")
+        };
+
+        let allowed: &[&str] = match kind {
+            "password" => &PASSWORD_OPTIONS,
+            "passphrase" => &PASSPHRASE_OPTIONS,
+            "pin" | "hex" | "base64url" => &[],
+            _ => &["comment"],
+        };
+        let allows_length = kind == "password" || allowed.is_empty();
+        for option in PASSWORD_OPTIONS
+            .iter()
+            .chain(PASSPHRASE_OPTIONS.iter())
+            .chain(OTHER_OPTIONS.iter())
+        {
+            let is_allowed = allowed.contains(option) || (*option == "length" && allows_length);
+            if !is_allowed && matches.is_present(option) {
+                io.error(
+                    format!("Woops! --{} doesn't work with --kind {}.", option, kind),
+                    OutputType::Error,
+                );
+                return None;
+            }
+        }
+
+        match kind {
+            "passphrase" => PassphraseSpec::from_matches(matches, io).map(Generator::Passphrase),
+            "pin" => length_or(matches, 6, 4, io).map(Generator::Pin),
+            "hex" => length_or(matches, 64, 16, io).map(Generator::Hex),
+            "base64url" => length_or(matches, 43, 16, io).map(Generator::Base64Url),
+            "uuid" => Some(Generator::Uuid),
+            "ssh-ed25519" => Some(Generator::SshEd25519 {
+                comment: matches.value_of("comment").unwrap_or("").to_owned(),
+            }),
+            _ => PasswordSpec::from_matches(matches, io).map(Generator::Password),
+        }
+    }
+
+    pub fn generate(&self) -> IoResult<Secret> {
+        let mut fields = BTreeMap::new();
+        let value = match self {
+            Generator::Password(spec) => spec.generate_hard_password()?,
+            Generator::Passphrase(spec) => spec.generate_passphrase()?,
+            Generator::Pin(len) => generate_pin(*len),
+            Generator::Hex(len) => generate_hex_token(*len),
+            Generator::Base64Url(len) => generate_base64url_token(*len),
+            Generator::Uuid => generate_uuid(),
+            Generator::SshEd25519 { comment } => {
+                let keypair = ssh_key::generate_ed25519_keypair(comment.as_str())?;
+                fields.insert(PUBLIC_KEY_FIELD.to_owned(), keypair.public_key);
+                keypair.private_key
+            }
+        };
+        Ok(Secret { value, fields })
+    }
+
+    /// Tells the user what they need to know about the generated secret, besides the secret
+    pub fn report(&self, secret: &Secret, output_type: OutputType, io: &mut impl CliInputOutput) {
+        match self {
+            Generator::Passphrase(spec) => io.info(
+                format!(
+                    "Your passphrase has {} words from a list of {}, that's {:.0} bits of entropy.",
+                    spec.words,
+                    spec.wordlist.len(),
+                    spec.entropy_bits()
+                ),
+                output_type,
+            ),
+            Generator::SshEd25519 { .. } => {
+                if let Some(public_key) = secret.fields.get(PUBLIC_KEY_FIELD) {
+                    io.info(format!("Your public key is: {}", public_key), output_type);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads `--length`, for the kinds of secrets that don't follow the password rules
+fn length_or(
+    matches: &clap::ArgMatches,
+    default: usize,
+    min: usize,
+    io: &mut impl CliInputOutput,
+) -> Option<usize> {
+    let len = match matches.value_of("length") {
+        None => return Some(default),
+        Some(len) => len.parse::<usize>().unwrap_or(0),
+    };
+    if len < min {
+        io.error(
+            format!("Woops! The length must be at least {}.", min),
+            OutputType::Error,
+        );
+        return None;
+    }
+    Some(len)
+}
+
 #[cfg(test)]
 mod test {
-    use crate::generate::PasswordSpec;
+    use crate::generate::{
+        generate_base64url_token, generate_hex_token, generate_pin, generate_uuid, parse_wordlist,
+        CharClass, PassphraseSpec, PasswordSpec, AMBIGUOUS_CHARACTERS,
+    };
     use std::ops::Deref;
 
     #[test]
@@ -131,4 +784,203 @@ mod test {
         }
         assert!(ok);
     }
+
+    #[test]
+    fn test_generate_password_with_custom_charset() {
+        let mut ps = PasswordSpec::new(true, Some(64));
+        ps.include = "_-".to_owned();
+        ps.exclude = "abcdefghijklmnopqrstuvwxyz".to_owned();
+        ps.avoid_ambiguous = true;
+        ps.min_counts = [None, None, None, Some(4)];
+
+        for _ in 0..20 {
+            let pw = ps.generate_hard_password().unwrap();
+            assert!(ps.check_password(pw.deref()).is_ok());
+            assert!(pw.deref().chars().all(|c| !c.is_lowercase()));
+            assert!(pw
+                .deref()
+                .chars()
+                .all(|c| !AMBIGUOUS_CHARACTERS.contains(c)));
+            assert!(pw.deref().chars().filter(|c| "_-".contains(*c)).count() >= 4);
+        }
+    }
+
+    #[test]
+    fn test_generate_password_with_minimum_counts() {
+        let mut ps = PasswordSpec::new(false, Some(8));
+        ps.min_counts = [Some(2), Some(2), Some(2), Some(2)];
+
+        for _ in 0..20 {
+            let pw = ps.generate_hard_password().unwrap();
+            for class in [
+                CharClass::Lowercase,
+                CharClass::Uppercase,
+                CharClass::Digit,
+                CharClass::Symbol,
+            ]
+            .iter()
+            {
+                let count = pw
+                    .deref()
+                    .chars()
+                    .filter(|c| CharClass::of(*c) == *class)
+                    .count();
+                assert_eq!(count, 2);
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_password_with_letter_first_and_max_repeat() {
+        let mut ps = PasswordSpec::new(true, Some(32));
+        ps.exclude = "abcdefghijklmnopqrstuvwxyzBCDEFGHIJKLMNOPQRSTUVWXYZ23456789".to_owned();
+        ps.letter_first = true;
+        ps.max_repeat = Some(1);
+
+        // Only "A", "0" and "1" are left, so repeats would be very likely without the rule.
+        for _ in 0..20 {
+            let pw = ps.generate_hard_password().unwrap();
+            assert!(pw.deref().starts_with('A'));
+            assert!(!pw.deref().contains("AA"));
+            assert!(!pw.deref().contains("00"));
+            assert!(!pw.deref().contains("11"));
+        }
+    }
+
+    #[test]
+    fn test_check_password() {
+        let mut ps = PasswordSpec::new(false, Some(6));
+        ps.letter_first = true;
+        ps.max_repeat = Some(2);
+
+        assert!(ps.check_password("aB3$cd").is_ok());
+        assert!(ps.check_password("aB3$c").is_err());
+        assert!(ps.check_password("ab3$cd").is_err());
+        assert!(ps.check_password("3Ba$cd").is_err());
+        assert!(ps.check_password("aB3$ccc").is_err());
+        assert!(ps.check_password("aB3ccc").is_err());
+        assert!(ps.check_password("aB3 cd").is_err());
+    }
+
+    #[test]
+    fn test_check_rules() {
+        let mut ps = PasswordSpec::new(true, Some(8));
+        ps.min_counts[3] = Some(1);
+        assert!(ps.check_rules().is_err());
+
+        let mut ps = PasswordSpec::new(false, Some(4));
+        ps.min_counts[2] = Some(4);
+        assert!(ps.check_rules().is_err());
+
+        let mut ps = PasswordSpec::new(false, Some(4));
+        ps.min_counts = [Some(0), Some(0), Some(2), Some(2)];
+        ps.letter_first = true;
+        assert!(ps.check_rules().is_err());
+
+        let mut ps = PasswordSpec::new(true, Some(8));
+        ps.exclude = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789".to_owned();
+        assert!(ps.check_rules().is_err());
+        assert!(ps.generate_hard_password().is_err());
+    }
+
+    #[test]
+    fn test_generate_passphrase() {
+        let mut ps = PassphraseSpec::new(6);
+        ps.separator = " ".to_owned();
+
+        let passphrase = ps.generate_passphrase().unwrap();
+        let words: Vec<&str> = passphrase.deref().split(' ').collect();
+        assert_eq!(words.len(), 6);
+        for word in words {
+            assert!(ps.wordlist.iter().any(|w| w == word));
+        }
+        assert_eq!(ps.wordlist.len(), 2048);
+        assert_eq!(ps.entropy_bits(), 66.0);
+    }
+
+    #[test]
+    fn test_default_words() {
+        let mut ps = PassphraseSpec::new(0);
+        assert_eq!(ps.default_words(), 7);
+        ps.words = ps.default_words();
+        assert_eq!(ps.entropy_bits(), 77.0);
+
+        // Like the EFF long wordlist
+        ps.wordlist = (0..7776).map(|i| format!("word{}", i)).collect();
+        assert_eq!(ps.default_words(), 6);
+        ps.words = ps.default_words();
+        assert!(ps.entropy_bits() > 77.5 && ps.entropy_bits() < 77.6);
+    }
+
+    #[test]
+    fn test_generate_passphrase_with_capitals_and_digit() {
+        let mut ps = PassphraseSpec::new(4);
+        ps.capitalize = true;
+        ps.add_digit = true;
+
+        let passphrase = ps.generate_passphrase().unwrap();
+        let words: Vec<&str> = passphrase.deref().split('-').collect();
+        assert_eq!(words.len(), 4);
+        assert!(words
+            .iter()
+            .all(|w| w.chars().next().unwrap().is_uppercase()));
+        assert_eq!(
+            passphrase
+                .deref()
+                .chars()
+                .filter(|c| c.is_ascii_digit())
+                .count(),
+            1
+        );
+        assert!(ps.entropy_bits() > 44.0);
+    }
+
+    #[test]
+    fn test_parse_wordlist() {
+        assert_eq!(
+            parse_wordlist("11111\tabacus\n11112\tabdomen\n\n11113\tabacus\n"),
+            vec!["abacus", "abdomen"]
+        );
+        assert_eq!(
+            parse_wordlist("apple\r\nbanana\r\n"),
+            vec!["apple", "banana"]
+        );
+    }
+
+    #[test]
+    fn test_generate_pin() {
+        let pin = generate_pin(6);
+        assert_eq!(pin.len(), 6);
+        assert!(pin.chars().all(|c| c.is_ascii_digit()));
+    }
+
+    #[test]
+    fn test_generate_tokens() {
+        for len in [16, 33, 64].iter() {
+            let hex = generate_hex_token(*len);
+            assert_eq!(hex.len(), *len);
+            assert!(hex
+                .chars()
+                .all(|c| c.is_ascii_digit() || ('a'..='f').contains(&c)));
+
+            let base64url = generate_base64url_token(*len);
+            assert_eq!(base64url.len(), *len);
+            assert!(base64url
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        }
+        assert_ne!(generate_hex_token(64), generate_hex_token(64));
+    }
+
+    #[test]
+    fn test_generate_uuid() {
+        let uuid = generate_uuid();
+        let groups: Vec<&str> = uuid.split('-').collect();
+        assert_eq!(
+            groups.iter().map(|g| g.len()).collect::<Vec<usize>>(),
+            vec![8, 4, 4, 4, 12]
+        );
+        assert!(groups[2].starts_with('4'));
+        assert!("89ab".contains(groups[3].chars().next().unwrap()));
+    }
 }
@@ -0,0 +1,50 @@
+use crate::agent::Agent;
+use crate::rutil::safe_vec::SafeVec;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+use std::path::Path;
+
+/// Somewhere the key derived from the master password can be kept between invocations
+pub trait KeyCache {
+    fn get_key(&self, vault: &Path) -> IoResult<Option<SafeVec>>;
+    fn put_key(&self, vault: &Path, key: &SafeVec) -> IoResult<()>;
+    fn forget_key(&self, vault: &Path) -> IoResult<()>;
+
+    /// Makes sure the master password is asked again next time
+    fn lock(&self, vault: &Path) -> IoResult<()>;
+}
+
+/// Returns the key caches the user enabled, in the order they should be tried
+pub fn from_env() -> Vec<Box<dyn KeyCache>> {
+    let mut caches: Vec<Box<dyn KeyCache>> = Vec::new();
+
+    if let Some(agent) = Agent::from_env() {
+        caches.push(Box::new(agent));
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(keyring) = crate::keyring::Keyring::from_env() {
+            caches.push(Box::new(keyring));
+        }
+    }
+
+    caches
+}
+
+/// Identifies a Rooster file in a key cache, so one cache can serve several files
+pub fn vault_id(vault: &Path) -> IoResult<String> {
+    let id = vault
+        .canonicalize()
+        .unwrap_or_else(|_| vault.to_path_buf())
+        .to_string_lossy()
+        .into_owned();
+
+    if id.contains('\n') {
+        return Err(IoError::new(
+            IoErrorKind::InvalidInput,
+            "file name contains a new line",
+        ));
+    }
+
+    Ok(id)
+}
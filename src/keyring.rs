@@ -0,0 +1,185 @@
+use crate::key_cache::{vault_id, KeyCache};
+use crate::rutil::safe_vec::SafeVec;
+use std::ffi::CString;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+use std::path::Path;
+
+/// Set to "on" to cache the derived key in the session keyring
+pub const ROOSTER_KEYRING_ENV_VAR: &str = "ROOSTER_KEYRING";
+
+/// How long the key stays in the keyring, in seconds
+pub const ROOSTER_KEYRING_TIMEOUT_ENV_VAR: &str = "ROOSTER_KEYRING_TIMEOUT";
+
+const DEFAULT_TIMEOUT: u32 = 900;
+
+// From <linux/keyctl.h>
+const KEY_SPEC_SESSION_KEYRING: libc::c_long = -3;
+const KEYCTL_SETPERM: libc::c_long = 5;
+const KEYCTL_SEARCH: libc::c_long = 10;
+const KEYCTL_READ: libc::c_long = 11;
+const KEYCTL_SET_TIMEOUT: libc::c_long = 15;
+const KEYCTL_INVALIDATE: libc::c_long = 21;
+const KEY_POS_ALL: libc::c_long = 0x3f00_0000;
+
+/// Caches keys in the Linux session keyring, through the `add_key` and `keyctl` syscalls
+///
+/// Unlike the agent, nothing needs to run in the background: the kernel keeps the key and
+/// removes it when the timeout expires or when the session ends.
+pub struct Keyring {
+    timeout: u32,
+}
+
+impl Keyring {
+    pub fn new(timeout: u32) -> Keyring {
+        Keyring { timeout }
+    }
+
+    /// Returns the keyring if it was enabled in the environment
+    pub fn from_env() -> Option<Keyring> {
+        match std::env::var(ROOSTER_KEYRING_ENV_VAR) {
+            Ok(ref value) if value == "on" => {}
+            _ => return None,
+        }
+
+        let timeout = std::env::var(ROOSTER_KEYRING_TIMEOUT_ENV_VAR)
+            .ok()
+            .and_then(|timeout| timeout.parse::<u32>().ok())
+            .unwrap_or(DEFAULT_TIMEOUT);
+
+        Some(Keyring::new(timeout))
+    }
+
+    /// Returns the serial number of the key for a Rooster file, if the kernel still has it
+    fn search(&self, description: &CString) -> IoResult<Option<libc::c_long>> {
+        let key_type = CString::new("user").unwrap();
+        let serial = unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                KEYCTL_SEARCH,
+                KEY_SPEC_SESSION_KEYRING,
+                key_type.as_ptr(),
+                description.as_ptr(),
+                0 as libc::c_long,
+            )
+        };
+
+        if serial < 0 {
+            let err = IoError::last_os_error();
+            return match err.raw_os_error() {
+                Some(libc::ENOKEY) | Some(libc::EKEYEXPIRED) | Some(libc::EKEYREVOKED) => Ok(None),
+                _ => Err(err),
+            };
+        }
+
+        Ok(Some(serial))
+    }
+}
+
+fn description(vault: &Path) -> IoResult<CString> {
+    CString::new(format!("rooster:{}", vault_id(vault)?))
+        .map_err(|err| IoError::new(IoErrorKind::InvalidInput, err))
+}
+
+fn keyctl_result(ret: libc::c_long) -> IoResult<libc::c_long> {
+    if ret < 0 {
+        Err(IoError::last_os_error())
+    } else {
+        Ok(ret)
+    }
+}
+
+impl KeyCache for Keyring {
+    fn get_key(&self, vault: &Path) -> IoResult<Option<SafeVec>> {
+        let serial = match self.search(&description(vault)?)? {
+            Some(serial) => serial,
+            None => return Ok(None),
+        };
+
+        let mut key = SafeVec::new(vec![0u8; 64]);
+        let len = keyctl_result(unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                KEYCTL_READ,
+                serial,
+                key.as_mut_ptr(),
+                key.len(),
+            )
+        })? as usize;
+        if len > key.len() {
+            return Err(IoError::new(
+                IoErrorKind::InvalidData,
+                "unexpected key size",
+            ));
+        }
+        key.inner_mut().truncate(len);
+
+        Ok(Some(key))
+    }
+
+    fn put_key(&self, vault: &Path, key: &SafeVec) -> IoResult<()> {
+        let key_type = CString::new("user").unwrap();
+        let description = description(vault)?;
+
+        // If the keyring already has a key for this file, the kernel updates it.
+        let serial = keyctl_result(unsafe {
+            libc::syscall(
+                libc::SYS_add_key,
+                key_type.as_ptr(),
+                description.as_ptr(),
+                key.as_ptr(),
+                key.len(),
+                KEY_SPEC_SESSION_KEYRING,
+            )
+        })?;
+
+        // Only processes that possess the session keyring may read the key.
+        keyctl_result(unsafe {
+            libc::syscall(libc::SYS_keyctl, KEYCTL_SETPERM, serial, KEY_POS_ALL)
+        })?;
+
+        keyctl_result(unsafe {
+            libc::syscall(
+                libc::SYS_keyctl,
+                KEYCTL_SET_TIMEOUT,
+                serial,
+                self.timeout as libc::c_long,
+            )
+        })?;
+
+        Ok(())
+    }
+
+    fn forget_key(&self, vault: &Path) -> IoResult<()> {
+        if let Some(serial) = self.search(&description(vault)?)? {
+            keyctl_result(unsafe { libc::syscall(libc::SYS_keyctl, KEYCTL_INVALIDATE, serial) })?;
+        }
+        Ok(())
+    }
+
+    fn lock(&self, vault: &Path) -> IoResult<()> {
+        self.forget_key(vault)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Keyring;
+    use crate::key_cache::KeyCache;
+    use crate::rutil::safe_vec::SafeVec;
+
+    #[test]
+    fn test_keyring_roundtrip() {
+        let keyring = Keyring::new(60);
+        let vault = tempfile::NamedTempFile::new().unwrap();
+        let key = SafeVec::new(vec![42u8; 32]);
+
+        // Some sandboxes don't give processes access to the kernel keyrings.
+        if keyring.put_key(vault.path(), &key).is_err() {
+            return;
+        }
+
+        assert_eq!(keyring.get_key(vault.path()).unwrap(), Some(key));
+        keyring.lock(vault.path()).unwrap();
+        assert_eq!(keyring.get_key(vault.path()).unwrap(), None);
+    }
+}
@@ -1,8 +1,11 @@
 // #![allow(useless_format, too_many_arguments)]
 
+use crate::key_cache::KeyCache;
 use crate::password::v2::PasswordStore;
 use crate::rclio::CliInputOutput;
 use crate::rclio::OutputType;
+use crate::rclio::{print_json_error, JsonInputOutput, NoInputOutput};
+use crate::rutil::file_lock::FileLock;
 use crate::rutil::safe_string::SafeString;
 use crate::rutil::safe_vec::SafeVec;
 use clap::{App, AppSettings, Arg};
@@ -14,12 +17,23 @@ use std::ops::Deref;
 use std::path::{Path, PathBuf};
 
 mod aes;
+mod agent;
+mod approval;
+mod autotype;
+mod breach;
 mod clip;
 mod commands;
+mod dbus;
+mod expiry;
 mod ffi;
 mod generate;
+mod key_cache;
+#[cfg(target_os = "linux")]
+mod keyring;
 mod list;
+mod origin;
 mod password;
+mod prompt_pattern;
 mod quale;
 #[allow(unused)]
 pub mod rclio;
@@ -29,7 +43,11 @@ mod rpassword;
 mod rprompt;
 #[allow(unused)]
 mod rutil;
-mod shell_escape;
+mod search;
+mod secret_service;
+mod strength;
+mod template;
+mod ui;
 
 fn validate_arg_digits(v: &str) -> Result<(), String> {
     if v.chars()
@@ -42,6 +60,159 @@ fn validate_arg_digits(v: &str) -> Result<(), String> {
     Ok(())
 }
 
+/// A strength score, which goes from 0 to 4
+fn validate_arg_score(v: &str) -> Result<(), String> {
+    match v.parse::<u8>() {
+        Ok(score) if score <= 4 => Ok(()),
+        _ => Err(String::from("The value must be a score from 0 to 4")),
+    }
+}
+
+fn validate_arg_days(v: &str) -> Result<(), String> {
+    validate_arg_digits(v)?;
+    v.parse::<u64>()
+        .map(|_| ())
+        .map_err(|_| String::from("The value must be a number of days"))
+}
+
+/// A look-ahead window, small enough to be turned into seconds
+fn validate_arg_window_days(v: &str) -> Result<(), String> {
+    validate_arg_digits(v)?;
+    v.parse::<u32>()
+        .map(|_| ())
+        .map_err(|_| String::from("The value must be a number of days"))
+}
+
+/// How often a password must be changed, which can't be never
+fn validate_arg_rotation_days(v: &str) -> Result<(), String> {
+    validate_arg_digits(v)?;
+    match v.parse::<u32>() {
+        Ok(days) if days > 0 => Ok(()),
+        _ => Err(String::from(
+            "The value must be a number of days, at least 1",
+        )),
+    }
+}
+
+/// The option of the exports that can be limited to some apps
+fn query_arg() -> Arg<'static> {
+    Arg::new("query")
+        .short('q')
+        .long("query")
+        .takes_value(true)
+        .about("Only export the apps matching this search, like 'tag:staging' or 'prod' for names starting with prod (default: all apps)")
+}
+
+/// The options of the commands that look for an app, read by `list::Selection::from_matches`
+fn selection_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("exact")
+            .long("exact")
+            .conflicts_with_all(&["first", "index"])
+            .about(
+                "Fail unless an app has exactly this name, instead of asking which one you mean",
+            ),
+        Arg::new("first")
+            .long("first")
+            .conflicts_with("index")
+            .about("Take the best match instead of asking which one you mean"),
+        Arg::new("index")
+            .long("index")
+            .takes_value(true)
+            .value_name("N")
+            .about("Take the Nth match, as numbered in the list of matches")
+            .validator(validate_arg_digits),
+    ]
+}
+
+/// The options of the commands that generate secrets, read by `Generator::from_matches`
+fn generator_args() -> Vec<Arg<'static>> {
+    vec![
+        Arg::new("kind")
+            .short('k')
+            .long("kind")
+            .takes_value(true)
+            .possible_values(&generate::KINDS)
+            .about("Generate this kind of secret instead of a password"),
+        Arg::new("alnum")
+            .short('a')
+            .long("alnum")
+            .about("Only use alpha numeric (a-z, A-Z, 0-9) in generated passwords"),
+        Arg::new("length")
+            .short('l')
+            .long("length")
+            .takes_value(true)
+            .about("Set a custom length for the generated password (default: 32), PIN (default: 6) or token (default: 64 for hex, 43 for base64url)")
+            .validator(validate_arg_digits),
+        Arg::new("include")
+            .long("include")
+            .takes_value(true)
+            .about("Also use these characters in generated passwords"),
+        Arg::new("exclude")
+            .long("exclude")
+            .takes_value(true)
+            .about("Never use these characters in generated passwords"),
+        Arg::new("no-ambiguous")
+            .long("no-ambiguous")
+            .about("Avoid characters that look alike (Il1|O0o)"),
+        Arg::new("min-lower")
+            .long("min-lower")
+            .takes_value(true)
+            .about("Use at least this many lowercase letters (default: 1)")
+            .validator(validate_arg_digits),
+        Arg::new("min-upper")
+            .long("min-upper")
+            .takes_value(true)
+            .about("Use at least this many uppercase letters (default: 1)")
+            .validator(validate_arg_digits),
+        Arg::new("min-digits")
+            .long("min-digits")
+            .takes_value(true)
+            .about("Use at least this many digits (default: 1)")
+            .validator(validate_arg_digits),
+        Arg::new("min-symbols")
+            .long("min-symbols")
+            .takes_value(true)
+            .about("Use at least this many symbols (default: 1, or 0 with --alnum)")
+            .validator(validate_arg_digits),
+        Arg::new("letter-first")
+            .long("letter-first")
+            .about("Start generated passwords with a letter"),
+        Arg::new("max-repeat")
+            .long("max-repeat")
+            .takes_value(true)
+            .about("Never repeat a character more than this many times in a row")
+            .validator(validate_arg_digits),
+        Arg::new("passphrase")
+            .long("passphrase")
+            .conflicts_with("kind")
+            .about("Generate a passphrase made of random words instead"),
+        Arg::new("words")
+            .long("words")
+            .takes_value(true)
+            .about("Use this many words in the passphrase (default: enough for 77 bits of entropy)")
+            .validator(validate_arg_digits),
+        Arg::new("separator")
+            .long("separator")
+            .takes_value(true)
+            .about("Put this between the words of the passphrase (default: -)"),
+        Arg::new("capitalize")
+            .long("capitalize")
+            .about("Start each word of the passphrase with an uppercase letter"),
+        Arg::new("add-digit")
+            .long("add-digit")
+            .about("Add a digit to one of the words of the passphrase"),
+        Arg::new("wordlist")
+            .long("wordlist")
+            .takes_value(true)
+            .about("Pick words from this file (one word per line) instead of the built-in list"),
+        Arg::new("comment")
+            .long("comment")
+            .takes_value(true)
+            .about("Put this comment in the public key of generated SSH keys"),
+    ]
+}
+
 fn open_password_file(filename: &str) -> IoResult<File> {
     let mut options = std::fs::OpenOptions::new();
     options.read(true);
@@ -63,6 +234,8 @@ fn sync_password_store(
     file: &mut File,
     io: &mut impl CliInputOutput,
 ) -> Result<(), i32> {
+    // Commands that keep running, like `rooster serve`, may be writing the file too.
+    let _lock = FileLock::lock(file).map_err(|_| 1)?;
     if let Err(err) = store.sync(file) {
         io.error(
             format!("I could not save the password file (reason: {:?}).", err),
@@ -76,13 +249,59 @@ fn sync_password_store(
 
 fn get_password_store(
     file: &mut File,
+    rooster_file_path: &Path,
+    use_key_cache: bool,
     io: &mut impl CliInputOutput,
 ) -> Result<password::v2::PasswordStore, i32> {
     // Read the Rooster file contents.
     let mut input: SafeVec = SafeVec::new(Vec::new());
-    file.read_to_end(input.inner_mut()).map_err(|_| 1)?;
+    {
+        let _lock = FileLock::lock(file).map_err(|_| 1)?;
+        file.read_to_end(input.inner_mut()).map_err(|_| 1)?;
+    }
+
+    let key_caches = key_cache::from_env();
+
+    if use_key_cache {
+        for key_cache in key_caches.iter() {
+            if let Some(store) =
+                get_password_store_from_key_cache(key_cache.as_ref(), &input, rooster_file_path)
+            {
+                return Ok(store);
+            }
+        }
+    }
+
+    let store =
+        get_password_store_from_input_interactive(&input, 3, false, false, io).map_err(|_| 1)?;
 
-    return get_password_store_from_input_interactive(&input, 3, false, false, io).map_err(|_| 1);
+    cache_key(&key_caches, rooster_file_path, &store);
+
+    return Ok(store);
+}
+
+fn get_password_store_from_key_cache(
+    key_cache: &dyn KeyCache,
+    input: &SafeVec,
+    rooster_file_path: &Path,
+) -> Option<password::v2::PasswordStore> {
+    let key = key_cache.get_key(rooster_file_path).ok()??;
+
+    match password::v2::PasswordStore::from_input_with_key(key, input.clone()) {
+        Ok(store) => Some(store),
+        Err(_) => {
+            // The master password or the scrypt parameters changed since the key was cached.
+            let _ = key_cache.forget_key(rooster_file_path);
+            None
+        }
+    }
+}
+
+fn cache_key(key_caches: &[Box<dyn KeyCache>], rooster_file_path: &Path, store: &PasswordStore) {
+    // Key caches are a convenience, Rooster works the same if they can't be reached.
+    for key_cache in key_caches.iter() {
+        let _ = key_cache.put_key(rooster_file_path, store.key());
+    }
 }
 
 fn get_password_store_from_input_interactive(
@@ -239,6 +458,21 @@ pub fn main_with_args(
         .setting(AppSettings::SubcommandRequiredElseHelp)
         .about("Welcome to Rooster, the simple password manager for geeks :-)")
         .version(env!("CARGO_PKG_VERSION"))
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .global(true)
+                .takes_value(true)
+                .possible_values(&["text", "json"])
+                .default_value("text")
+                .about("Print results and errors as JSON documents, for scripts"),
+        )
+        .arg(
+            Arg::new("no-input")
+                .long("no-input")
+                .global(true)
+                .about("Fail instead of asking questions, like which app you mean or your master password"),
+        )
         .subcommand(
             App::new("init").about("Create a new password file").arg(
                 Arg::new("force-for-tests")
@@ -280,14 +514,18 @@ pub fn main_with_args(
                         .short('s')
                         .long("show")
                         .about("Show the password instead of copying it to the clipboard"),
-                ),
+                )
+                .args(selection_args()),
         )
         .subcommand(
-            App::new("delete").about("Delete a password").arg(
-                Arg::new("app")
-                    .required(true)
-                    .about("The name of the app (fuzzy-matched)"),
-            ),
+            App::new("delete")
+                .about("Delete a password")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .about("The name of the app (fuzzy-matched)"),
+                )
+                .args(selection_args()),
         )
         .subcommand(
             App::new("generate")
@@ -308,20 +546,7 @@ pub fn main_with_args(
                         .long("show")
                         .about("Show the password instead of copying it to the clipboard"),
                 )
-                .arg(
-                    Arg::new("alnum")
-                        .short('a')
-                        .long("alnum")
-                        .about("Only use alpha numeric (a-z, A-Z, 0-9) in generated passwords"),
-                )
-                .arg(
-                    Arg::new("length")
-                        .short('l')
-                        .long("length")
-                        .default_value("32")
-                        .about("Set a custom length for the generated password")
-                        .validator(validate_arg_digits),
-                ),
+                .args(generator_args()),
         )
         .subcommand(
             App::new("regenerate")
@@ -337,20 +562,13 @@ pub fn main_with_args(
                         .long("show")
                         .about("Show the password instead of copying it to the clipboard"),
                 )
-                .arg(
-                    Arg::new("alnum")
-                        .short('a')
-                        .long("alnum")
-                        .about("Only use alpha numeric (a-z, A-Z, 0-9) in generated passwords"),
-                )
-                .arg(
-                    Arg::new("length")
-                        .short('l')
-                        .long("length")
-                        .default_value("32")
-                        .about("Set a custom length for the generated password")
-                        .validator(validate_arg_digits),
-                ),
+                .args(generator_args())
+                .args(selection_args()),
+        )
+        .subcommand(
+            App::new("gen")
+                .about("Print a new secret without saving it")
+                .args(generator_args()),
         )
         .subcommand(
             App::new("get")
@@ -365,7 +583,54 @@ pub fn main_with_args(
                         .short('s')
                         .long("show")
                         .about("Show the password instead of copying it to the clipboard"),
-                ),
+                )
+                .arg(
+                    Arg::new("print")
+                        .long("print")
+                        .takes_value(true)
+                        .value_name("FIELD")
+                        .conflicts_with("show")
+                        .about("Print only this field (password, username, name or an extra field) for scripts"),
+                )
+                .args(selection_args()),
+        )
+        .subcommand(
+            App::new("type")
+                .about("Type a username and password into the focused window")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .about("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("delay")
+                        .short('d')
+                        .long("delay")
+                        .default_value("2")
+                        .about("Wait this many seconds before typing, to focus the right window")
+                        .validator(validate_arg_digits),
+                )
+                .args(selection_args()),
+        )
+        .subcommand(
+            App::new("field")
+                .about("See or change the extra fields of a password, like its auto-type sequence")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .about("The name of the app (fuzzy-matched)"),
+                )
+                .arg(Arg::new("name").about("The name of the field, all fields are listed if omitted"))
+                .arg(Arg::new("value").about("The new value of the field"))
+                .arg(
+                    Arg::new("unset")
+                        .short('u')
+                        .long("unset")
+                        .requires("name")
+                        .conflicts_with("value")
+                        .about("Remove the field"),
+                )
+                .args(selection_args()),
         )
         .subcommand(
             App::new("rename")
@@ -379,7 +644,8 @@ pub fn main_with_args(
                     Arg::new("new_name")
                         .required(true)
                         .about("The new name of the app"),
-                ),
+                )
+                .args(selection_args()),
         )
         .subcommand(
             App::new("transfer")
@@ -393,9 +659,164 @@ pub fn main_with_args(
                     Arg::new("new_username")
                         .required(true)
                         .about("Your new username for this account"),
+                )
+                .args(selection_args()),
+        )
+        .subcommand(
+            App::new("run")
+                .about("Run a command with passwords in its environment variables")
+                .arg(
+                    Arg::new("env")
+                        .short('e')
+                        .long("env")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .required(true)
+                        .value_name("VAR=app:field")
+                        .about("Set the variable VAR to this field (password, username, name or an extra field) of the app"),
+                )
+                .arg(
+                    Arg::new("command")
+                        .required(true)
+                        .multiple(true)
+                        .last(true)
+                        .about("The command to run, after --"),
+                )
+                .args(selection_args()),
+        )
+        .subcommand(
+            App::new("exec")
+                .about("Run a command that asks for a password on its terminal, and type it")
+                .arg(
+                    Arg::new("pty")
+                        .long("pty")
+                        .required(true)
+                        .about("Run the command in a pseudo-terminal, watching it for password prompts"),
+                )
+                .arg(
+                    Arg::new("answer")
+                        .short('a')
+                        .long("answer")
+                        .takes_value(true)
+                        .multiple_occurrences(true)
+                        .value_name("PATTERN => APP")
+                        .about("Type the password of APP at prompts like PATTERN"),
+                )
+                .arg(
+                    Arg::new("command")
+                        .required(true)
+                        .multiple(true)
+                        .last(true)
+                        .about("The command to run, after --"),
+                ),
+        )
+        .subcommand(
+            App::new("inject")
+                .about("Fill in a template, replacing references like rooster://app/password with their values")
+                .arg(
+                    Arg::new("input")
+                        .short('i')
+                        .long("input")
+                        .takes_value(true)
+                        .required(true)
+                        .about("The template"),
+                )
+                .arg(
+                    Arg::new("output-file")
+                        .short('o')
+                        .long("output-file")
+                        .takes_value(true)
+                        .about("Write to this file, only readable by you, instead of printing"),
+                ),
+        )
+        .subcommand(
+            App::new("git-credential")
+                .about("Give passwords to git, as a credential helper")
+                .arg(
+                    Arg::new("operation")
+                        .required(true)
+                        .possible_values(&["get", "store", "erase"])
+                        .about("What git asks for"),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .takes_value(true)
+                        .default_value("{host}")
+                        .about("How apps are named, with {protocol}, {host}, {username} and {path}, for apps without a matching url field"),
+                ),
+        )
+        .subcommand(
+            App::new("serve")
+                .about("Answer other programs over a Unix socket, with JSON-RPC")
+                .arg(
+                    Arg::new("socket")
+                        .long("socket")
+                        .takes_value(true)
+                        .required(true)
+                        .about("The path of the socket, which only you can use"),
+                )
+                .arg(
+                    Arg::new("confirm")
+                        .long("confirm")
+                        .about("Ask before answering each call"),
+                ),
+        )
+        .subcommand(
+            App::new("secret-service")
+                .about("Give passwords to programs that use a keyring, through the Secret Service API")
+                .arg(
+                    Arg::new("address")
+                        .long("address")
+                        .takes_value(true)
+                        .about("The bus to serve on, instead of the session bus"),
+                ),
+        )
+        .subcommand(
+            App::new("native-host")
+                .about("Give passwords to a browser extension, through native messaging")
+                .arg(
+                    Arg::new("caller")
+                        .multiple(true)
+                        .about("Who started Rooster, as the browser tells it"),
+                )
+                .subcommand(
+                    App::new("install")
+                        .about("Tell the browser how to start Rooster for an extension")
+                        .arg(
+                            Arg::new("browser")
+                                .required(true)
+                                .possible_values(&["chrome", "chromium", "firefox"])
+                                .about("The browser of the extension"),
+                        )
+                        .arg(
+                            Arg::new("extension-id")
+                                .long("extension-id")
+                                .takes_value(true)
+                                .required(true)
+                                .about("The ID of the extension allowed to talk to Rooster"),
+                        )
+                        .arg(
+                            Arg::new("manifest-dir")
+                                .long("manifest-dir")
+                                .takes_value(true)
+                                .about("Where to write the manifest, if the browser looks somewhere else"),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("askpass")
+                .about("Give a password to ssh or sudo, as SSH_ASKPASS or SUDO_ASKPASS")
+                .arg(
+                    Arg::new("prompt")
+                        .required(true)
+                        .about("What ssh or sudo asks, which tells which app the password is for"),
                 ),
         )
         .subcommand(App::new("list").about("List all apps and usernames"))
+        .subcommand(App::new("ui").about(
+            "Browse, search and edit your passwords in a full-screen terminal interface",
+        ))
         .subcommand(
             App::new("import")
                 .setting(AppSettings::SubcommandRequiredElseHelp)
@@ -437,6 +858,151 @@ pub fn main_with_args(
                 .subcommand(
                     App::new("1password")
                         .about("Export raw password data in 1Password compatible CSV format"),
+                )
+                .subcommand(
+                    App::new("env")
+                        .about("Export usernames and passwords as a .env file, like PROD_DB_PASSWORD=\"...\"")
+                        .arg(query_arg()),
+                )
+                .subcommand(
+                    App::new("k8s-secret")
+                        .about("Export usernames and passwords as a Kubernetes Secret manifest")
+                        .arg(
+                            Arg::new("name")
+                                .long("name")
+                                .takes_value(true)
+                                .required(true)
+                                .about("The name of the Secret"),
+                        )
+                        .arg(
+                            Arg::new("namespace")
+                                .long("namespace")
+                                .takes_value(true)
+                                .about("The namespace of the Secret"),
+                        )
+                        .arg(query_arg()),
+                )
+                .subcommand(
+                    App::new("systemd-creds")
+                        .about("Export usernames and passwords as a directory of systemd credentials, one file each")
+                        .arg(
+                            Arg::new("directory")
+                                .required(true)
+                                .about("The directory to write the credentials in, created if needed"),
+                        )
+                        .arg(query_arg()),
+                ),
+        )
+        .subcommand(
+            App::new("expiry")
+                .about("See or change when a password needs to be changed")
+                .arg(
+                    Arg::new("app")
+                        .required(true)
+                        .about("The name of the app (fuzzy-matched)"),
+                )
+                .arg(
+                    Arg::new("every")
+                        .long("every")
+                        .takes_value(true)
+                        .about("Change the password every this many days")
+                        .validator(validate_arg_rotation_days),
+                )
+                .arg(
+                    Arg::new("on")
+                        .long("on")
+                        .takes_value(true)
+                        .about("Change the password before this date (for instance 2030-01-31)"),
+                )
+                .arg(
+                    Arg::new("never")
+                        .long("never")
+                        .conflicts_with_all(&["every", "on"])
+                        .about("Never ask to change the password"),
+                )
+                .args(selection_args()),
+        )
+        .subcommand(
+            App::new("stale")
+                .about("List passwords that need to be changed")
+                .arg(
+                    Arg::new("within")
+                        .short('w')
+                        .long("within")
+                        .default_value("0")
+                        .about("Also list passwords that need to be changed within this many days")
+                        .validator(validate_arg_window_days),
+                ),
+        )
+        .subcommand(
+            App::new("audit")
+                .about("Report reused, weak and old passwords")
+                .arg(
+                    Arg::new("min-score")
+                        .long("min-score")
+                        .default_value("3")
+                        .about("Report passwords with a strength score below this one, from 0 to 4")
+                        .validator(validate_arg_score),
+                )
+                .arg(
+                    Arg::new("max-age")
+                        .long("max-age")
+                        .default_value("365")
+                        .about("Report passwords not changed in this many days (0 to never report them)")
+                        .validator(validate_arg_days),
+                )
+                .arg(
+                    Arg::new("hibp-file")
+                        .long("hibp-file")
+                        .takes_value(true)
+                        .about("Also report passwords that appear in this Pwned Passwords file"),
+                )
+                .subcommand(
+                    App::new("breaches")
+                        .about("List passwords that appear in a Have I Been Pwned password file")
+                        .arg(
+                            Arg::new("hibp-file")
+                                .long("hibp-file")
+                                .takes_value(true)
+                                .required(true)
+                                .about("The Pwned Passwords file (SHA-1, ordered by hash)"),
+                        ),
+                ),
+        )
+        .subcommand(
+            App::new("agent")
+                .about("Start an agent that remembers your master password for a while")
+                .arg(
+                    Arg::new("socket")
+                        .long("socket")
+                        .takes_value(true)
+                        .about("The path of the Unix socket to listen on"),
+                )
+                .arg(
+                    Arg::new("timeout")
+                        .short('t')
+                        .long("timeout")
+                        .default_value("900")
+                        .about("Forget the master password after this many idle seconds (0 to never forget)")
+                        .validator(validate_arg_digits),
+                )
+                .arg(
+                    Arg::new("foreground")
+                        .short('f')
+                        .long("foreground")
+                        .about("Stay in the foreground instead of running in the background"),
+                ),
+        )
+        .subcommand(App::new("lock").about("Make Rooster forget your cached master password"))
+        .subcommand(
+            App::new("clear-clipboard")
+                .setting(AppSettings::Hidden)
+                .about("Clear the clipboard after a while, used internally")
+                .arg(
+                    Arg::new("seconds")
+                        .required(true)
+                        .about("How many seconds to wait before clearing the clipboard")
+                        .validator(validate_arg_digits),
                 ),
         )
         .subcommand(App::new("set-master-password").about("Set your master password"))
@@ -468,12 +1034,55 @@ pub fn main_with_args(
                         .about("Disable parameter checks"),
                 ),
         )
-        .get_matches_from(args);
+        .try_get_matches_from(args);
+
+    let matches = match matches {
+        Ok(matches) => matches,
+        Err(err) if wants_json_output(args) => {
+            print_json_error(io, &err.to_string(), 2);
+            return 2;
+        }
+        Err(err) => err.exit(),
+    };
+
+    if matches.is_present("no-input") {
+        let mut no_input_io = NoInputOutput::new(io);
+        return run_command_with_output(&matches, &mut no_input_io, rooster_file_path);
+    }
 
+    run_command_with_output(&matches, io, rooster_file_path)
+}
+
+fn run_command_with_output(
+    matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &PathBuf,
+) -> i32 {
     let subcommand = matches.subcommand_name().unwrap();
 
     let command_matches = matches.subcommand_matches(subcommand).unwrap();
 
+    if matches.value_of("output") == Some("json") {
+        let mut json_io = JsonInputOutput::new(io, subcommand);
+        let code = run_command(subcommand, command_matches, &mut json_io, rooster_file_path);
+        json_io.finish(code);
+        return code;
+    }
+
+    run_command(subcommand, command_matches, io, rooster_file_path)
+}
+
+/// Whether `--output json` was asked for, even if the other arguments are wrong
+fn wants_json_output(args: &[&str]) -> bool {
+    args.contains(&"--output=json") || args.windows(2).any(|pair| pair == ["--output", "json"])
+}
+
+fn run_command(
+    subcommand: &str,
+    command_matches: &clap::ArgMatches,
+    io: &mut impl CliInputOutput,
+    rooster_file_path: &PathBuf,
+) -> i32 {
     if subcommand == "init" {
         match commands::init::callback_exec(command_matches, io, rooster_file_path) {
             Err(i) => return i,
@@ -481,6 +1090,43 @@ pub fn main_with_args(
         }
     }
 
+    if subcommand == "agent" {
+        match commands::agent::callback_exec(command_matches, io) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if subcommand == "gen" {
+        match commands::gen::callback_exec(command_matches, io) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if subcommand == "clear-clipboard" {
+        match commands::clear_clipboard::callback_exec(command_matches, io) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
+    if let Some(install_matches) = command_matches.subcommand_matches("install") {
+        if subcommand == "native-host" {
+            match commands::native_host::install(install_matches, io, rooster_file_path) {
+                Err(i) => return i,
+                _ => return 0,
+            }
+        }
+    }
+
+    if subcommand == "lock" {
+        match commands::lock::callback_exec(command_matches, io, rooster_file_path) {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
+
     let password_file_path_as_string = rooster_file_path.to_string_lossy().into_owned();
 
     if !rooster_file_path.exists() {
@@ -525,13 +1171,37 @@ pub fn main_with_args(
         }
     };
 
-    let mut store = match get_password_store(&mut file, io) {
-        Err(code) => return code,
-        Ok(store) => store,
-    };
+    // These commands need the master password itself, a cached key is not enough.
+    let needs_master_password =
+        subcommand == "set-master-password" || subcommand == "set-scrypt-params";
+
+    let mut store =
+        match get_password_store(&mut file, rooster_file_path, !needs_master_password, io) {
+            Err(code) => return code,
+            Ok(store) => store,
+        };
+
+    // These commands keep running, saving changes as they make them.
+    if subcommand == "native-host" || subcommand == "serve" || subcommand == "secret-service" {
+        let result = match subcommand {
+            "native-host" => {
+                commands::native_host::callback_exec(command_matches, &mut store, &mut file, io)
+            }
+            "secret-service" => {
+                commands::secret_service::callback_exec(command_matches, &mut store, &mut file, io)
+            }
+            _ => commands::serve::callback_exec(command_matches, &mut store, &mut file, io),
+        };
+        match result {
+            Err(i) => return i,
+            _ => return 0,
+        }
+    }
 
     let callback = match subcommand {
         "get" => commands::get::callback_exec,
+        "type" => commands::autotype::callback_exec,
+        "field" => commands::field::callback_exec,
         "add" => commands::add::callback_exec,
         "delete" => commands::delete::callback_exec,
         "generate" => commands::generate::callback_exec,
@@ -539,11 +1209,20 @@ pub fn main_with_args(
         "list" => commands::list::callback_exec,
         "import" => commands::import::callback_exec,
         "export" => commands::export::callback_exec,
+        "audit" => commands::audit::callback_exec,
+        "expiry" => commands::expiry::callback_exec,
+        "stale" => commands::stale::callback_exec,
         "set-master-password" => commands::set_master_password::callback_exec,
         "set-scrypt-params" => commands::set_scrypt_params::callback_exec,
         "rename" => commands::rename::callback_exec,
         "transfer" => commands::transfer::callback_exec,
         "change" => commands::change::callback_exec,
+        "ui" => commands::ui::callback_exec,
+        "run" => commands::run::callback_exec,
+        "exec" => commands::exec::callback_exec,
+        "inject" => commands::inject::callback_exec,
+        "git-credential" => commands::git_credential::callback_exec,
+        "askpass" => commands::askpass::callback_exec,
         _ => unreachable!("Validation should have been done by `clap` before"),
     };
 
@@ -551,9 +1230,19 @@ pub fn main_with_args(
         return code;
     }
 
+    // The command we ran may have changed the password file with Rooster itself, and we have
+    // nothing to save anyway.
+    if subcommand == "run" || subcommand == "exec" {
+        return 0;
+    }
+
     if let Err(code) = sync_password_store(&mut store, &mut file, io) {
         return code;
     }
 
+    if needs_master_password {
+        cache_key(&key_cache::from_env(), rooster_file_path, &store);
+    }
+
     return 0;
 }
@@ -1,5 +1,6 @@
 use crate::password::v2::{Password, PasswordStore};
 use crate::rclio::{CliInputOutput, OutputType};
+use serde_json::json;
 
 /// Used to indicate lists should have a number, ie: 23 Google my.account@gmail.com
 pub const WITH_NUMBERS: bool = true;
@@ -141,13 +142,53 @@ fn choose_password_in_list(
     request_password_index_from_stdin(passwords, prompt, io)
 }
 
+/// How to choose between several passwords matching a query
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Selection {
+    /// Ask which one, unless an app has exactly the name that was searched for
+    Ask,
+    /// Only take an app with exactly the name that was searched for
+    Exact,
+    /// Take the best match
+    First,
+    /// Take the Nth match, starting from 1 as in the list shown when asking
+    Index(usize),
+}
+
+impl Selection {
+    pub fn from_matches(matches: &clap::ArgMatches) -> Selection {
+        if matches.is_present("exact") {
+            Selection::Exact
+        } else if matches.is_present("first") {
+            Selection::First
+        } else if let Some(index) = matches.value_of("index") {
+            Selection::Index(index.parse().unwrap_or(0))
+        } else {
+            Selection::Ask
+        }
+    }
+}
+
 pub fn search_and_choose_password<'a>(
     store: &'a PasswordStore,
     query: &str,
+    selection: Selection,
     with_numbers: bool,
     prompt: &str,
     io: &mut impl CliInputOutput,
 ) -> Option<&'a Password> {
+    // Names can look like queries, like "host:8080" which searches a "host" field, so an app with
+    // that exact name comes first.
+    if !matches!(selection, Selection::Index(_)) {
+        if let Some(password) = store
+            .get_all_passwords()
+            .into_iter()
+            .find(|p| p.name.to_lowercase() == query.to_lowercase())
+        {
+            return Some(password);
+        }
+    }
+
     let passwords = store.search_passwords(query);
     if passwords.len() == 0 {
         io.error(
@@ -157,11 +198,43 @@ pub fn search_and_choose_password<'a>(
         return None;
     }
 
-    if let Some(&password) = passwords
-        .iter()
-        .find(|p| p.name.to_lowercase() == query.to_lowercase())
-    {
-        return Some(&password);
+    if let Selection::Index(index) = selection {
+        if index == 0 || index > passwords.len() {
+            io.error(
+                format!(
+                    "Woops, \"{}\" matches {} apps, so I need an index between 1 and {}.",
+                    query,
+                    passwords.len(),
+                    passwords.len()
+                ),
+                OutputType::Error,
+            );
+            return None;
+        }
+        return Some(passwords[index - 1]);
+    }
+
+    if selection == Selection::First {
+        return Some(passwords[0]);
+    }
+
+    if selection == Selection::Exact || !io.is_interactive() {
+        io.result(json!({
+            "candidates": passwords
+                .iter()
+                .map(|password| password.to_json(false))
+                .collect::<Vec<_>>()
+        }));
+        io.error(
+            format!(
+                "Woops, \"{}\" doesn't match an app name exactly. Use --first or --index to choose \
+                 between the {} apps that match.",
+                query,
+                passwords.len()
+            ),
+            OutputType::Error,
+        );
+        return None;
     }
 
     let index = choose_password_in_list(&passwords, with_numbers, prompt, io);
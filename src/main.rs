@@ -1,9 +1,11 @@
 use rooster::rclio::RegularInputOutput;
 use std::env::VarError;
-use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
 
 const ROOSTER_FILE_ENV_VAR: &'static str = "ROOSTER_FILE";
 const ROOSTER_FILE_DEFAULT: &'static str = ".passwords.rooster";
+const ROOSTER_ASKPASS_PROGRAM: &'static str = "rooster-askpass";
 
 fn get_password_file_path() -> Result<PathBuf, i32> {
     // First, look for the ROOSTER_FILE environment variable.
@@ -28,7 +30,18 @@ fn get_password_file_path() -> Result<PathBuf, i32> {
 }
 
 fn main() {
-    let args = std::env::args().collect::<Vec<String>>();
+    let mut args = std::env::args().collect::<Vec<String>>();
+
+    // SSH_ASKPASS and SUDO_ASKPASS can't have arguments, so a link named rooster-askpass stands
+    // for `rooster askpass`.
+    let program = args
+        .get(0)
+        .map(|arg| Path::new(arg))
+        .and_then(|path| path.file_name());
+    if program == Some(OsStr::new(ROOSTER_ASKPASS_PROGRAM)) {
+        args.splice(1..1, vec!["askpass".to_owned(), "--".to_owned()]);
+    }
+
     let args_refs = args.iter().map(|s| s.as_str()).collect::<Vec<&str>>();
 
     let rooster_file_path = get_password_file_path().unwrap_or_else(|err| std::process::exit(err));
@@ -0,0 +1,68 @@
+use crate::password::v2::Password;
+
+/// Field holding the URL of an app, which websites and git servers are matched against
+pub const URL_FIELD: &str = "url";
+
+/// Splits a URL like `https://git.example.com/team/repo` into its protocol, if any, and host
+pub fn protocol_and_host(url: &str) -> (Option<&str>, &str) {
+    let (protocol, rest) = match url.find("://") {
+        Some(i) => (Some(&url[..i]), &url[i + 3..]),
+        None => (None, url),
+    };
+    let host = match rest.find('/') {
+        Some(i) => &rest[..i],
+        None => rest,
+    };
+    (protocol, host)
+}
+
+/// The only protocol we give passwords over when the user didn't name one, because the others
+/// would send them in cleartext
+pub const DEFAULT_PROTOCOL: &str = "https";
+
+/// Whether the URL field of the password is for this protocol and host. A URL without a
+/// protocol is only for https.
+pub fn url_matches(password: &Password, protocol: &str, host: &str) -> bool {
+    match password.fields.get(URL_FIELD) {
+        Some(url) => {
+            let (url_protocol, url_host) = protocol_and_host(url);
+            url_host.eq_ignore_ascii_case(host)
+                && url_protocol.unwrap_or(DEFAULT_PROTOCOL) == protocol
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{protocol_and_host, url_matches, URL_FIELD};
+    use crate::password::v2::Password;
+
+    #[test]
+    fn test_protocol_and_host() {
+        assert_eq!(
+            protocol_and_host("https://git.example.com:8443/team/repo"),
+            (Some("https"), "git.example.com:8443")
+        );
+        assert_eq!(
+            protocol_and_host("git.example.com"),
+            (None, "git.example.com")
+        );
+    }
+
+    #[test]
+    fn test_url_matches() {
+        let mut password = Password::new("Example", "alice", "p@ssw0rd");
+        password
+            .fields
+            .insert(URL_FIELD.to_owned(), "git.example.com".to_owned());
+        assert!(url_matches(&password, "https", "git.example.com"));
+        assert!(!url_matches(&password, "http", "git.example.com"));
+
+        password
+            .fields
+            .insert(URL_FIELD.to_owned(), "http://git.example.com".to_owned());
+        assert!(url_matches(&password, "http", "git.example.com"));
+        assert!(!url_matches(&password, "https", "git.example.com"));
+    }
+}
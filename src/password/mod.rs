@@ -3,6 +3,7 @@ pub mod v2;
 
 use crate::rutil::safe_string::SafeString;
 use crate::rutil::safe_vec::SafeVec;
+use std::collections::BTreeMap;
 use std::convert::From;
 use std::io::Error as IoError;
 use std::ops::Deref;
@@ -40,6 +41,9 @@ fn upgrade_v1_v2(
             password: p.password.clone(),
             created_at: p.created_at,
             updated_at: p.updated_at,
+            fields: BTreeMap::new(),
+            rotation_days: None,
+            expires_at: None,
         };
         v2_store.add_password(v2_password)?;
     }
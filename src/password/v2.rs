@@ -3,11 +3,13 @@ use crate::ffi;
 use crate::password::PasswordError;
 use crate::rutil::safe_string::SafeString;
 use crate::rutil::safe_vec::SafeVec;
+use crate::search;
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use rand::{rngs::OsRng, RngCore};
 use serde::{Deserialize, Serialize};
 use serde_json;
 use serde_json::Error;
+use std::collections::BTreeMap;
 use std::fs::File;
 use std::io::{
     Cursor, Error as IoError, ErrorKind as IoErrorKind, Read, Result as IoResult, Seek, SeekFrom,
@@ -224,6 +226,15 @@ pub struct Password {
     pub password: SafeString,
     pub created_at: ffi::time_t,
     pub updated_at: ffi::time_t,
+    /// Extra information about the account, such as the auto-type sequence
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub fields: BTreeMap<String, String>,
+    /// How many days the password can be used before it needs to be changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rotation_days: Option<u32>,
+    /// When the password stops working, no matter when it was last changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<ffi::time_t>,
 }
 
 impl Password {
@@ -239,8 +250,38 @@ impl Password {
             password: password.into(),
             created_at: timestamp,
             updated_at: timestamp,
+            fields: BTreeMap::new(),
+            rotation_days: None,
+            expires_at: None,
         }
     }
+
+    /// The value of `password`, `username`, `name` or one of the extra fields
+    pub fn field_value(&self, field: &str) -> Option<SafeString> {
+        match field {
+            "password" => Some(self.password.clone()),
+            "username" => Some(self.username.as_str().into()),
+            "name" => Some(self.name.as_str().into()),
+            _ => self.fields.get(field).map(|value| value.as_str().into()),
+        }
+    }
+
+    /// How the password appears in `--output json`, with the secret itself only if asked for
+    pub fn to_json(&self, with_secret: bool) -> serde_json::Value {
+        let mut json = serde_json::json!({
+            "name": self.name,
+            "username": self.username,
+            "created_at": self.created_at,
+            "updated_at": self.updated_at,
+            "fields": self.fields,
+            "rotation_days": self.rotation_days,
+            "expires_at": self.expires_at,
+        });
+        if with_secret {
+            json["password"] = serde_json::Value::String(self.password.deref().clone());
+        }
+        json
+    }
 }
 
 pub struct PasswordStore {
@@ -250,7 +291,8 @@ pub struct PasswordStore {
     scrypt_p: u32,
     salt: [u8; SALT_LEN],
     schema: Schema,
-    master_password: String,
+    // Not known when the store was opened with a cached key.
+    master_password: Option<String>,
 }
 
 /// Read and writes to a Rooster file
@@ -282,13 +324,42 @@ impl PasswordStore {
             scrypt_p: SCRYPT_PARAM_P,
             salt: salt,
             schema: Schema::new(),
-            master_password: master_password.into_inner(),
+            master_password: Some(master_password.into_inner()),
         })
     }
 
     pub fn from_input(
         master_password: SafeString,
         input: SafeVec,
+    ) -> Result<PasswordStore, PasswordError> {
+        let mut store = PasswordStore::from_input_with_key_derivation(
+            input,
+            |salt, scrypt_log2_n, scrypt_r, scrypt_p| {
+                generate_encryption_key(
+                    master_password.deref(),
+                    salt,
+                    scrypt_log2_n,
+                    scrypt_r,
+                    scrypt_p,
+                )
+            },
+        )?;
+        store.master_password = Some(master_password.deref().into());
+        Ok(store)
+    }
+
+    /// Opens a Rooster file with a key that was derived previously, for instance by a
+    /// `rooster agent`. This skips the (slow) key derivation entirely.
+    pub fn from_input_with_key(
+        key: SafeVec,
+        input: SafeVec,
+    ) -> Result<PasswordStore, PasswordError> {
+        PasswordStore::from_input_with_key_derivation(input, |_, _, _, _| key.clone())
+    }
+
+    fn from_input_with_key_derivation(
+        input: SafeVec,
+        derive_key: impl Fn([u8; SALT_LEN], u8, u32, u32) -> SafeVec,
     ) -> Result<PasswordStore, PasswordError> {
         let mut reader = Cursor::new(input.deref());
 
@@ -342,13 +413,7 @@ impl PasswordStore {
         reader.read_to_end(&mut blob)?;
 
         // Derive a 256 bits encryption key from the password.
-        let key = generate_encryption_key(
-            master_password.deref(),
-            salt,
-            scrypt_log2_n,
-            scrypt_r,
-            scrypt_p,
-        );
+        let key = derive_key(salt, scrypt_log2_n, scrypt_r, scrypt_p);
 
         // Decrypt the data.
         let passwords = match aes::decrypt(blob.deref(), key.as_ref(), iv.as_ref()) {
@@ -401,10 +466,22 @@ impl PasswordStore {
             schema: Schema {
                 passwords: passwords,
             },
-            master_password: master_password.deref().into(),
+            master_password: None,
         })
     }
 
+    /// Reads the passwords from the file again, since other Rooster commands may have changed it
+    /// since we opened it. This needs the key to be the same, which changes along with the master
+    /// password or the scrypt parameters.
+    pub fn reload(&mut self, file: &mut File) -> Result<(), PasswordError> {
+        let mut input = SafeVec::new(Vec::new());
+        file.seek(SeekFrom::Start(0))?;
+        file.read_to_end(input.inner_mut())?;
+        let store = PasswordStore::from_input_with_key(self.key.clone(), input)?;
+        self.schema = store.schema;
+        Ok(())
+    }
+
     pub fn sync(&self, file: &mut File) -> Result<(), PasswordError> {
         // This should never fail. The structs are all encodable.
         let json_schema = match serde_json::to_string(&self.schema) {
@@ -464,6 +541,11 @@ impl PasswordStore {
         Ok(())
     }
 
+    /// The key derived from the master password, used to cache it between invocations.
+    pub fn key(&self) -> &SafeVec {
+        &self.key
+    }
+
     pub fn get_all_passwords(&self) -> Vec<&Password> {
         let mut passwords: Vec<&Password> = self.schema.passwords.iter().collect();
 
@@ -501,55 +583,9 @@ impl PasswordStore {
         unreachable!();
     }
 
-    pub fn search_passwords(&self, name: &str) -> Vec<&Password> {
-        // Fuzzy search password app names.
-        let keys = self
-            .schema
-            .passwords
-            .iter()
-            .map(|p| p.name.to_lowercase())
-            .collect::<Vec<String>>();
-
-        let mut search_results = vec![];
-        // Check if each app name can be matched against the search query.
-        //
-        // It's fine if there are some characters left out in the query. For instance, you can
-        // search for the app "Facebook" with just "fcbk".
-        for app_name in keys.iter().map(|s| s.as_str()) {
-            let mut matches_query = true;
-            let mut last_i = 0;
-            for c in name.chars() {
-                let c_lowercase = format!("{}", c).to_lowercase();
-                match app_name[last_i..].find(c_lowercase.as_str()) {
-                    // Query chars must be present in the app name in the right order.
-                    Some(ic) => {
-                        last_i += ic + 1;
-                    }
-                    // Query char is not present, no match.
-                    None => {
-                        matches_query = false;
-                        break;
-                    }
-                }
-            }
-
-            if matches_query {
-                search_results.push(app_name.to_owned());
-            }
-        }
-
-        let mut passwords = vec![];
-        for p in self.schema.passwords.iter() {
-            if search_results.contains(&p.name.to_lowercase()) {
-                passwords.push(p);
-            }
-        }
-
-        passwords.sort_by_key(|p| {
-            return p.name.to_lowercase();
-        });
-
-        passwords
+    /// Searches passwords with a query like `user:alice tag:work git`, best matches first
+    pub fn search_passwords(&self, query: &str) -> Vec<&Password> {
+        search::search(&self.schema.passwords, query)
     }
 
     pub fn get_password(&self, name: &str) -> Option<Password> {
@@ -603,6 +639,7 @@ impl PasswordStore {
             self.scrypt_r,
             self.scrypt_p,
         );
+        self.master_password = Some(master_password.into());
     }
 
     pub fn change_scrypt_params(&mut self, scrypt_log2_n: u8, scrypt_r: u32, scrypt_p: u32) {
@@ -610,8 +647,13 @@ impl PasswordStore {
         self.scrypt_r = scrypt_r;
         self.scrypt_p = scrypt_p;
 
+        let master_password = self
+            .master_password
+            .as_ref()
+            .expect("Changing scrypt params requires the store to be opened with a password");
+
         self.key = generate_encryption_key(
-            self.master_password.deref(),
+            master_password.deref(),
             self.salt,
             self.scrypt_log2_n,
             self.scrypt_r,
@@ -787,6 +829,34 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_from_input_with_key() {
+        use crate::rutil::safe_vec::SafeVec;
+        use std::io::{Read, Seek, SeekFrom};
+
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        assert!(store
+            .add_password(Password::new("name", "username", "password"))
+            .is_ok());
+
+        let mut file = tempfile::tempfile().unwrap();
+        store.sync(&mut file).unwrap();
+        let mut input = SafeVec::new(Vec::new());
+        file.seek(SeekFrom::Start(0)).unwrap();
+        file.read_to_end(input.inner_mut()).unwrap();
+
+        let reopened =
+            PasswordStore::from_input_with_key(store.key().clone(), input.clone()).unwrap();
+        assert_eq!(
+            reopened.get_password("name").unwrap(),
+            Password::new("name", "username", "password")
+        );
+
+        // a stale key must not open the file
+        let stale_key = SafeVec::new(vec![0u8; 32]);
+        assert!(PasswordStore::from_input_with_key(stale_key, input).is_err());
+    }
+
     #[test]
     fn test_has_password() {
         let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
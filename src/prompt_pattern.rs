@@ -0,0 +1,222 @@
+use std::collections::HashMap;
+
+/// The prompts to answer, as in `alice@db01's password => db01`, separated by `;`
+///
+/// There are no default patterns: whoever prints a prompt chooses its text, and SSH servers can
+/// print any prompt they like, so which app a prompt is for has to come from the user.
+pub const ROOSTER_ASKPASS_PATTERNS_ENV_VAR: &str = "ROOSTER_ASKPASS_PATTERNS";
+
+#[derive(Debug, PartialEq)]
+enum Token {
+    Literal(String),
+    /// `{name}`, one or more characters other than spaces
+    Capture(String),
+    /// `*`, anything at all, as much as possible
+    Any,
+}
+
+/// A prompt pattern and the name of the app it is for
+#[derive(Debug, PartialEq)]
+pub struct Rule {
+    pattern: Vec<Token>,
+    app: String,
+}
+
+impl Rule {
+    /// Whether the app is named after part of the prompt, like `{host}`
+    pub fn names_app_from_prompt(&self) -> bool {
+        self.pattern.iter().any(|token| match token {
+            Token::Capture(name) => self.app.contains(&format!("{{{}}}", name)),
+            _ => false,
+        })
+    }
+}
+
+/// Prompts usually end with a colon and a space, which patterns don't have to spell out
+fn trim_prompt(prompt: &str) -> &str {
+    prompt.trim().trim_end_matches(':').trim_end()
+}
+
+fn parse_pattern(pattern: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = trim_prompt(pattern);
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('*') {
+            tokens.push(Token::Any);
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix('{') {
+            let end = after.find('}')?;
+            if end == 0 {
+                return None;
+            }
+            tokens.push(Token::Capture(after[..end].to_owned()));
+            rest = &after[end + 1..];
+        } else {
+            let end = rest.find(&['*', '{'][..]).unwrap_or(rest.len());
+            tokens.push(Token::Literal(rest[..end].to_owned()));
+            rest = &rest[end..];
+        }
+    }
+    Some(tokens)
+}
+
+/// Parses rules like `PATTERN => APP; PATTERN => APP`, returning the first invalid one on error
+pub fn parse_rules(rules: &str) -> Result<Vec<Rule>, String> {
+    rules
+        .split(';')
+        .filter(|rule| !rule.trim().is_empty())
+        .map(|rule| {
+            let arrow = rule.find("=>").ok_or_else(|| rule.trim().to_owned())?;
+            let pattern = parse_pattern(&rule[..arrow]).ok_or_else(|| rule.trim().to_owned())?;
+            let app = rule[arrow + 2..].trim();
+            if pattern.is_empty() || app.is_empty() {
+                return Err(rule.trim().to_owned());
+            }
+            Ok(Rule {
+                pattern,
+                app: app.to_owned(),
+            })
+        })
+        .collect()
+}
+
+/// Matches the whole input, trying shorter captures first
+fn match_tokens(tokens: &[Token], input: &str, captures: &mut HashMap<String, String>) -> bool {
+    let (token, rest) = match tokens.split_first() {
+        Some(split) => split,
+        None => return input.is_empty(),
+    };
+
+    match token {
+        Token::Literal(literal) => match input.strip_prefix(literal.as_str()) {
+            Some(after) => match_tokens(rest, after, captures),
+            None => false,
+        },
+        Token::Any => std::iter::once(input.len())
+            .chain(input.char_indices().rev().map(|(i, _)| i))
+            .any(|i| match_tokens(rest, &input[i..], captures)),
+        Token::Capture(name) => {
+            let max = input.find(char::is_whitespace).unwrap_or(input.len());
+            for (i, c) in input[..max].char_indices() {
+                let end = i + c.len_utf8();
+                if match_tokens(rest, &input[end..], captures) {
+                    captures.insert(name.clone(), input[..end].to_owned());
+                    return true;
+                }
+            }
+            false
+        }
+    }
+}
+
+/// The first rule that matches this prompt, and the name of the app it is for
+pub fn app_name<'a>(rules: &'a [Rule], prompt: &str) -> Option<(&'a Rule, String)> {
+    let prompt = trim_prompt(prompt);
+    rules.iter().find_map(|rule| {
+        let mut captures = HashMap::new();
+        if !match_tokens(&rule.pattern, prompt, &mut captures) {
+            return None;
+        }
+        let mut name = rule.app.clone();
+        for (key, value) in captures.iter() {
+            name = name.replace(&format!("{{{}}}", key), value);
+        }
+        Some((rule, name))
+    })
+}
+
+/// The rules from the environment. On error, returns the first invalid rule.
+pub fn configured_rules() -> Result<Vec<Rule>, String> {
+    parse_rules(&std::env::var(ROOSTER_ASKPASS_PATTERNS_ENV_VAR).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{app_name, parse_pattern, parse_rules, Rule, Token};
+
+    fn name(rules: &[Rule], prompt: &str) -> Option<String> {
+        app_name(rules, prompt).map(|(_, name)| name)
+    }
+
+    #[test]
+    fn test_parse_pattern() {
+        assert_eq!(
+            parse_pattern("*password for {user}@{host}: "),
+            Some(vec![
+                Token::Any,
+                Token::Literal("password for ".to_owned()),
+                Token::Capture("user".to_owned()),
+                Token::Literal("@".to_owned()),
+                Token::Capture("host".to_owned()),
+            ])
+        );
+        assert_eq!(parse_pattern("password for {user"), None);
+        assert_eq!(parse_pattern("password for {}"), None);
+    }
+
+    #[test]
+    fn test_parse_rules() {
+        assert_eq!(parse_rules("").unwrap().len(), 0);
+        assert_eq!(
+            parse_rules("Password for {host} => {host}; Token: => gitlab;")
+                .unwrap()
+                .len(),
+            2
+        );
+        assert_eq!(
+            parse_rules("Password for {host} => {host}; Token"),
+            Err("Token".to_owned())
+        );
+        assert_eq!(parse_rules(" => db"), Err("=> db".to_owned()));
+    }
+
+    #[test]
+    fn test_app_name() {
+        let rules =
+            parse_rules("{user}@db01's password => db01; [sudo] password for alice => sudo")
+                .unwrap();
+        assert_eq!(
+            name(&rules, "alice@db01's password: "),
+            Some("db01".to_owned())
+        );
+        assert_eq!(
+            name(&rules, "bob@db01's password:"),
+            Some("db01".to_owned())
+        );
+        assert_eq!(
+            name(&rules, "[sudo] password for alice: "),
+            Some("sudo".to_owned())
+        );
+        assert_eq!(
+            name(
+                &rules,
+                "Are you sure you want to continue connecting (yes/no/[fingerprint])? "
+            ),
+            None
+        );
+        // Servers choose what they ask, but that doesn't get them the password of other apps.
+        assert_eq!(name(&rules, "alice@github.com's password: "), None);
+        assert_eq!(name(&rules, "alice@db01's password for github.com: "), None);
+        assert!(!rules[0].names_app_from_prompt());
+    }
+
+    #[test]
+    fn test_app_name_with_custom_patterns() {
+        let rules = parse_rules(
+            "Enter passphrase for key '*/{key}' => ssh/{key}; {user}@{host}'s password => {user}@{host}",
+        )
+        .unwrap();
+        assert_eq!(
+            name(
+                &rules,
+                "Enter passphrase for key '/home/alice/.ssh/id_ed25519': "
+            ),
+            Some("ssh/id_ed25519".to_owned())
+        );
+        assert_eq!(
+            name(&rules, "alice@db01's password: "),
+            Some("alice@db01".to_owned())
+        );
+        assert!(rules.iter().all(Rule::names_app_from_prompt));
+    }
+}
@@ -1,3 +1,7 @@
 mod all;
+mod json;
+mod no_input;
 
 pub use all::*;
+pub use json::*;
+pub use no_input::*;
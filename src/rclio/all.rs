@@ -9,7 +9,7 @@ use crate::rutil::safe_string::SafeString;
 use ansi_term::Color::{Green, Red, Yellow};
 use ansi_term::Style as AnsiTermStyle;
 use std::io::Result as IoResult;
-use std::io::{Cursor, StderrLock, StdinLock, StdoutLock, Write};
+use std::io::{Cursor, Read, StderrLock, StdinLock, StdoutLock, Write};
 
 pub enum OutputType {
     Standard,
@@ -64,6 +64,12 @@ pub trait CliInputOutput {
     fn prompt_line(&mut self, prompt: impl ToString) -> IoResult<String>;
     fn read_password(&mut self) -> IoResult<SafeString>;
     fn prompt_password(&mut self, prompt: impl ToString) -> IoResult<SafeString>;
+    /// Reads everything piped to stdin, unlike `read_line` which reads from the TTY
+    fn read_stdin(&mut self) -> IoResult<String>;
+    /// Reads exactly enough bytes from stdin to fill the buffer, for binary protocols
+    fn read_stdin_exact(&mut self, buf: &mut [u8]) -> IoResult<()>;
+    /// Writes bytes as they are to stdout, for binary protocols
+    fn write_stdout(&mut self, bytes: &[u8]) -> IoResult<()>;
 
     fn nl(&mut self, output_type: OutputType);
     fn write(&mut self, s: impl ToString, output_type: OutputType);
@@ -101,6 +107,14 @@ pub trait CliInputOutput {
     fn success(&mut self, s: impl ToString, output_type: OutputType) {
         self.writeln(Green.normal().paint(s.to_string()).to_string(), output_type)
     }
+
+    /// Records the result of a command, which only `--output json` prints
+    fn result(&mut self, _result: serde_json::Value) {}
+
+    /// Whether the user can answer questions, like which of several passwords they mean
+    fn is_interactive(&self) -> bool {
+        true
+    }
 }
 
 impl<'a> CliInputOutput for RegularInputOutput<'a> {
@@ -120,22 +134,31 @@ impl<'a> CliInputOutput for RegularInputOutput<'a> {
         prompt_reply(prompt)
     }
 
+    // Passwords are read from the TTY itself, so stdin and stdout may be pipes, as they are when
+    // ssh or sudo run `rooster askpass`.
     fn read_password(&mut self) -> IoResult<SafeString> {
-        if !atty::is(atty::Stream::Stdin) {
-            panic!("Need a TTY to read password");
-        }
-
         Ok(SafeString::from_string(read_password()?))
     }
 
     fn prompt_password(&mut self, prompt: impl ToString) -> IoResult<SafeString> {
-        if !atty::is(atty::Stream::Stdin) || !atty::is(atty::Stream::Stdout) {
-            panic!("Need a TTY to read password");
-        }
-
         Ok(SafeString::from_string(prompt_password(prompt)?))
     }
 
+    fn read_stdin(&mut self) -> IoResult<String> {
+        let mut input = String::new();
+        self.stdin_lock.read_to_string(&mut input)?;
+        Ok(input)
+    }
+
+    fn read_stdin_exact(&mut self, buf: &mut [u8]) -> IoResult<()> {
+        self.stdin_lock.read_exact(buf)
+    }
+
+    fn write_stdout(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.stdout_lock.write_all(bytes)?;
+        self.stdout_lock.flush()
+    }
+
     fn nl(&mut self, output_type: OutputType) {
         match output_type {
             OutputType::Standard => {
@@ -209,6 +232,20 @@ impl CliInputOutput for CursorInputOutput {
         )?))
     }
 
+    fn read_stdin(&mut self) -> IoResult<String> {
+        let mut input = String::new();
+        self.stdin_cursor.read_to_string(&mut input)?;
+        Ok(input)
+    }
+
+    fn read_stdin_exact(&mut self, buf: &mut [u8]) -> IoResult<()> {
+        self.stdin_cursor.read_exact(buf)
+    }
+
+    fn write_stdout(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.stdout_cursor.write_all(bytes)
+    }
+
     fn nl(&mut self, output_type: OutputType) {
         match output_type {
             OutputType::Standard => {
@@ -0,0 +1,240 @@
+use super::{CliInputOutput, OutputType};
+use crate::rutil::safe_string::SafeString;
+use serde_json::{json, Value};
+use std::io::Result as IoResult;
+
+/// Version of the documents printed by `--output json`, changed only when they change
+/// in a way that could break scripts
+pub const JSON_OUTPUT_VERSION: u32 = 1;
+
+/// Wraps another `CliInputOutput` for `--output json`
+///
+/// Messages meant for humans are not printed. Warnings and errors are kept, along with the
+/// result of the command, and `finish` prints them as JSON documents once the command is done.
+/// Commands that print something on stdout without a result get it as `{"output": "..."}`, so
+/// nothing is lost. Passwords are still read from the terminal, through the wrapped
+/// `CliInputOutput`.
+pub struct JsonInputOutput<'a, IO: CliInputOutput> {
+    inner: &'a mut IO,
+    command: String,
+    result: Option<Value>,
+    /// What was written to stdout, other than messages
+    output: String,
+    warnings: Vec<String>,
+    errors: Vec<String>,
+}
+
+impl<'a, IO: CliInputOutput> JsonInputOutput<'a, IO> {
+    pub fn new(inner: &'a mut IO, command: &str) -> JsonInputOutput<'a, IO> {
+        JsonInputOutput {
+            inner,
+            command: command.to_owned(),
+            result: None,
+            output: String::new(),
+            warnings: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Prints the result on stdout and, if the command failed, the error on stderr
+    pub fn finish(self, exit_code: i32) {
+        let ok = exit_code == 0;
+        let result = match self.result {
+            None if !self.output.is_empty() => Some(json!({ "output": self.output })),
+            result => result,
+        };
+        if ok || result.is_some() {
+            let document = json!({
+                "version": JSON_OUTPUT_VERSION,
+                "command": self.command,
+                "ok": ok,
+                "result": result.unwrap_or(Value::Null),
+                "warnings": self.warnings,
+            });
+            self.inner.writeln(document, OutputType::Standard);
+        }
+        if !ok {
+            let error = if !self.errors.is_empty() {
+                self.errors.join("\n")
+            } else {
+                self.warnings
+                    .last()
+                    .cloned()
+                    .unwrap_or_else(|| "Woops, something went wrong.".to_owned())
+            };
+            let document = json!({
+                "version": JSON_OUTPUT_VERSION,
+                "command": self.command,
+                "ok": false,
+                "exit_code": exit_code,
+                "error": error,
+            });
+            self.inner.writeln(document, OutputType::Error);
+        }
+    }
+}
+
+/// Prints an error that happened before a command could even start, like a wrong argument
+pub fn print_json_error(io: &mut impl CliInputOutput, error: &str, exit_code: i32) {
+    let document = json!({
+        "version": JSON_OUTPUT_VERSION,
+        "command": Value::Null,
+        "ok": false,
+        "exit_code": exit_code,
+        "error": error.trim(),
+    });
+    io.writeln(document, OutputType::Error);
+}
+
+impl<'a, IO: CliInputOutput> CliInputOutput for JsonInputOutput<'a, IO> {
+    fn read_line(&mut self) -> IoResult<String> {
+        self.inner.read_line()
+    }
+
+    fn prompt_line(&mut self, prompt: impl ToString) -> IoResult<String> {
+        self.inner.prompt_line(prompt)
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        self.inner.read_password()
+    }
+
+    fn prompt_password(&mut self, prompt: impl ToString) -> IoResult<SafeString> {
+        self.inner.prompt_password(prompt)
+    }
+
+    fn read_stdin(&mut self) -> IoResult<String> {
+        self.inner.read_stdin()
+    }
+
+    fn read_stdin_exact(&mut self, buf: &mut [u8]) -> IoResult<()> {
+        self.inner.read_stdin_exact(buf)
+    }
+
+    fn write_stdout(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.inner.write_stdout(bytes)
+    }
+
+    fn nl(&mut self, _output_type: OutputType) {}
+
+    fn write(&mut self, s: impl ToString, output_type: OutputType) {
+        if let OutputType::Standard = output_type {
+            self.output.push_str(&s.to_string());
+        }
+    }
+
+    fn writeln(&mut self, s: impl ToString, output_type: OutputType) {
+        if let OutputType::Standard = output_type {
+            self.output.push_str(&s.to_string());
+            self.output.push('\n');
+        }
+    }
+
+    fn title(&mut self, _s: impl ToString, _output_type: OutputType) {}
+
+    fn info(&mut self, _s: impl ToString, _output_type: OutputType) {}
+
+    fn success(&mut self, _s: impl ToString, _output_type: OutputType) {}
+
+    fn warning(&mut self, s: impl ToString, _output_type: OutputType) {
+        self.warnings.push(s.to_string().trim().to_owned());
+    }
+
+    fn error(&mut self, s: impl ToString, _output_type: OutputType) {
+        self.errors.push(s.to_string().trim().to_owned());
+    }
+
+    fn result(&mut self, result: Value) {
+        self.result = Some(result);
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::JsonInputOutput;
+    use crate::rclio::{CliInputOutput, CursorInputOutput, OutputType};
+    use serde_json::{json, Value};
+
+    fn parse(cursor: std::io::Cursor<Vec<u8>>) -> Value {
+        serde_json::from_slice(cursor.into_inner().as_slice()).unwrap_or(Value::Null)
+    }
+
+    #[test]
+    fn test_success() {
+        let mut inner = CursorInputOutput::new("", "");
+        let mut io = JsonInputOutput::new(&mut inner, "get");
+        io.success("Alright!", OutputType::Standard);
+        io.warning("Careful", OutputType::Error);
+        io.result(json!({"name": "GitHub"}));
+        io.finish(0);
+
+        assert_eq!(
+            parse(inner.stdout_cursor),
+            json!({
+                "version": 1,
+                "command": "get",
+                "ok": true,
+                "result": {"name": "GitHub"},
+                "warnings": ["Careful"],
+            })
+        );
+        assert_eq!(parse(inner.stderr_cursor), Value::Null);
+    }
+
+    #[test]
+    fn test_output_without_result() {
+        let mut inner = CursorInputOutput::new("", "");
+        let mut io = JsonInputOutput::new(&mut inner, "gen");
+        io.title("Your secret", OutputType::Standard);
+        io.write("abcd", OutputType::Standard);
+        io.writeln("", OutputType::Standard);
+        io.writeln("Not for scripts", OutputType::Error);
+        io.finish(0);
+
+        assert_eq!(
+            parse(inner.stdout_cursor)["result"],
+            json!({"output": "abcd\n"})
+        );
+    }
+
+    #[test]
+    fn test_result_wins_over_output() {
+        let mut inner = CursorInputOutput::new("", "");
+        let mut io = JsonInputOutput::new(&mut inner, "gen");
+        io.result(json!({"secret": "abcd"}));
+        io.writeln("abcd", OutputType::Standard);
+        io.finish(0);
+
+        assert_eq!(
+            parse(inner.stdout_cursor)["result"],
+            json!({"secret": "abcd"})
+        );
+    }
+
+    #[test]
+    fn test_failure() {
+        let mut inner = CursorInputOutput::new("", "");
+        let mut io = JsonInputOutput::new(&mut inner, "add");
+        io.error(
+            "\nWoops, there is already an app with that name.",
+            OutputType::Error,
+        );
+        io.finish(1);
+
+        assert!(inner.stdout_cursor.get_ref().is_empty());
+        assert_eq!(
+            parse(inner.stderr_cursor),
+            json!({
+                "version": 1,
+                "command": "add",
+                "ok": false,
+                "exit_code": 1,
+                "error": "Woops, there is already an app with that name.",
+            })
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use super::{CliInputOutput, OutputType};
+use crate::rutil::safe_string::SafeString;
+use serde_json::Value;
+use std::io::{Error, ErrorKind, Result as IoResult};
+
+/// Wraps another `CliInputOutput` for `--no-input`, so that questions fail instead of waiting
+/// for an answer that will never come
+pub struct NoInputOutput<'a, IO: CliInputOutput> {
+    inner: &'a mut IO,
+}
+
+impl<'a, IO: CliInputOutput> NoInputOutput<'a, IO> {
+    pub fn new(inner: &'a mut IO) -> NoInputOutput<'a, IO> {
+        NoInputOutput { inner }
+    }
+}
+
+/// Reading would block until someone answers, which is what we were asked not to do
+fn no_input_error() -> Error {
+    Error::new(
+        ErrorKind::WouldBlock,
+        "I was told not to ask for input with --no-input",
+    )
+}
+
+impl<'a, IO: CliInputOutput> CliInputOutput for NoInputOutput<'a, IO> {
+    fn read_line(&mut self) -> IoResult<String> {
+        Err(no_input_error())
+    }
+
+    fn prompt_line(&mut self, _prompt: impl ToString) -> IoResult<String> {
+        Err(no_input_error())
+    }
+
+    fn read_password(&mut self) -> IoResult<SafeString> {
+        Err(no_input_error())
+    }
+
+    fn prompt_password(&mut self, _prompt: impl ToString) -> IoResult<SafeString> {
+        Err(no_input_error())
+    }
+
+    fn read_stdin(&mut self) -> IoResult<String> {
+        self.inner.read_stdin()
+    }
+
+    fn read_stdin_exact(&mut self, buf: &mut [u8]) -> IoResult<()> {
+        self.inner.read_stdin_exact(buf)
+    }
+
+    fn write_stdout(&mut self, bytes: &[u8]) -> IoResult<()> {
+        self.inner.write_stdout(bytes)
+    }
+
+    fn nl(&mut self, output_type: OutputType) {
+        self.inner.nl(output_type)
+    }
+
+    fn write(&mut self, s: impl ToString, output_type: OutputType) {
+        self.inner.write(s, output_type)
+    }
+
+    fn writeln(&mut self, s: impl ToString, output_type: OutputType) {
+        self.inner.writeln(s, output_type)
+    }
+
+    fn warning(&mut self, s: impl ToString, output_type: OutputType) {
+        self.inner.warning(s, output_type)
+    }
+
+    fn error(&mut self, s: impl ToString, output_type: OutputType) {
+        self.inner.error(s, output_type)
+    }
+
+    fn result(&mut self, result: Value) {
+        self.inner.result(result)
+    }
+
+    fn is_interactive(&self) -> bool {
+        false
+    }
+}
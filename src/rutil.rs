@@ -1,6 +1,11 @@
 pub mod atty;
+pub mod external_tool;
+pub mod file_lock;
 pub mod fix_new_line;
+pub mod peer_cred;
 pub mod print_tty;
+pub mod private_file;
+pub mod pty;
 pub mod safe_string;
 pub mod safe_string_serde;
 pub mod safe_vec;
@@ -0,0 +1,73 @@
+use crate::rutil::safe_string::SafeString;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Runs a tool, feeding `input` to its standard input
+///
+/// The password never appears in the arguments of any process, where other users could see it.
+pub fn pipe_to_tool(command: &[&str], input: &[u8]) -> Result<(), ()> {
+    let mut child = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|_| ())?;
+
+    {
+        let mut stdin = child.stdin.take().ok_or(())?;
+        if stdin.write_all(input).is_err() {
+            let _ = child.wait();
+            return Err(());
+        }
+        // Dropping stdin closes it, which tells the tool the input is complete.
+    }
+
+    if child.wait().map_err(|_| ())?.success() {
+        Ok(())
+    } else {
+        Err(())
+    }
+}
+
+/// Runs a tool, returning what it wrote to its standard output
+pub fn read_from_tool(command: &[&str]) -> Result<SafeString, ()> {
+    let output = Command::new(command[0])
+        .args(&command[1..])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .map_err(|_| ())?;
+    if !output.status.success() {
+        return Err(());
+    }
+    Ok(SafeString::from_string(
+        String::from_utf8_lossy(output.stdout.as_slice()).into_owned(),
+    ))
+}
+
+/// Runs a tool without any input or output
+pub fn run_tool(command: &[&str]) -> Result<(), ()> {
+    read_from_tool(command).map(|_| ())
+}
+
+#[cfg(test)]
+mod test {
+    use super::pipe_to_tool;
+
+    #[test]
+    fn test_pipe_to_tool_writes_to_stdin() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input");
+        let path = path.to_str().unwrap();
+
+        pipe_to_tool(&["tee", path], b"p@ss 'word\"; $(rm -rf ~)").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(path).unwrap(),
+            "p@ss 'word\"; $(rm -rf ~)"
+        );
+
+        assert!(pipe_to_tool(&["false"], b"").is_err());
+        assert!(pipe_to_tool(&["rooster-no-such-tool"], b"").is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use std::fs::File;
+use std::io::{Error as IoError, ErrorKind as IoErrorKind, Result as IoResult};
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// An exclusive lock on a file, released when dropped
+///
+/// Commands that keep running, like `rooster serve`, take it to change the password file on top
+/// of what other Rooster commands saved in the meantime.
+pub struct FileLock {
+    fd: RawFd,
+}
+
+impl FileLock {
+    /// Waits for the other Rooster commands to be done with the file
+    pub fn lock(file: &File) -> IoResult<FileLock> {
+        let fd = file.as_raw_fd();
+        loop {
+            if unsafe { libc::flock(fd, libc::LOCK_EX) } == 0 {
+                return Ok(FileLock { fd });
+            }
+            let err = IoError::last_os_error();
+            if err.kind() != IoErrorKind::Interrupted {
+                return Err(err);
+            }
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        unsafe {
+            libc::flock(self.fd, libc::LOCK_UN);
+        }
+    }
+}
@@ -0,0 +1,49 @@
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::UnixStream;
+
+/// Returns the user ID of the process on the other end of a Unix socket
+#[cfg(target_os = "linux")]
+pub fn peer_uid(stream: &UnixStream) -> std::io::Result<libc::uid_t> {
+    let mut cred = libc::ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+    let ret = unsafe {
+        libc::getsockopt(
+            stream.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_PEERCRED,
+            &mut cred as *mut libc::ucred as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if ret != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(cred.uid)
+}
+
+/// Returns the user ID of the process on the other end of a Unix socket
+#[cfg(not(target_os = "linux"))]
+pub fn peer_uid(stream: &UnixStream) -> std::io::Result<libc::uid_t> {
+    let mut uid: libc::uid_t = 0;
+    let mut gid: libc::gid_t = 0;
+
+    if unsafe { libc::getpeereid(stream.as_raw_fd(), &mut uid, &mut gid) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(uid)
+}
+
+/// Returns true if the process on the other end of a Unix socket runs as the current user
+pub fn is_same_user(stream: &UnixStream) -> bool {
+    match peer_uid(stream) {
+        Ok(uid) => uid == unsafe { libc::getuid() },
+        Err(_) => false,
+    }
+}
@@ -0,0 +1,32 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+
+/// Writes the file through a temporary file only we can read, so that the file is never
+/// readable by others or half-written, even if it already existed
+pub fn write_private_file(path: &Path, contents: &str) -> std::io::Result<()> {
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidInput, "not a file"))?;
+    let temporary_path = path.with_file_name(format!(
+        ".{}.rooster-{}",
+        file_name.to_string_lossy(),
+        std::process::id()
+    ));
+
+    let written = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(&temporary_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temporary_path, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temporary_path);
+    }
+    written
+}
@@ -0,0 +1,122 @@
+use libc::{c_int, termios};
+use std::ffi::{CStr, OsStr, OsString};
+use std::fs::{File, OpenOptions};
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::os::unix::io::{AsRawFd, FromRawFd, RawFd};
+#[cfg(not(target_os = "linux"))]
+use std::sync::Mutex;
+
+/// Turns a C function return into an IO Result
+fn io_result(ret: c_int) -> io::Result<()> {
+    match ret {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn safe_tcgetattr(fd: RawFd) -> io::Result<termios> {
+    let mut term = mem::MaybeUninit::<termios>::uninit();
+    io_result(unsafe { libc::tcgetattr(fd, term.as_mut_ptr()) })?;
+    Ok(unsafe { term.assume_init() })
+}
+
+/// Opens a pseudo-terminal, returning its master and slave sides. Programs use the slave side as
+/// their terminal, what they print can be read from the master side and what is written there is
+/// what they read.
+pub fn open_pty() -> io::Result<(File, File)> {
+    let fd = unsafe { libc::posix_openpt(libc::O_RDWR | libc::O_NOCTTY) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let master = unsafe { File::from_raw_fd(fd) };
+    io_result(unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) }.min(0))?;
+    io_result(unsafe { libc::grantpt(fd) })?;
+    io_result(unsafe { libc::unlockpt(fd) })?;
+
+    let slave = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .custom_flags(libc::O_NOCTTY)
+        .open(slave_name(fd)?)?;
+
+    Ok((master, slave))
+}
+
+/// The path of the slave side of a pseudo-terminal
+#[cfg(target_os = "linux")]
+fn slave_name(fd: RawFd) -> io::Result<OsString> {
+    let mut name = [0 as libc::c_char; 128];
+    let ret = unsafe { libc::ptsname_r(fd, name.as_mut_ptr(), name.len()) };
+    if ret != 0 {
+        return Err(io::Error::from_raw_os_error(ret));
+    }
+    let name = unsafe { CStr::from_ptr(name.as_ptr()) };
+    Ok(OsStr::from_bytes(name.to_bytes()).to_owned())
+}
+
+/// The path of the slave side of a pseudo-terminal. Other platforms don't all have
+/// `ptsname_r`, and `ptsname` returns a static buffer, so calls to it take turns.
+#[cfg(not(target_os = "linux"))]
+fn slave_name(fd: RawFd) -> io::Result<OsString> {
+    static PTSNAME_LOCK: Mutex<()> = Mutex::new(());
+
+    let _guard = PTSNAME_LOCK.lock().unwrap_or_else(|err| err.into_inner());
+    let name = unsafe { libc::ptsname(fd) };
+    if name.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let name = unsafe { CStr::from_ptr(name) };
+    Ok(OsStr::from_bytes(name.to_bytes()).to_owned())
+}
+
+/// Makes the terminal on stdin the controlling terminal of the process, which must have just
+/// started a new session. Only meant to be called in a child, between fork and exec.
+pub fn make_controlling_terminal() -> io::Result<()> {
+    if unsafe { libc::setsid() } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    io_result(unsafe { libc::ioctl(libc::STDIN_FILENO, libc::TIOCSCTTY as _, 0) })
+}
+
+/// Copies the window size of one terminal to another, so that full-screen programs fit
+pub fn copy_window_size(from: RawFd, to: &File) -> io::Result<()> {
+    let mut size = mem::MaybeUninit::<libc::winsize>::uninit();
+    io_result(unsafe { libc::ioctl(from, libc::TIOCGWINSZ, size.as_mut_ptr()) })?;
+    io_result(unsafe { libc::ioctl(to.as_raw_fd(), libc::TIOCSWINSZ, size.as_ptr()) })
+}
+
+/// Whether the terminal shows what is typed, which programs turn off to read passwords
+pub fn echo_enabled(pty: &File) -> io::Result<bool> {
+    Ok(safe_tcgetattr(pty.as_raw_fd())?.c_lflag & libc::ECHO != 0)
+}
+
+/// Sends keys to us one by one, without echoing them or turning Ctrl-C into a signal, until it
+/// is dropped
+pub struct RawMode {
+    fd: RawFd,
+    term_orig: termios,
+}
+
+impl RawMode {
+    pub fn enable(fd: RawFd) -> io::Result<RawMode> {
+        let term_orig = safe_tcgetattr(fd)?;
+        let mut term = term_orig;
+        term.c_iflag &= !(libc::BRKINT | libc::ICRNL | libc::INPCK | libc::ISTRIP | libc::IXON);
+        term.c_lflag &= !(libc::ECHO | libc::ICANON | libc::IEXTEN | libc::ISIG);
+        term.c_cc[libc::VMIN] = 1;
+        term.c_cc[libc::VTIME] = 0;
+        io_result(unsafe { libc::tcsetattr(fd, libc::TCSANOW, &term) })?;
+        Ok(RawMode { fd, term_orig })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(self.fd, libc::TCSANOW, &self.term_orig);
+        }
+    }
+}
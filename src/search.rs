@@ -0,0 +1,310 @@
+use crate::password::v2::Password;
+
+/// Field holding comma or space separated tags, searched with `tag:work`
+pub const TAGS_FIELD: &str = "tags";
+
+const SCORE_MATCH: i64 = 16;
+const BONUS_PREFIX: i64 = 12;
+const BONUS_WORD_START: i64 = 8;
+const BONUS_CAMEL_CASE: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 10;
+const PENALTY_GAP: i64 = 1;
+const MAX_PENALTY_LEADING: i64 = 3;
+
+/// One part of a query, like "git" or "user:alice"
+#[derive(Debug, PartialEq)]
+enum Term {
+    Any(String),
+    Name(String),
+    Username(String),
+    Tag(String),
+    Field(String, String),
+}
+
+/// A search query, made of terms that must all match
+#[derive(Debug, PartialEq)]
+pub struct Query {
+    terms: Vec<Term>,
+}
+
+impl Query {
+    /// Parses queries like `user:alice tag:work git`.
+    ///
+    /// Terms without a prefix are fuzzy-matched against app names, and also looked up in
+    /// usernames and fields. `name:`, `user:` and `tag:` restrict a term to app names,
+    /// usernames and tags. Any other prefix is the name of a field, like `url:github.com`.
+    pub fn parse(query: &str) -> Query {
+        let terms = query
+            .split_whitespace()
+            .filter_map(|word| {
+                let (prefix, value) = match word.find(':') {
+                    Some(i) if i > 0 => (&word[..i], &word[i + 1..]),
+                    _ => return Some(Term::Any(word.to_lowercase())),
+                };
+                if value.is_empty() {
+                    return None;
+                }
+                let value = value.to_lowercase();
+                Some(match prefix.to_lowercase().as_str() {
+                    "name" | "app" => Term::Name(value),
+                    "user" | "username" => Term::Username(value),
+                    "tag" => Term::Tag(value),
+                    field => Term::Field(field.to_owned(), value),
+                })
+            })
+            .collect();
+        Query { terms }
+    }
+
+    /// Scores how well a password matches the query, higher is better
+    pub fn score(&self, password: &Password) -> Option<i64> {
+        let mut total = 0;
+        for term in self.terms.iter() {
+            total += term.score(password)?;
+        }
+        Some(total)
+    }
+
+    /// Whether a password matches the query without fuzzy matching: the terms about names must
+    /// start the name, ignoring case, so that "db" matches "db-prod" but not "Dropbox"
+    pub fn matches_name_prefix(&self, password: &Password) -> bool {
+        let name = password.name.to_lowercase();
+        self.terms.iter().all(|term| match term {
+            Term::Any(text) | Term::Name(text) => name.starts_with(text.as_str()),
+            _ => term.score(password).is_some(),
+        })
+    }
+}
+
+impl Term {
+    fn score(&self, password: &Password) -> Option<i64> {
+        Some(match self {
+            Term::Any(text) => {
+                let in_name = fuzzy_score(text, &password.name);
+                let elsewhere = std::iter::once(&password.username)
+                    .chain(password.fields.values())
+                    .filter_map(|value| substring_score(text, value))
+                    .max()
+                    .map(|score| score / 2);
+                in_name.max(elsewhere)?
+            }
+            Term::Name(text) => fuzzy_score(text, &password.name)?,
+            Term::Username(text) => substring_score(text, &password.username)?,
+            Term::Tag(tag) => {
+                if tags(password).any(|t| t.to_lowercase() == *tag) {
+                    SCORE_MATCH
+                } else {
+                    return None;
+                }
+            }
+            Term::Field(field, text) => password
+                .fields
+                .iter()
+                .filter(|(key, _)| key.to_lowercase() == *field)
+                .filter_map(|(_, value)| substring_score(text, value))
+                .max()?,
+        })
+    }
+}
+
+/// Returns the tags of a password
+pub fn tags(password: &Password) -> impl Iterator<Item = &str> {
+    password
+        .fields
+        .get(TAGS_FIELD)
+        .map(|tags| tags.as_str())
+        .unwrap_or("")
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|tag| !tag.is_empty())
+}
+
+/// Returns the passwords matching the query, best matches first
+pub fn search<'a>(passwords: &'a [Password], query: &str) -> Vec<&'a Password> {
+    let query = Query::parse(query);
+    let mut results: Vec<(i64, &Password)> = passwords
+        .iter()
+        .filter_map(|password| query.score(password).map(|score| (score, password)))
+        .collect();
+    results.sort_by(|(score_a, a), (score_b, b)| {
+        score_b
+            .cmp(score_a)
+            .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+    });
+    results.into_iter().map(|(_, password)| password).collect()
+}
+
+fn substring_score(pattern: &str, text: &str) -> Option<i64> {
+    if text.to_lowercase().contains(pattern) {
+        fuzzy_score(pattern, text)
+    } else {
+        None
+    }
+}
+
+/// Bonus for matching the character at index `i`, depending on where it is in a word
+fn position_bonus(text: &[char], i: usize) -> i64 {
+    if i == 0 {
+        return BONUS_PREFIX;
+    }
+    let (previous, current) = (text[i - 1], text[i]);
+    if !previous.is_alphanumeric() && current.is_alphanumeric() {
+        BONUS_WORD_START
+    } else if previous.is_lowercase() && current.is_uppercase()
+        || !previous.is_numeric() && current.is_numeric()
+    {
+        BONUS_CAMEL_CASE
+    } else {
+        0
+    }
+}
+
+/// Scores a case-insensitive subsequence match of `pattern` (lowercase) in `text`.
+///
+/// Among all the ways the pattern can match, this picks the one with the most consecutive
+/// characters and characters at the start of words, so "gh" ranks "GitHub" above
+/// "Gigahertz Shop".
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<i64> {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let original: Vec<char> = text.chars().collect();
+    let text: Vec<char> = original
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    if pattern.is_empty() {
+        return Some(0);
+    }
+    if pattern.len() > text.len() {
+        return None;
+    }
+
+    // best[j] is the best score with the current pattern character matched at text[j]
+    let mut best: Vec<Option<i64>> = text
+        .iter()
+        .enumerate()
+        .map(|(j, c)| {
+            if *c == pattern[0] {
+                let leading = (j as i64 * PENALTY_GAP).min(MAX_PENALTY_LEADING);
+                Some(SCORE_MATCH + position_bonus(&original, j) - leading)
+            } else {
+                None
+            }
+        })
+        .collect();
+
+    for p in pattern.iter().skip(1) {
+        let mut next = vec![None; text.len()];
+        for j in 1..text.len() {
+            if text[j] != *p {
+                continue;
+            }
+            let previous = (0..j)
+                .filter_map(|k| {
+                    best[k].map(|score| {
+                        if k + 1 == j {
+                            score + BONUS_CONSECUTIVE
+                        } else {
+                            score - (j - k - 1) as i64 * PENALTY_GAP
+                        }
+                    })
+                })
+                .max();
+            next[j] = previous.map(|score| score + SCORE_MATCH + position_bonus(&original, j));
+        }
+        best = next;
+    }
+
+    best.into_iter().flatten().max()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fuzzy_score, search, Query, Term};
+    use crate::password::v2::Password;
+
+    fn names(passwords: Vec<&Password>) -> Vec<&str> {
+        passwords.iter().map(|p| p.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            Query::parse("user:Alice tag:work Git url:"),
+            Query {
+                terms: vec![
+                    Term::Username("alice".to_owned()),
+                    Term::Tag("work".to_owned()),
+                    Term::Any("git".to_owned()),
+                ]
+            }
+        );
+        assert_eq!(
+            Query::parse(":x url:github.com"),
+            Query {
+                terms: vec![
+                    Term::Any(":x".to_owned()),
+                    Term::Field("url".to_owned(), "github.com".to_owned()),
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("fcbk", "Facebook").is_some());
+        assert_eq!(fuzzy_score("kbcf", "Facebook"), None);
+        assert!(fuzzy_score("gh", "GitHub") > fuzzy_score("gh", "Gigahertz Shop"));
+        assert!(fuzzy_score("hub", "GitHub") > fuzzy_score("hub", "Hotel Bubbles"));
+        assert!(fuzzy_score("mail", "Mail") > fuzzy_score("mail", "Gmail"));
+        assert!(fuzzy_score("shop", "Gigahertz Shop") > fuzzy_score("shop", "Sheep Shape"));
+    }
+
+    #[test]
+    fn test_search() {
+        let mut work = Password::new("Gitlab", "alice", "xxxx");
+        work.fields
+            .insert("tags".to_owned(), "work, dev".to_owned());
+        let mut personal = Password::new("GitHub", "bob", "xxxx");
+        personal
+            .fields
+            .insert("url".to_owned(), "https://github.com".to_owned());
+        let passwords = vec![
+            Password::new("Gigahertz Shop", "alice", "xxxx"),
+            personal,
+            work,
+        ];
+
+        assert_eq!(
+            names(search(&passwords, "gh")),
+            vec!["GitHub", "Gigahertz Shop"]
+        );
+        assert_eq!(
+            names(search(&passwords, "")),
+            vec!["Gigahertz Shop", "GitHub", "Gitlab"]
+        );
+        assert_eq!(
+            names(search(&passwords, "user:alice git")),
+            vec!["Gitlab", "Gigahertz Shop"]
+        );
+        assert_eq!(names(search(&passwords, "tag:work")), vec!["Gitlab"]);
+        assert_eq!(names(search(&passwords, "tag:wor")), Vec::<&str>::new());
+        assert_eq!(names(search(&passwords, "url:github")), vec!["GitHub"]);
+        assert_eq!(names(search(&passwords, "bob")), vec!["GitHub"]);
+    }
+
+    #[test]
+    fn test_matches_name_prefix() {
+        let mut staging = Password::new("db-staging", "alice", "xxxx");
+        staging
+            .fields
+            .insert("tags".to_owned(), "staging".to_owned());
+        let dropbox = Password::new("Dropbox", "bob", "xxxx");
+
+        let query = Query::parse("db");
+        assert!(query.matches_name_prefix(&staging));
+        assert!(!query.matches_name_prefix(&dropbox));
+        assert!(Query::parse("DB-S tag:staging").matches_name_prefix(&staging));
+        assert!(!Query::parse("db tag:prod").matches_name_prefix(&staging));
+        assert!(Query::parse("user:bob").matches_name_prefix(&dropbox));
+        assert!(!Query::parse("name:box").matches_name_prefix(&dropbox));
+    }
+}
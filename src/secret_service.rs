@@ -0,0 +1,1299 @@
+use crate::dbus::marshal::Value;
+use crate::dbus::{Connection, Message, MessageType, NO_REPLY_EXPECTED};
+use crate::ffi;
+use crate::password::v2::{Password, PasswordStore};
+use crate::password::PasswordError;
+use crate::rutil::file_lock::FileLock;
+use crate::rutil::safe_string::SafeString;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs::File;
+use std::io::{ErrorKind as IoErrorKind, Result as IoResult};
+
+/// The name programs like libsecret look for on the session bus
+pub const SERVICE_NAME: &str = "org.freedesktop.secrets";
+
+/// Secret Service attributes are kept as fields with this prefix, like `attribute:server`
+pub const ATTRIBUTE_FIELD_PREFIX: &str = "attribute:";
+
+/// Only passwords with this field are items, so programs on the session bus can't read the rest
+/// of the vault. Items created through the Secret Service get it, and `rooster field APP
+/// secret-service yes` shares another password.
+pub const ITEM_FIELD: &str = "secret-service";
+const ITEM_FIELD_VALUE: &str = "yes";
+
+const SERVICE_PATH: &str = "/org/freedesktop/secrets";
+/// Rooster keeps all passwords in one collection, which is also the default one
+const COLLECTION_PATH: &str = "/org/freedesktop/secrets/collection/rooster";
+const DEFAULT_ALIAS_PATH: &str = "/org/freedesktop/secrets/aliases/default";
+const SESSION_PATH: &str = "/org/freedesktop/secrets/session";
+const PROMPT_PATH: &str = "/org/freedesktop/secrets/prompt";
+
+const SERVICE_INTERFACE: &str = "org.freedesktop.Secret.Service";
+const COLLECTION_INTERFACE: &str = "org.freedesktop.Secret.Collection";
+const ITEM_INTERFACE: &str = "org.freedesktop.Secret.Item";
+const SESSION_INTERFACE: &str = "org.freedesktop.Secret.Session";
+const PROMPT_INTERFACE: &str = "org.freedesktop.Secret.Prompt";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const PEER_INTERFACE: &str = "org.freedesktop.DBus.Peer";
+
+const COLLECTION_LABEL: &str = "Rooster";
+const CONTENT_TYPE: &str = "text/plain; charset=utf8";
+
+/// Where a path means "nothing", like "no prompt needed"
+const NO_OBJECT: &str = "/";
+
+// From the D-Bus and Secret Service specifications
+const FAILED: &str = "org.freedesktop.DBus.Error.Failed";
+const INVALID_ARGS: &str = "org.freedesktop.DBus.Error.InvalidArgs";
+const NOT_SUPPORTED: &str = "org.freedesktop.DBus.Error.NotSupported";
+const UNKNOWN_METHOD: &str = "org.freedesktop.DBus.Error.UnknownMethod";
+const UNKNOWN_PROPERTY: &str = "org.freedesktop.DBus.Error.UnknownProperty";
+const PROPERTY_READ_ONLY: &str = "org.freedesktop.DBus.Error.PropertyReadOnly";
+const IS_LOCKED: &str = "org.freedesktop.Secret.Error.IsLocked";
+const NO_SESSION: &str = "org.freedesktop.Secret.Error.NoSession";
+const NO_SUCH_OBJECT: &str = "org.freedesktop.Secret.Error.NoSuchObject";
+
+struct CallError {
+    name: &'static str,
+    message: String,
+}
+
+impl CallError {
+    fn new(name: &'static str, message: impl ToString) -> CallError {
+        CallError {
+            name,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<PasswordError> for CallError {
+    fn from(err: PasswordError) -> CallError {
+        match err {
+            PasswordError::NoSuchAppError => {
+                CallError::new(NO_SUCH_OBJECT, "there is no such item")
+            }
+            PasswordError::AppExistsError => {
+                CallError::new(FAILED, "there is already an app with that name")
+            }
+            PasswordError::EmptyPasswordError => {
+                CallError::new(INVALID_ARGS, "Rooster doesn't keep empty secrets")
+            }
+            err => CallError::new(FAILED, format!("{:?}", err)),
+        }
+    }
+}
+
+/// The objects we serve
+enum Object {
+    Service,
+    Collection,
+    Item(Password),
+    Session,
+    Prompt,
+}
+
+impl Object {
+    fn interface(&self) -> &'static str {
+        match self {
+            Object::Service => SERVICE_INTERFACE,
+            Object::Collection => COLLECTION_INTERFACE,
+            Object::Item(_) => ITEM_INTERFACE,
+            Object::Session => SESSION_INTERFACE,
+            Object::Prompt => PROMPT_INTERFACE,
+        }
+    }
+}
+
+/// The path of the item of an app, which escapes everything but letters and digits as `_XX`
+fn item_path(name: &str) -> String {
+    let mut path = format!("{}/", COLLECTION_PATH);
+    for b in name.bytes() {
+        if b.is_ascii_alphanumeric() {
+            path.push(b as char);
+        } else {
+            path.push_str(&format!("_{:02x}", b));
+        }
+    }
+    path
+}
+
+/// The name of the app of an item, from its path in the collection or its alias
+fn item_name(path: &str) -> Option<String> {
+    let escaped = path
+        .strip_prefix(COLLECTION_PATH)
+        .or_else(|| path.strip_prefix(DEFAULT_ALIAS_PATH))?
+        .strip_prefix('/')?;
+    let mut bytes = Vec::new();
+    let mut rest = escaped.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'_' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+/// The attributes of an item, from the fields of its password
+fn attributes(password: &Password) -> BTreeMap<String, String> {
+    password
+        .fields
+        .iter()
+        .filter_map(|(field, value)| {
+            field
+                .strip_prefix(ATTRIBUTE_FIELD_PREFIX)
+                .map(|attribute| (attribute.to_owned(), value.clone()))
+        })
+        .collect()
+}
+
+fn is_item(password: &Password) -> bool {
+    password.fields.contains_key(ITEM_FIELD)
+}
+
+/// The passwords shared with the Secret Service
+fn items(store: &PasswordStore) -> Vec<&Password> {
+    store
+        .get_all_passwords()
+        .into_iter()
+        .filter(|password| is_item(password))
+        .collect()
+}
+
+/// The password of an item, if it is shared with the Secret Service
+fn item(store: &PasswordStore, path: &str) -> Option<Password> {
+    item_name(path)
+        .and_then(|name| store.get_password(&name))
+        .filter(is_item)
+}
+
+/// Replaces the attributes of a password, keeping its other fields
+fn set_attributes(password: &mut Password, attributes: &BTreeMap<String, String>) {
+    password
+        .fields
+        .retain(|field, _| !field.starts_with(ATTRIBUTE_FIELD_PREFIX));
+    for (attribute, value) in attributes {
+        password.fields.insert(
+            format!("{}{}", ATTRIBUTE_FIELD_PREFIX, attribute),
+            value.clone(),
+        );
+    }
+    // Schemas name the account differently, but they all mean our username.
+    if let Some(username) = ["username", "user", "account"]
+        .iter()
+        .find_map(|attribute| attributes.get(*attribute))
+    {
+        password.username = username.clone();
+    }
+}
+
+/// The paths of the items that have all these attributes
+fn search(store: &PasswordStore, wanted: &BTreeMap<String, String>) -> Vec<String> {
+    items(store)
+        .into_iter()
+        .filter(|password| {
+            let attributes = attributes(password);
+            wanted
+                .iter()
+                .all(|(attribute, value)| attributes.get(attribute) == Some(value))
+        })
+        .map(|password| item_path(&password.name))
+        .collect()
+}
+
+/// An app name for a new item, which must not be taken already
+fn unique_name(store: &PasswordStore, label: &str) -> String {
+    let label = if label.trim().is_empty() {
+        "Secret"
+    } else {
+        label.trim()
+    };
+    let mut name = label.to_owned();
+    let mut n = 2;
+    while store.has_password(&name) {
+        name = format!("{} ({})", label, n);
+        n += 1;
+    }
+    name
+}
+
+fn arg(args: &[Value], i: usize) -> Result<&Value, CallError> {
+    args.get(i)
+        .ok_or_else(|| CallError::new(INVALID_ARGS, "an argument is missing"))
+}
+
+fn str_arg(args: &[Value], i: usize) -> Result<&str, CallError> {
+    arg(args, i)?
+        .as_str()
+        .ok_or_else(|| CallError::new(INVALID_ARGS, "an argument should be a string"))
+}
+
+/// An `ao` argument
+fn paths_arg(args: &[Value], i: usize) -> Result<Vec<String>, CallError> {
+    match arg(args, i)? {
+        Value::Array(_, paths) => paths
+            .iter()
+            .map(|path| path.as_str().map(String::from))
+            .collect::<Option<Vec<String>>>(),
+        _ => None,
+    }
+    .ok_or_else(|| CallError::new(INVALID_ARGS, "an argument should be a list of objects"))
+}
+
+/// An `a{ss}` argument, or one in a variant
+fn attributes_arg(value: &Value) -> Result<BTreeMap<String, String>, CallError> {
+    value
+        .unwrap_variant()
+        .as_dict()
+        .and_then(|entries| {
+            entries
+                .into_iter()
+                .map(|(attribute, value)| {
+                    Some((attribute.as_str()?.to_owned(), value.as_str()?.to_owned()))
+                })
+                .collect::<Option<BTreeMap<String, String>>>()
+        })
+        .ok_or_else(|| CallError::new(INVALID_ARGS, "attributes should map names to strings"))
+}
+
+/// What answering a call did
+pub struct Answer {
+    /// The return or error for the caller
+    pub reply: Message,
+    pub signals: Vec<Message>,
+    /// Whether the password file needs saving
+    pub changed: bool,
+}
+
+/// The Secret Service API, for the passwords of a store
+///
+/// The passwords shared with the Secret Service are items of a single collection, which is also
+/// the default one. The service starts locked. Locking doesn't touch the store: it only stops the
+/// service from giving out secrets until a prompt asks the user to unlock it.
+pub struct SecretService {
+    locked: bool,
+    sessions: HashSet<String>,
+    /// The objects each prompt unlocks, until the caller runs it
+    prompts: HashMap<String, Vec<String>>,
+    next_id: u32,
+    /// Asks the user whether to unlock
+    approve: fn(&str) -> bool,
+    signals: Vec<Message>,
+    changed: bool,
+}
+
+impl SecretService {
+    pub fn new(approve: fn(&str) -> bool) -> SecretService {
+        SecretService {
+            locked: true,
+            sessions: HashSet::new(),
+            prompts: HashMap::new(),
+            next_id: 1,
+            approve,
+            signals: Vec::new(),
+            changed: false,
+        }
+    }
+
+    /// Answers a method call
+    pub fn handle(&mut self, store: &mut PasswordStore, call: &Message) -> Answer {
+        let reply = match self.dispatch(store, call) {
+            Ok(body) => call.method_return(body),
+            Err(err) => call.error(err.name, &err.message),
+        };
+        Answer {
+            reply,
+            signals: std::mem::take(&mut self.signals),
+            changed: std::mem::take(&mut self.changed),
+        }
+    }
+
+    fn dispatch(
+        &mut self,
+        store: &mut PasswordStore,
+        call: &Message,
+    ) -> Result<Vec<Value>, CallError> {
+        let path = call.path.as_deref().unwrap_or("");
+        let interface = call.interface.as_deref().unwrap_or("");
+        let member = call.member.as_deref().unwrap_or("");
+        let args = &call.body;
+
+        if interface == PEER_INTERFACE && member == "Ping" {
+            return Ok(Vec::new());
+        }
+
+        let object = self.object(store, path)?;
+        if interface == PROPERTIES_INTERFACE {
+            return self.properties(store, &object, member, args);
+        }
+        // Calls without an interface go to the only one the object has.
+        if !interface.is_empty() && interface != object.interface() {
+            return Err(CallError::new(
+                UNKNOWN_METHOD,
+                format!("{} has no interface {}", path, interface),
+            ));
+        }
+
+        match object {
+            Object::Service => self.service_method(store, member, args),
+            Object::Collection => self.collection_method(store, member, args),
+            Object::Item(password) => self.item_method(store, password, member, args),
+            Object::Session => match member {
+                "Close" => {
+                    self.sessions.remove(path);
+                    Ok(Vec::new())
+                }
+                _ => Err(unknown_method(member)),
+            },
+            Object::Prompt => self.prompt_method(path, member),
+        }
+    }
+
+    fn object(&self, store: &PasswordStore, path: &str) -> Result<Object, CallError> {
+        match path {
+            SERVICE_PATH => Ok(Object::Service),
+            COLLECTION_PATH | DEFAULT_ALIAS_PATH => Ok(Object::Collection),
+            _ if self.sessions.contains(path) => Ok(Object::Session),
+            _ if self.prompts.contains_key(path) => Ok(Object::Prompt),
+            _ => item(store, path).map(Object::Item).ok_or_else(|| {
+                CallError::new(NO_SUCH_OBJECT, format!("there is no object at {}", path))
+            }),
+        }
+    }
+
+    fn new_path(&mut self, prefix: &str) -> String {
+        let path = format!("{}/{}", prefix, self.next_id);
+        self.next_id += 1;
+        path
+    }
+
+    fn check_unlocked(&self) -> Result<(), CallError> {
+        if self.locked {
+            return Err(CallError::new(
+                IS_LOCKED,
+                "the collection is locked, unlock it first",
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_session(&self, session: &str) -> Result<(), CallError> {
+        if !self.sessions.contains(session) {
+            return Err(CallError::new(
+                NO_SESSION,
+                format!("there is no session {}, open one first", session),
+            ));
+        }
+        Ok(())
+    }
+
+    /// A secret as `(oayays)`: the session, the algorithm parameters, the value and its type
+    fn secret(&self, session: &str, password: &Password) -> Value {
+        Value::Struct(vec![
+            Value::ObjectPath(session.to_owned()),
+            Value::Bytes(Vec::new()),
+            Value::Bytes(password.password.as_bytes().to_vec()),
+            Value::Str(CONTENT_TYPE.to_owned()),
+        ])
+    }
+
+    fn read_secret(&self, secret: &Value) -> Result<SafeString, CallError> {
+        let fields = match secret {
+            Value::Struct(fields) if fields.len() == 4 => fields,
+            _ => return Err(CallError::new(INVALID_ARGS, "a secret should be (oayays)")),
+        };
+        self.check_session(fields[0].as_str().unwrap_or(""))?;
+        let value = match fields[2] {
+            // Plain sessions send the secret as it is.
+            Value::Bytes(ref value) => value.clone(),
+            _ => return Err(CallError::new(INVALID_ARGS, "a secret should be (oayays)")),
+        };
+        let value = String::from_utf8(value)
+            .map_err(|_| CallError::new(INVALID_ARGS, "Rooster only keeps UTF-8 secrets"))?;
+        Ok(SafeString::from_string(value))
+    }
+
+    fn collection_signal(&mut self, member: &str, item: &str) {
+        self.signals.push(Message::signal(
+            COLLECTION_PATH,
+            COLLECTION_INTERFACE,
+            member,
+            vec![Value::ObjectPath(item.to_owned())],
+        ));
+    }
+
+    fn service_method(
+        &mut self,
+        store: &mut PasswordStore,
+        member: &str,
+        args: &[Value],
+    ) -> Result<Vec<Value>, CallError> {
+        match member {
+            "OpenSession" => {
+                let algorithm = str_arg(args, 0)?;
+                if algorithm != "plain" {
+                    return Err(CallError::new(
+                        NOT_SUPPORTED,
+                        format!(
+                            "Rooster only supports the plain algorithm, not {}",
+                            algorithm
+                        ),
+                    ));
+                }
+                let session = self.new_path(SESSION_PATH);
+                self.sessions.insert(session.clone());
+                Ok(vec![
+                    Value::Variant(Box::new(Value::Str(String::new()))),
+                    Value::ObjectPath(session),
+                ])
+            }
+            "SearchItems" => {
+                let found = search(store, &attributes_arg(arg(args, 0)?)?);
+                let none: Vec<String> = Vec::new();
+                Ok(if self.locked {
+                    vec![Value::object_paths(&none), Value::object_paths(&found)]
+                } else {
+                    vec![Value::object_paths(&found), Value::object_paths(&none)]
+                })
+            }
+            "Unlock" => {
+                let objects = paths_arg(args, 0)?;
+                if !self.locked {
+                    return Ok(vec![
+                        Value::object_paths(&objects),
+                        Value::ObjectPath(NO_OBJECT.to_owned()),
+                    ]);
+                }
+                let prompt = self.new_path(PROMPT_PATH);
+                self.prompts.insert(prompt.clone(), objects);
+                Ok(vec![
+                    Value::object_paths::<String>(&[]),
+                    Value::ObjectPath(prompt),
+                ])
+            }
+            "Lock" => {
+                let objects = paths_arg(args, 0)?;
+                self.locked = true;
+                self.signals.push(Message::signal(
+                    SERVICE_PATH,
+                    SERVICE_INTERFACE,
+                    "CollectionChanged",
+                    vec![Value::ObjectPath(COLLECTION_PATH.to_owned())],
+                ));
+                Ok(vec![
+                    Value::object_paths(&objects),
+                    Value::ObjectPath(NO_OBJECT.to_owned()),
+                ])
+            }
+            "GetSecrets" => {
+                let paths = paths_arg(args, 0)?;
+                let session = str_arg(args, 1)?;
+                self.check_session(session)?;
+                self.check_unlocked()?;
+                let secrets = paths
+                    .iter()
+                    .filter_map(|path| {
+                        let password = item(store, path)?;
+                        Some((
+                            Value::ObjectPath(path.clone()),
+                            self.secret(session, &password),
+                        ))
+                    })
+                    .collect();
+                Ok(vec![Value::dict("o", "(oayays)", secrets)])
+            }
+            "ReadAlias" => Ok(vec![Value::ObjectPath(
+                match str_arg(args, 0)? {
+                    "default" => COLLECTION_PATH,
+                    _ => NO_OBJECT,
+                }
+                .to_owned(),
+            )]),
+            "CreateCollection" | "SetAlias" => Err(CallError::new(
+                NOT_SUPPORTED,
+                "Rooster keeps all passwords in a single collection",
+            )),
+            _ => Err(unknown_method(member)),
+        }
+    }
+
+    fn collection_method(
+        &mut self,
+        store: &mut PasswordStore,
+        member: &str,
+        args: &[Value],
+    ) -> Result<Vec<Value>, CallError> {
+        match member {
+            "SearchItems" => Ok(vec![Value::object_paths(&search(
+                store,
+                &attributes_arg(arg(args, 0)?)?,
+            ))]),
+            "CreateItem" => {
+                let properties = arg(args, 0)?
+                    .as_dict()
+                    .ok_or_else(|| CallError::new(INVALID_ARGS, "properties should be a{sv}"))?;
+                let mut label = "";
+                let mut item_attributes = BTreeMap::new();
+                for (property, value) in properties {
+                    match property.as_str() {
+                        Some("org.freedesktop.Secret.Item.Label") => {
+                            label = value.unwrap_variant().as_str().unwrap_or("");
+                        }
+                        Some("org.freedesktop.Secret.Item.Attributes") => {
+                            item_attributes = attributes_arg(value)?;
+                        }
+                        _ => {}
+                    }
+                }
+                let secret = self.read_secret(arg(args, 1)?)?;
+                let replace = arg(args, 2)? == &Value::Bool(true);
+                self.check_unlocked()?;
+
+                // Replacing means updating the item that has the same attributes.
+                let existing = if replace && !item_attributes.is_empty() {
+                    items(store)
+                        .into_iter()
+                        .find(|password| attributes(password) == item_attributes)
+                        .map(|password| password.name.clone())
+                } else {
+                    None
+                };
+                let path = match existing {
+                    Some(name) => {
+                        if secret.is_empty() {
+                            return Err(PasswordError::EmptyPasswordError.into());
+                        }
+                        let password = store.change_password(&name, &|old_password| Password {
+                            password: secret.clone(),
+                            updated_at: ffi::time(),
+                            ..old_password
+                        })?;
+                        let path = item_path(&password.name);
+                        self.collection_signal("ItemChanged", &path);
+                        path
+                    }
+                    None => {
+                        let mut password =
+                            Password::new(unique_name(store, label), "", secret.clone());
+                        set_attributes(&mut password, &item_attributes);
+                        password
+                            .fields
+                            .insert(ITEM_FIELD.to_owned(), ITEM_FIELD_VALUE.to_owned());
+                        let path = item_path(&password.name);
+                        store.add_password(password)?;
+                        self.collection_signal("ItemCreated", &path);
+                        path
+                    }
+                };
+                self.changed = true;
+                Ok(vec![
+                    Value::ObjectPath(path),
+                    Value::ObjectPath(NO_OBJECT.to_owned()),
+                ])
+            }
+            "Delete" => Err(CallError::new(
+                NOT_SUPPORTED,
+                "Rooster keeps all passwords in a single collection",
+            )),
+            _ => Err(unknown_method(member)),
+        }
+    }
+
+    fn item_method(
+        &mut self,
+        store: &mut PasswordStore,
+        password: Password,
+        member: &str,
+        args: &[Value],
+    ) -> Result<Vec<Value>, CallError> {
+        match member {
+            "GetSecret" => {
+                let session = str_arg(args, 0)?;
+                self.check_session(session)?;
+                self.check_unlocked()?;
+                Ok(vec![self.secret(session, &password)])
+            }
+            "SetSecret" => {
+                let secret = self.read_secret(arg(args, 0)?)?;
+                self.check_unlocked()?;
+                if secret.is_empty() {
+                    return Err(PasswordError::EmptyPasswordError.into());
+                }
+                store.change_password(&password.name, &|old_password| Password {
+                    password: secret.clone(),
+                    updated_at: ffi::time(),
+                    ..old_password
+                })?;
+                self.collection_signal("ItemChanged", &item_path(&password.name));
+                self.changed = true;
+                Ok(Vec::new())
+            }
+            "Delete" => {
+                self.check_unlocked()?;
+                store.delete_password(&password.name)?;
+                self.collection_signal("ItemDeleted", &item_path(&password.name));
+                self.changed = true;
+                Ok(vec![Value::ObjectPath(NO_OBJECT.to_owned())])
+            }
+            _ => Err(unknown_method(member)),
+        }
+    }
+
+    fn prompt_method(&mut self, path: &str, member: &str) -> Result<Vec<Value>, CallError> {
+        let objects = match member {
+            "Prompt" => self.prompts.remove(path).unwrap_or_default(),
+            "Dismiss" => {
+                self.prompts.remove(path);
+                Vec::new()
+            }
+            _ => return Err(unknown_method(member)),
+        };
+        let dismissed = member == "Dismiss"
+            || !(self.approve)("Allow a program to use your Rooster passwords?");
+        if !dismissed {
+            self.locked = false;
+            self.signals.push(Message::signal(
+                SERVICE_PATH,
+                SERVICE_INTERFACE,
+                "CollectionChanged",
+                vec![Value::ObjectPath(COLLECTION_PATH.to_owned())],
+            ));
+        }
+        let unlocked = if dismissed { Vec::new() } else { objects };
+        self.signals.push(Message::signal(
+            path,
+            PROMPT_INTERFACE,
+            "Completed",
+            vec![
+                Value::Bool(dismissed),
+                Value::Variant(Box::new(Value::object_paths(&unlocked))),
+            ],
+        ));
+        Ok(Vec::new())
+    }
+
+    fn property_values(
+        &self,
+        store: &PasswordStore,
+        object: &Object,
+    ) -> Vec<(&'static str, Value)> {
+        match object {
+            Object::Service => vec![("Collections", Value::object_paths(&[COLLECTION_PATH]))],
+            Object::Collection => {
+                let passwords = items(store);
+                let items: Vec<String> = passwords
+                    .iter()
+                    .map(|password| item_path(&password.name))
+                    .collect();
+                let created = passwords.iter().map(|p| p.created_at).min().unwrap_or(0);
+                let modified = passwords.iter().map(|p| p.updated_at).max().unwrap_or(0);
+                vec![
+                    ("Items", Value::object_paths(&items)),
+                    ("Label", Value::Str(COLLECTION_LABEL.to_owned())),
+                    ("Locked", Value::Bool(self.locked)),
+                    ("Created", Value::Uint64(created as u64)),
+                    ("Modified", Value::Uint64(modified as u64)),
+                ]
+            }
+            Object::Item(password) => vec![
+                ("Locked", Value::Bool(self.locked)),
+                (
+                    "Attributes",
+                    Value::dict(
+                        "s",
+                        "s",
+                        attributes(password)
+                            .into_iter()
+                            .map(|(attribute, value)| (Value::Str(attribute), Value::Str(value)))
+                            .collect(),
+                    ),
+                ),
+                ("Label", Value::Str(password.name.clone())),
+                ("Created", Value::Uint64(password.created_at as u64)),
+                ("Modified", Value::Uint64(password.updated_at as u64)),
+            ],
+            Object::Session | Object::Prompt => Vec::new(),
+        }
+    }
+
+    fn properties(
+        &mut self,
+        store: &mut PasswordStore,
+        object: &Object,
+        member: &str,
+        args: &[Value],
+    ) -> Result<Vec<Value>, CallError> {
+        let interface = str_arg(args, 0)?;
+        let values = if interface.is_empty() || interface == object.interface() {
+            self.property_values(store, object)
+        } else {
+            Vec::new()
+        };
+        match member {
+            "GetAll" => Ok(vec![Value::dict(
+                "s",
+                "v",
+                values
+                    .into_iter()
+                    .map(|(name, value)| {
+                        (Value::Str(name.to_owned()), Value::Variant(Box::new(value)))
+                    })
+                    .collect(),
+            )]),
+            "Get" => {
+                let name = str_arg(args, 1)?;
+                values
+                    .into_iter()
+                    .find(|(property, _)| *property == name)
+                    .map(|(_, value)| vec![Value::Variant(Box::new(value))])
+                    .ok_or_else(|| unknown_property(name))
+            }
+            "Set" => {
+                let name = str_arg(args, 1)?;
+                let value = arg(args, 2)?;
+                let password = match object {
+                    Object::Item(password) if name == "Label" || name == "Attributes" => password,
+                    _ if values.iter().any(|(property, _)| *property == name) => {
+                        return Err(CallError::new(
+                            PROPERTY_READ_ONLY,
+                            format!("{} can't be changed", name),
+                        ))
+                    }
+                    _ => return Err(unknown_property(name)),
+                };
+                self.check_unlocked()?;
+                let old_path = item_path(&password.name);
+                let mut new_password = password.clone();
+                if name == "Label" {
+                    new_password.name = value
+                        .unwrap_variant()
+                        .as_str()
+                        .filter(|label| !label.trim().is_empty())
+                        .ok_or_else(|| CallError::new(INVALID_ARGS, "a label can't be empty"))?
+                        .trim()
+                        .to_owned();
+                } else {
+                    set_attributes(&mut new_password, &attributes_arg(value)?);
+                }
+                store.change_password(&password.name, &|_| new_password.clone())?;
+
+                // Items are named after their label, so renaming one moves it.
+                let new_path = item_path(&new_password.name);
+                if new_path == old_path {
+                    self.collection_signal("ItemChanged", &new_path);
+                } else {
+                    self.collection_signal("ItemDeleted", &old_path);
+                    self.collection_signal("ItemCreated", &new_path);
+                }
+                self.changed = true;
+                Ok(Vec::new())
+            }
+            _ => Err(unknown_method(member)),
+        }
+    }
+}
+
+fn unknown_method(member: &str) -> CallError {
+    CallError::new(UNKNOWN_METHOD, format!("there is no method {}", member))
+}
+
+fn unknown_property(name: &str) -> CallError {
+    CallError::new(UNKNOWN_PROPERTY, format!("there is no property {}", name))
+}
+
+/// Answers a call on top of the password file as it is now, since other Rooster commands may have
+/// changed it, and saves the changes before anyone else can
+fn handle_with_file(
+    service: &mut SecretService,
+    store: &mut PasswordStore,
+    file: &mut File,
+    call: &Message,
+) -> Answer {
+    let failed = |message: String| Answer {
+        reply: call.error(FAILED, &message),
+        signals: Vec::new(),
+        changed: false,
+    };
+    let _lock = match FileLock::lock(file) {
+        Ok(lock) => lock,
+        Err(err) => return failed(format!("I couldn't lock the password file ({})", err)),
+    };
+    if let Err(err) = store.reload(file) {
+        return failed(format!(
+            "I couldn't read the password file again ({:?})",
+            err
+        ));
+    }
+    let answer = service.handle(store, call);
+    if answer.changed {
+        if let Err(err) = store.sync(file) {
+            return failed(format!("I couldn't save the password file ({:?})", err));
+        }
+    }
+    answer
+}
+
+/// Answers calls until the bus goes away, saving the store whenever it changes
+pub fn serve(
+    connection: &mut Connection,
+    service: &mut SecretService,
+    store: &mut PasswordStore,
+    file: &mut File,
+) -> IoResult<()> {
+    loop {
+        let call = match connection.receive() {
+            Ok(call) => call,
+            Err(ref err) if err.kind() == IoErrorKind::UnexpectedEof => return Ok(()),
+            Err(err) => return Err(err),
+        };
+        // The bus tells us things too, like that we got our name.
+        if call.message_type != MessageType::MethodCall {
+            continue;
+        }
+
+        let answer = handle_with_file(service, store, file, &call);
+        if call.flags & NO_REPLY_EXPECTED == 0 {
+            connection.send(answer.reply)?;
+        }
+        for signal in answer.signals {
+            connection.send(signal)?;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{item_name, item_path, serve, SecretService, SERVICE_NAME};
+    use crate::dbus::marshal::Value;
+    use crate::dbus::{Connection, Message, MessageType};
+    use crate::password::v2::{Password, PasswordStore};
+    use crate::quale::which;
+    use crate::rutil::safe_string::SafeString;
+    use std::io::{BufRead, BufReader};
+    use std::process::{Child, Command, Stdio};
+
+    #[test]
+    fn test_item_path() {
+        assert_eq!(
+            item_path("my_app 2"),
+            "/org/freedesktop/secrets/collection/rooster/my_5fapp_202"
+        );
+        assert_eq!(
+            item_name(&item_path("my_app 2")),
+            Some("my_app 2".to_owned())
+        );
+        assert_eq!(
+            item_name("/org/freedesktop/secrets/aliases/default/github_2ecom"),
+            Some("github.com".to_owned())
+        );
+        assert_eq!(
+            item_name("/org/freedesktop/secrets/collection/rooster"),
+            None
+        );
+        assert_eq!(
+            item_name("/org/freedesktop/secrets/collection/rooster/a_zz"),
+            None
+        );
+    }
+
+    /// A bus of our own, so that tests don't meet the user's keyring
+    struct Bus {
+        daemon: Child,
+        address: String,
+        _dir: tempfile::TempDir,
+    }
+
+    impl Drop for Bus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    fn start_bus() -> Option<Bus> {
+        let dbus_daemon = which("dbus-daemon")?;
+        let dir = tempfile::tempdir().unwrap();
+        let config = dir.path().join("bus.conf");
+        std::fs::write(
+            &config,
+            format!(
+                "<busconfig>
+                  <type>session</type>
+                  <listen>unix:path={}</listen>
+                  <auth>EXTERNAL</auth>
+                  <policy context=\"default\">
+                    <allow send_destination=\"*\" eavesdrop=\"true\"/>
+                    <allow eavesdrop=\"true\"/>
+                    <allow own=\"*\"/>
+                  </policy>
+                </busconfig>",
+                dir.path().join("bus").to_string_lossy()
+            ),
+        )
+        .unwrap();
+        let mut daemon = Command::new(dbus_daemon)
+            .arg(format!("--config-file={}", config.to_string_lossy()))
+            .arg("--nofork")
+            .arg("--print-address=1")
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+        Some(Bus {
+            daemon,
+            address: address.trim().to_owned(),
+            _dir: dir,
+        })
+    }
+
+    fn call(
+        client: &mut Connection,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> Message {
+        client
+            .call(SERVICE_NAME, path, interface, member, body)
+            .unwrap()
+    }
+
+    fn secret(session: &str, value: &str) -> Value {
+        Value::Struct(vec![
+            Value::ObjectPath(session.to_owned()),
+            Value::Bytes(Vec::new()),
+            Value::Bytes(value.as_bytes().to_vec()),
+            Value::Str("text/plain".to_owned()),
+        ])
+    }
+
+    fn item_properties(label: &str, attributes: &[(&str, &str)]) -> Value {
+        let attributes = Value::dict(
+            "s",
+            "s",
+            attributes
+                .iter()
+                .map(|(k, v)| (Value::Str(k.to_string()), Value::Str(v.to_string())))
+                .collect(),
+        );
+        Value::dict(
+            "s",
+            "v",
+            vec![
+                (
+                    Value::Str("org.freedesktop.Secret.Item.Label".to_owned()),
+                    Value::Variant(Box::new(Value::Str(label.to_owned()))),
+                ),
+                (
+                    Value::Str("org.freedesktop.Secret.Item.Attributes".to_owned()),
+                    Value::Variant(Box::new(attributes)),
+                ),
+            ],
+        )
+    }
+
+    fn server_attributes() -> Value {
+        Value::dict(
+            "s",
+            "s",
+            vec![(
+                Value::Str("server".to_owned()),
+                Value::Str("example.com".to_owned()),
+            )],
+        )
+    }
+
+    fn path(value: &Value) -> String {
+        value.as_str().unwrap().to_owned()
+    }
+
+    fn paths(value: &Value) -> Vec<String> {
+        match value {
+            Value::Array(_, paths) => paths.iter().map(path).collect(),
+            _ => panic!("{:?} is not an array", value),
+        }
+    }
+
+    fn secret_value(reply: &Message) -> Vec<u8> {
+        match reply.body[0] {
+            Value::Struct(ref fields) => match fields[2] {
+                Value::Bytes(ref value) => value.clone(),
+                _ => panic!("{:?} has no secret", reply),
+            },
+            _ => panic!("{:?} has no secret", reply),
+        }
+    }
+
+    /// Unlocks the collection through a prompt, and returns the signal that says how it went
+    fn unlock(client: &mut Connection, collection: &str) -> Message {
+        let reply = call(
+            client,
+            "/org/freedesktop/secrets",
+            "org.freedesktop.Secret.Service",
+            "Unlock",
+            vec![Value::object_paths(&[collection])],
+        );
+        let prompt = path(&reply.body[1]);
+        assert_ne!(prompt, "/");
+        client
+            .call(
+                "org.freedesktop.DBus",
+                "/org/freedesktop/DBus",
+                "org.freedesktop.DBus",
+                "AddMatch",
+                vec![Value::Str(
+                    "type='signal',interface='org.freedesktop.Secret.Prompt'".to_owned(),
+                )],
+            )
+            .unwrap();
+        call(
+            client,
+            &prompt,
+            "org.freedesktop.Secret.Prompt",
+            "Prompt",
+            vec![Value::Str(String::new())],
+        );
+        loop {
+            let message = client.receive().unwrap();
+            if message.message_type == MessageType::Signal
+                && message.member.as_deref() == Some("Completed")
+                && message.path.as_deref() == Some(prompt.as_str())
+            {
+                break message;
+            }
+        }
+    }
+
+    #[test]
+    fn test_secret_service_on_a_private_bus() {
+        let bus = match start_bus() {
+            Some(bus) => bus,
+            // Without dbus-daemon, there's no bus to test on.
+            None => return,
+        };
+
+        let mut store = PasswordStore::new(SafeString::from_string("xxxx".to_owned())).unwrap();
+        store
+            .add_password(Password::new("existing", "alice", "hunter2"))
+            .unwrap();
+        let mut file = tempfile::tempfile().unwrap();
+        store.sync(&mut file).unwrap();
+        let mut server = Connection::open(&bus.address).unwrap();
+        server.request_name(SERVICE_NAME).unwrap();
+        let service_thread = std::thread::spawn(move || {
+            serve(
+                &mut server,
+                &mut SecretService::new(|_| true),
+                &mut store,
+                &mut file,
+            )
+        });
+
+        let service = "/org/freedesktop/secrets";
+        let collection = "/org/freedesktop/secrets/aliases/default";
+        let service_interface = "org.freedesktop.Secret.Service";
+        let collection_interface = "org.freedesktop.Secret.Collection";
+        let item_interface = "org.freedesktop.Secret.Item";
+        let mut client = Connection::open(&bus.address).unwrap();
+
+        let reply = call(
+            &mut client,
+            service,
+            service_interface,
+            "OpenSession",
+            vec![
+                Value::Str("dh-ietf1024-sha256-aes128-cbc-pkcs7".to_owned()),
+                Value::Variant(Box::new(Value::Bytes(Vec::new()))),
+            ],
+        );
+        assert_eq!(
+            reply.error_name.as_deref(),
+            Some("org.freedesktop.DBus.Error.NotSupported")
+        );
+        let reply = call(
+            &mut client,
+            service,
+            service_interface,
+            "OpenSession",
+            vec![
+                Value::Str("plain".to_owned()),
+                Value::Variant(Box::new(Value::Str(String::new()))),
+            ],
+        );
+        let session = path(&reply.body[1]);
+
+        // Passwords that weren't shared with the Secret Service stay hidden.
+        let reply = call(
+            &mut client,
+            service,
+            service_interface,
+            "SearchItems",
+            vec![Value::dict("s", "s", Vec::new())],
+        );
+        assert!(paths(&reply.body[0]).is_empty());
+        assert!(paths(&reply.body[1]).is_empty());
+        let reply = call(
+            &mut client,
+            &item_path("existing"),
+            item_interface,
+            "GetSecret",
+            vec![Value::ObjectPath(session.clone())],
+        );
+        assert_eq!(
+            reply.error_name.as_deref(),
+            Some("org.freedesktop.Secret.Error.NoSuchObject")
+        );
+
+        // The service starts locked.
+        let reply = call(
+            &mut client,
+            collection,
+            collection_interface,
+            "CreateItem",
+            vec![
+                item_properties("Example", &[("server", "example.com"), ("user", "bob")]),
+                secret(&session, "p@ssw0rd"),
+                Value::Bool(true),
+            ],
+        );
+        assert_eq!(
+            reply.error_name.as_deref(),
+            Some("org.freedesktop.Secret.Error.IsLocked")
+        );
+        let completed = unlock(&mut client, collection);
+        assert_eq!(completed.body[0], Value::Bool(false));
+
+        let reply = call(
+            &mut client,
+            collection,
+            collection_interface,
+            "CreateItem",
+            vec![
+                item_properties("Example", &[("server", "example.com"), ("user", "bob")]),
+                secret(&session, "p@ssw0rd"),
+                Value::Bool(true),
+            ],
+        );
+        let item = path(&reply.body[0]);
+        assert_eq!(item, item_path("Example"));
+
+        let reply = call(
+            &mut client,
+            service,
+            service_interface,
+            "SearchItems",
+            vec![server_attributes()],
+        );
+        assert_eq!(paths(&reply.body[0]), vec![item.clone()]);
+        assert!(paths(&reply.body[1]).is_empty());
+
+        let reply = call(
+            &mut client,
+            &item,
+            item_interface,
+            "GetSecret",
+            vec![Value::ObjectPath(session.clone())],
+        );
+        assert_eq!(secret_value(&reply), b"p@ssw0rd");
+
+        // Storing again with the same attributes replaces the secret.
+        let reply = call(
+            &mut client,
+            collection,
+            collection_interface,
+            "CreateItem",
+            vec![
+                item_properties("Example", &[("server", "example.com"), ("user", "bob")]),
+                secret(&session, "n3w p@ssw0rd"),
+                Value::Bool(true),
+            ],
+        );
+        assert_eq!(path(&reply.body[0]), item);
+        let reply = call(
+            &mut client,
+            &item,
+            item_interface,
+            "GetSecret",
+            vec![Value::ObjectPath(session.clone())],
+        );
+        assert_eq!(secret_value(&reply), b"n3w p@ssw0rd");
+
+        let reply = call(
+            &mut client,
+            &item,
+            "org.freedesktop.DBus.Properties",
+            "Get",
+            vec![
+                Value::Str(item_interface.to_owned()),
+                Value::Str("Attributes".to_owned()),
+            ],
+        );
+        assert_eq!(reply.body[0].unwrap_variant().as_dict().unwrap().len(), 2);
+
+        // Locked, items show up as locked and keep their secrets.
+        call(
+            &mut client,
+            service,
+            service_interface,
+            "Lock",
+            vec![Value::object_paths(&[collection])],
+        );
+        let reply = call(
+            &mut client,
+            service,
+            service_interface,
+            "SearchItems",
+            vec![server_attributes()],
+        );
+        assert!(paths(&reply.body[0]).is_empty());
+        assert_eq!(paths(&reply.body[1]), vec![item.clone()]);
+        let reply = call(
+            &mut client,
+            &item,
+            item_interface,
+            "GetSecret",
+            vec![Value::ObjectPath(session.clone())],
+        );
+        assert_eq!(
+            reply.error_name.as_deref(),
+            Some("org.freedesktop.Secret.Error.IsLocked")
+        );
+
+        // Unlocking again goes through a prompt too.
+        let completed = unlock(&mut client, collection);
+        assert_eq!(completed.body[0], Value::Bool(false));
+        assert_eq!(
+            paths(completed.body[1].unwrap_variant()),
+            vec![collection.to_owned()]
+        );
+        let reply = call(
+            &mut client,
+            &item,
+            item_interface,
+            "GetSecret",
+            vec![Value::ObjectPath(session.clone())],
+        );
+        assert_eq!(secret_value(&reply), b"n3w p@ssw0rd");
+
+        call(&mut client, &item, item_interface, "Delete", Vec::new());
+        let reply = call(
+            &mut client,
+            service,
+            service_interface,
+            "SearchItems",
+            vec![server_attributes()],
+        );
+        assert!(paths(&reply.body[0]).is_empty());
+
+        drop(bus);
+        service_thread.join().unwrap().unwrap();
+    }
+}
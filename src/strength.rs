@@ -0,0 +1,826 @@
+use crate::ffi;
+use crate::rclio::CliInputOutput;
+use crate::rclio::OutputType;
+use lazy_static::lazy_static;
+use std::collections::{HashMap, HashSet};
+
+/// The minimum score, from 0 to 4, of new master passwords
+pub const ROOSTER_MIN_MASTER_PASSWORD_SCORE_ENV_VAR: &str = "ROOSTER_MIN_MASTER_PASSWORD_SCORE";
+
+/// Master passwords protect the password file against attackers who stole it, so they need to
+/// resist offline guessing
+pub const DEFAULT_MIN_MASTER_PASSWORD_SCORE: u8 = 3;
+
+/// Passwords people use the most, most common first
+const COMMON_PASSWORDS: &str = include_str!("wordlists/common_passwords.txt");
+
+/// Common English words, the same ones passphrases are made of by default
+const ENGLISH_WORDS: &str = include_str!("wordlists/bip39_english.txt");
+
+/// How fast an attacker who stole the password file guesses, given that it is protected by scrypt
+const OFFLINE_GUESSES_PER_SECOND: f64 = 1e4;
+
+/// Longer passwords are analyzed this many characters at a time
+const MAX_ANALYZED_LEN: usize = 64;
+
+/// Keys that can start a keyboard pattern, and how many neighbours keys have on average
+const KEYBOARD_STARTING_KEYS: f64 = 47.0;
+const KEYBOARD_AVERAGE_DEGREE: f64 = 4.6;
+
+/// The QWERTY keyboard, unshifted and shifted, with how far right each row starts
+const KEYBOARD_ROWS: [(&str, &str, f64); 4] = [
+    ("`1234567890-=", "~!@#$%^&*()_+", 0.0),
+    ("qwertyuiop[]\\", "QWERTYUIOP{}|", 1.5),
+    ("asdfghjkl;'", "ASDFGHJKL:\"", 1.75),
+    ("zxcvbnm,./", "ZXCVBNM<>?", 2.25),
+];
+
+/// Characters people use in place of letters, as in "p@ssw0rd"
+const L33T_SUBSTITUTIONS: [(char, char); 14] = [
+    ('4', 'a'),
+    ('@', 'a'),
+    ('8', 'b'),
+    ('(', 'c'),
+    ('3', 'e'),
+    ('6', 'g'),
+    ('1', 'i'),
+    ('!', 'i'),
+    ('|', 'l'),
+    ('0', 'o'),
+    ('$', 's'),
+    ('5', 's'),
+    ('7', 't'),
+    ('+', 't'),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Pattern {
+    CommonPassword,
+    Word,
+    Keyboard,
+    Sequence,
+    Repeat,
+    Date,
+    Random,
+}
+
+/// A part of a password that follows a pattern
+#[derive(Clone, Copy, Debug)]
+struct Match {
+    start: usize,
+    end: usize,
+    pattern: Pattern,
+    /// How many guesses it takes to find this part, as a power of 10
+    guesses_log10: f64,
+}
+
+/// Where the last part of a split password starts, and which match it is (none for random
+/// characters)
+type Step = (usize, Option<usize>);
+
+/// How hard a password is to guess
+#[derive(Debug)]
+pub struct Estimate {
+    /// How many guesses it takes to find the password, as a power of 10
+    pub guesses_log10: f64,
+    /// From 0 (too guessable) to 4 (very unguessable), like zxcvbn
+    pub score: u8,
+    /// The pattern that makes the password easiest to guess, if any
+    pub weakness: Option<Pattern>,
+    /// Whether the password is a common password or a single word, rather than containing one
+    weakness_is_whole_password: bool,
+}
+
+impl Estimate {
+    pub fn entropy_bits(&self) -> f64 {
+        self.guesses_log10 * 10f64.log2()
+    }
+
+    pub fn offline_crack_time(&self) -> String {
+        describe_duration(10f64.powf(self.guesses_log10) / OFFLINE_GUESSES_PER_SECOND)
+    }
+
+    pub fn describe_score(&self) -> &'static str {
+        match self.score {
+            0 => "very weak",
+            1 => "weak",
+            2 => "fair",
+            3 => "strong",
+            _ => "very strong",
+        }
+    }
+
+    /// Explains what makes a weak password easy to guess
+    pub fn warning(&self) -> Option<&'static str> {
+        if self.score >= 3 {
+            return None;
+        }
+        Some(match (self.weakness, self.weakness_is_whole_password) {
+            (Some(Pattern::CommonPassword), true) => "This is one of the most common passwords.",
+            (Some(Pattern::CommonPassword), false) => {
+                "This is similar to a commonly used password."
+            }
+            (Some(Pattern::Word), true) => "A word by itself is easy to guess.",
+            (Some(Pattern::Word), false) => "Common words are easy to guess.",
+            (Some(Pattern::Keyboard), _) => {
+                "Patterns on the keyboard, like \"qwerty\" or \"zxcvbn\", are easy to guess."
+            }
+            (Some(Pattern::Sequence), _) => {
+                "Sequences, like \"abc\" or \"6543\", are easy to guess."
+            }
+            (Some(Pattern::Repeat), _) => "Repeats, like \"aaa\" or \"abcabc\", are easy to guess.",
+            (Some(Pattern::Date), _) => "Dates and years are easy to guess.",
+            _ => "This password is too short.",
+        })
+    }
+}
+
+struct Dictionaries {
+    /// Common passwords, with their rank
+    common_passwords: HashMap<String, usize>,
+    words: HashSet<String>,
+    reference_year: i64,
+}
+
+lazy_static! {
+    /// Parsing the lists takes longer than most estimates, so it is only done once
+    static ref DICTIONARIES: Dictionaries = Dictionaries::new();
+}
+
+impl Dictionaries {
+    fn new() -> Dictionaries {
+        Dictionaries {
+            common_passwords: COMMON_PASSWORDS
+                .lines()
+                .enumerate()
+                .map(|(i, password)| (password.to_owned(), i + 1))
+                .collect(),
+            words: ENGLISH_WORDS.lines().map(|word| word.to_owned()).collect(),
+            reference_year: 1970 + ffi::time() as i64 / 31_556_952,
+        }
+    }
+}
+
+/// Estimates how many guesses it takes to find a password, in the spirit of zxcvbn
+///
+/// The password is split into the parts that are easiest to guess: common passwords, English
+/// words (also with l33t substitutions, reversed or capitalized), keyboard patterns, sequences,
+/// repeats, dates and years. Whatever is left counts as random characters.
+pub fn estimate(password: &str) -> Estimate {
+    let chars: Vec<char> = password.chars().collect();
+    let (guesses_log10, sequence) = most_guessable(&chars, &DICTIONARIES);
+
+    let score = if guesses_log10 < 3.0 {
+        0
+    } else if guesses_log10 < 6.0 {
+        1
+    } else if guesses_log10 < 8.0 {
+        2
+    } else if guesses_log10 < 10.0 {
+        3
+    } else {
+        4
+    };
+
+    let weakest = sequence
+        .iter()
+        .filter(|m| m.pattern != Pattern::Random)
+        .max_by_key(|m| m.end - m.start);
+    Estimate {
+        guesses_log10,
+        score,
+        weakness: weakest.map(|m| m.pattern),
+        weakness_is_whole_password: sequence.len() == 1,
+    }
+}
+
+/// Finds the way to split the password into parts that takes the fewest guesses
+fn most_guessable(chars: &[char], dictionaries: &Dictionaries) -> (f64, Vec<Match>) {
+    if chars.is_empty() {
+        return (0.0, Vec::new());
+    }
+    let analyzed = &chars[..chars.len().min(MAX_ANALYZED_LEN)];
+    let n = analyzed.len();
+    // Longer passwords are analyzed in chunks, patterns that cross chunks are missed but it is
+    // much faster.
+    let rest_log10 = if chars.len() > n {
+        most_guessable(&chars[n..], dictionaries).0 + 2f64.log10()
+    } else {
+        0.0
+    };
+
+    let mut matches = find_matches(analyzed, dictionaries);
+    for m in matches.iter_mut() {
+        // Parts of a password are never quite as easy to guess as the same part on its own.
+        if m.end - m.start < n {
+            let min_log10 = if m.end - m.start == 1 {
+                1.0
+            } else {
+                50f64.log10()
+            };
+            m.guesses_log10 = m.guesses_log10.max(min_log10);
+        }
+    }
+
+    // best[k][j] is the fewest guesses for the first j characters, split in k parts.
+    let mut best = vec![vec![f64::INFINITY; n + 1]; n + 1];
+    let mut previous: Vec<Vec<Option<Step>>> = vec![vec![None; n + 1]; n + 1];
+    best[0][0] = 0.0;
+    for end in 1..=n {
+        for k in 0..end {
+            for start in 0..end {
+                if best[k][start].is_infinite() {
+                    continue;
+                }
+                // Random characters, 10 guesses each like zxcvbn.
+                let guesses = best[k][start] + (end - start) as f64;
+                if guesses < best[k + 1][end] {
+                    best[k + 1][end] = guesses;
+                    previous[k + 1][end] = Some((start, None));
+                }
+            }
+            for (i, m) in matches.iter().enumerate().filter(|(_, m)| m.end == end) {
+                let guesses = best[k][m.start] + m.guesses_log10;
+                if guesses < best[k + 1][end] {
+                    best[k + 1][end] = guesses;
+                    previous[k + 1][end] = Some((m.start, Some(i)));
+                }
+            }
+        }
+    }
+
+    // The more parts, the more ways to put them together.
+    let (parts, guesses_log10) = (1..=n).map(|k| (k, best[k][n] + log10_factorial(k))).fold(
+        (1, f64::INFINITY),
+        |min, candidate| {
+            if candidate.1 < min.1 {
+                candidate
+            } else {
+                min
+            }
+        },
+    );
+
+    let mut sequence = Vec::new();
+    let mut end = n;
+    for k in (1..=parts).rev() {
+        let (start, match_index) = previous[k][end].unwrap();
+        sequence.push(match match_index {
+            Some(i) => matches[i],
+            None => Match {
+                start,
+                end,
+                pattern: Pattern::Random,
+                guesses_log10: (end - start) as f64,
+            },
+        });
+        end = start;
+    }
+    sequence.reverse();
+
+    (guesses_log10 + rest_log10, sequence)
+}
+
+fn log10_factorial(k: usize) -> f64 {
+    (1..=k).map(|i| (i as f64).log10()).sum()
+}
+
+fn find_matches(chars: &[char], dictionaries: &Dictionaries) -> Vec<Match> {
+    let mut matches = dictionary_matches(chars, dictionaries);
+    matches.extend(keyboard_matches(chars));
+    matches.extend(sequence_matches(chars));
+    matches.extend(repeat_matches(chars, dictionaries));
+    matches.extend(date_matches(chars, dictionaries.reference_year));
+    matches
+}
+
+fn dictionary_matches(chars: &[char], dictionaries: &Dictionaries) -> Vec<Match> {
+    let lowercase: Vec<char> = chars
+        .iter()
+        .map(|c| c.to_lowercase().next().unwrap_or(*c))
+        .collect();
+    let mut matches = Vec::new();
+
+    for start in 0..chars.len() {
+        for end in start + 3..=chars.len().min(start + 32) {
+            let part: String = lowercase[start..end].iter().collect();
+            let reversed: String = lowercase[start..end].iter().rev().collect();
+            let case_log10 = uppercase_variations(&chars[start..end]).log10();
+
+            // Plain, then with l33t substitutions undone, then reversed.
+            let mut candidates = vec![(part.clone(), 0.0)];
+            for one in ['i', 'l'].iter() {
+                let unl33ted = unl33t(&part, *one);
+                if unl33ted != part {
+                    candidates.push((unl33ted, 2f64.log10()));
+                }
+            }
+            if reversed != part {
+                candidates.push((reversed, 2f64.log10()));
+            }
+
+            let best = candidates
+                .iter()
+                .filter_map(|(candidate, extra_log10)| {
+                    if let Some(rank) = dictionaries.common_passwords.get(candidate) {
+                        Some((
+                            Pattern::CommonPassword,
+                            (*rank as f64).log10() + extra_log10,
+                        ))
+                    } else if dictionaries.words.contains(candidate) {
+                        Some((
+                            Pattern::Word,
+                            (dictionaries.words.len() as f64).log10() + extra_log10,
+                        ))
+                    } else {
+                        None
+                    }
+                })
+                .fold(None, |best: Option<(Pattern, f64)>, candidate| match best {
+                    Some(best) if best.1 <= candidate.1 => Some(best),
+                    _ => Some(candidate),
+                });
+            if let Some((pattern, guesses_log10)) = best {
+                matches.push(Match {
+                    start,
+                    end,
+                    pattern,
+                    guesses_log10: guesses_log10 + case_log10,
+                });
+            }
+        }
+    }
+
+    matches
+}
+
+fn unl33t(part: &str, one: char) -> String {
+    part.chars()
+        .map(|c| {
+            if c == '1' {
+                return one;
+            }
+            match L33T_SUBSTITUTIONS.iter().find(|(l33t, _)| *l33t == c) {
+                Some((_, letter)) => *letter,
+                None => c,
+            }
+        })
+        .collect()
+}
+
+/// How many ways there are to capitalize a word like this one
+fn uppercase_variations(chars: &[char]) -> f64 {
+    let upper = chars.iter().filter(|c| c.is_uppercase()).count();
+    let lower = chars.iter().filter(|c| c.is_lowercase()).count();
+    if upper == 0 {
+        return 1.0;
+    }
+    let first_only = upper == 1 && chars[0].is_uppercase();
+    let last_only = upper == 1 && chars[chars.len() - 1].is_uppercase();
+    if first_only || last_only || lower == 0 {
+        return 2.0;
+    }
+    (1..=upper.min(lower))
+        .map(|i| binomial(upper + lower, i))
+        .sum()
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    (1..=k).fold(1.0, |acc, i| acc * (n + 1 - i) as f64 / i as f64)
+}
+
+/// Where a key is on the keyboard, and whether it needs shift
+fn key_position(c: char) -> Option<(f64, f64, bool)> {
+    for (row, (unshifted, shifted, offset)) in KEYBOARD_ROWS.iter().enumerate() {
+        if let Some(column) = unshifted.chars().position(|k| k == c) {
+            return Some((row as f64, offset + column as f64, false));
+        }
+        if let Some(column) = shifted.chars().position(|k| k == c) {
+            return Some((row as f64, offset + column as f64, true));
+        }
+    }
+    None
+}
+
+fn keyboard_matches(chars: &[char]) -> Vec<Match> {
+    let positions: Vec<Option<(f64, f64, bool)>> = chars.iter().map(|c| key_position(*c)).collect();
+    let adjacent = |a: (f64, f64, bool), b: (f64, f64, bool)| {
+        let (rows, columns) = ((a.0 - b.0).abs(), (a.1 - b.1).abs());
+        (rows == 0.0 && columns == 1.0) || (rows == 1.0 && columns <= 0.75)
+    };
+
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = start + 1;
+        let mut turns = 1;
+        let mut last_direction = None;
+        while end < chars.len() {
+            match (positions[end - 1], positions[end]) {
+                (Some(a), Some(b)) if adjacent(a, b) => {
+                    let direction = ((b.0 - a.0) as i64, ((b.1 - a.1) * 4.0) as i64);
+                    if last_direction.is_some() && last_direction != Some(direction) {
+                        turns += 1;
+                    }
+                    last_direction = Some(direction);
+                    end += 1;
+                }
+                _ => break,
+            }
+        }
+
+        if end - start >= 3 {
+            let len = end - start;
+            // Like zxcvbn: every way to make `turns` turns along a pattern of this length.
+            let mut guesses = 0.0;
+            for i in 2..=len {
+                for j in 1..=turns.min(i - 1) {
+                    guesses += binomial(i - 1, j - 1)
+                        * KEYBOARD_STARTING_KEYS
+                        * KEYBOARD_AVERAGE_DEGREE.powi(j as i32);
+                }
+            }
+            let shifted = positions[start..end]
+                .iter()
+                .filter(|p| matches!(p, Some((_, _, true))))
+                .count();
+            if shifted > 0 {
+                guesses *= if shifted == len {
+                    2.0
+                } else {
+                    binomial(len, shifted)
+                };
+            }
+            matches.push(Match {
+                start,
+                end,
+                pattern: Pattern::Keyboard,
+                guesses_log10: guesses.log10(),
+            });
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+
+    matches
+}
+
+fn sequence_matches(chars: &[char]) -> Vec<Match> {
+    let same_kind = |a: char, b: char| {
+        (a.is_ascii_lowercase() && b.is_ascii_lowercase())
+            || (a.is_ascii_uppercase() && b.is_ascii_uppercase())
+            || (a.is_ascii_digit() && b.is_ascii_digit())
+    };
+
+    let mut matches = Vec::new();
+    let mut start = 0;
+    while start + 1 < chars.len() {
+        let delta = chars[start + 1] as i64 - chars[start] as i64;
+        let mut end = start + 1;
+        if delta != 0 && delta.abs() <= 5 {
+            while end < chars.len()
+                && same_kind(chars[start], chars[end])
+                && chars[end] as i64 - chars[end - 1] as i64 == delta
+            {
+                end += 1;
+            }
+        }
+
+        if end - start >= 3 {
+            let first = chars[start];
+            let base = if "aAzZ019".contains(first) {
+                4.0
+            } else if first.is_ascii_digit() {
+                10.0
+            } else {
+                26.0
+            };
+            let direction = if delta < 0 { 2.0 } else { 1.0 };
+            matches.push(Match {
+                start,
+                end,
+                pattern: Pattern::Sequence,
+                guesses_log10: (base * direction * (end - start) as f64).log10(),
+            });
+            start = end - 1;
+        } else {
+            start += 1;
+        }
+    }
+
+    matches
+}
+
+fn repeat_matches(chars: &[char], dictionaries: &Dictionaries) -> Vec<Match> {
+    let mut matches = Vec::new();
+    // Repeats of repeats, like "aaaaaa", have the same blocks at many places.
+    let mut block_guesses: HashMap<&[char], f64> = HashMap::new();
+
+    for start in 0..chars.len() {
+        for block_len in 1..=(chars.len() - start) / 2 {
+            // Only the longest repeat starting here is interesting.
+            let continues_earlier = start >= block_len
+                && chars[start - block_len..start] == chars[start..start + block_len];
+            if continues_earlier {
+                continue;
+            }
+
+            let block = &chars[start..start + block_len];
+            let mut count = 1;
+            while start + (count + 1) * block_len <= chars.len()
+                && &chars[start + count * block_len..start + (count + 1) * block_len] == block
+            {
+                count += 1;
+            }
+            if count < 2 || count * block_len < 3 {
+                continue;
+            }
+
+            let block_log10 = *block_guesses
+                .entry(block)
+                .or_insert_with(|| block_guesses_log10(block, dictionaries));
+            matches.push(Match {
+                start,
+                end: start + count * block_len,
+                pattern: Pattern::Repeat,
+                guesses_log10: block_log10 + (count as f64).log10(),
+            });
+        }
+    }
+
+    matches
+}
+
+/// How many guesses it takes to find the block of a repeat, as a single pattern or else as random
+/// characters. Splitting it like whole passwords are would make repeats take super-linear time.
+fn block_guesses_log10(block: &[char], dictionaries: &Dictionaries) -> f64 {
+    let mut matches = dictionary_matches(block, dictionaries);
+    matches.extend(keyboard_matches(block));
+    matches.extend(sequence_matches(block));
+    matches.extend(date_matches(block, dictionaries.reference_year));
+    matches
+        .iter()
+        .filter(|m| m.start == 0 && m.end == block.len())
+        .map(|m| m.guesses_log10)
+        .fold(block.len() as f64, f64::min)
+}
+
+fn date_matches(chars: &[char], reference_year: i64) -> Vec<Match> {
+    let mut matches = Vec::new();
+
+    for start in 0..chars.len() {
+        for end in start + 4..=chars.len().min(start + 10) {
+            let part: String = chars[start..end].iter().collect();
+            let (year, separator) = match parse_date(&part) {
+                Some(date) => date,
+                None => continue,
+            };
+            let year_space = ((year - reference_year).abs() as f64).max(20.0);
+            let guesses = if part.len() == 4 && year == part.parse::<i64>().unwrap_or(-1) {
+                year_space
+            } else {
+                365.0 * year_space * if separator { 4.0 } else { 1.0 }
+            };
+            matches.push(Match {
+                start,
+                end,
+                pattern: Pattern::Date,
+                guesses_log10: guesses.log10(),
+            });
+        }
+    }
+
+    matches
+}
+
+/// Returns the year of a date or a year like "1987", "14/07/1789", "2021-03-04" or "040321",
+/// and whether it has separators
+fn parse_date(part: &str) -> Option<(i64, bool)> {
+    if part.len() == 4 {
+        if let Ok(year) = part.parse::<i64>() {
+            if (1900..=2099).contains(&year) {
+                return Some((year, false));
+            }
+        }
+    }
+
+    let separators: Vec<char> = part.chars().filter(|c| !c.is_ascii_digit()).collect();
+    if separators.is_empty() {
+        if part.len() > 8 {
+            return None;
+        }
+        for i in 1..part.len() {
+            for j in i + 1..part.len() {
+                if let Some(year) = parse_day_month_year([&part[..i], &part[i..j], &part[j..]]) {
+                    return Some((year, false));
+                }
+            }
+        }
+        return None;
+    }
+
+    let separator = separators[0];
+    if separators.len() != 2 || separators[1] != separator || !"-/._ ".contains(separator) {
+        return None;
+    }
+    let groups: Vec<&str> = part.split(separator).collect();
+    if groups.iter().any(|group| group.is_empty()) {
+        return None;
+    }
+    parse_day_month_year([groups[0], groups[1], groups[2]]).map(|year| (year, true))
+}
+
+fn parse_day_month_year(groups: [&str; 3]) -> Option<i64> {
+    if groups.iter().any(|group| group.len() > 4) {
+        return None;
+    }
+    let numbers: Vec<i64> = groups
+        .iter()
+        .filter_map(|group| group.parse().ok())
+        .collect();
+    if numbers.len() != 3 {
+        return None;
+    }
+
+    let year = |i: usize| match groups[i].len() {
+        2 if numbers[i] < 50 => Some(2000 + numbers[i]),
+        2 => Some(1900 + numbers[i]),
+        4 if (1000..=2099).contains(&numbers[i]) => Some(numbers[i]),
+        _ => None,
+    };
+    let day_and_month = |a: i64, b: i64| {
+        ((1..=31).contains(&a) && (1..=12).contains(&b))
+            || ((1..=12).contains(&a) && (1..=31).contains(&b))
+    };
+    let short = |i: usize| groups[i].len() <= 2;
+
+    if short(0) && short(1) && day_and_month(numbers[0], numbers[1]) {
+        if let Some(year) = year(2) {
+            return Some(year);
+        }
+    }
+    if short(1) && short(2) && day_and_month(numbers[1], numbers[2]) {
+        if let Some(year) = year(0) {
+            return Some(year);
+        }
+    }
+    None
+}
+
+fn describe_duration(seconds: f64) -> String {
+    let units = [
+        ("second", 1.0),
+        ("minute", 60.0),
+        ("hour", 3600.0),
+        ("day", 86400.0),
+        ("month", 86400.0 * 31.0),
+        ("year", 86400.0 * 365.0),
+    ];
+    if seconds < 1.0 {
+        return "less than a second".to_owned();
+    }
+    if seconds >= 86400.0 * 365.0 * 100.0 {
+        return "centuries".to_owned();
+    }
+    let (unit, length) = units
+        .iter()
+        .rev()
+        .find(|(_, length)| seconds >= *length)
+        .unwrap();
+    let count = (seconds / length).round() as u64;
+    format!("{} {}{}", count, unit, if count == 1 { "" } else { "s" })
+}
+
+/// Tells the user how strong a password is, warning them if it is weak
+pub fn report(estimate: &Estimate, io: &mut impl CliInputOutput) {
+    let message = format!(
+        "This password is {} ({}/4): about {:.0} bits of entropy, it would take {} to crack offline.",
+        estimate.describe_score(),
+        estimate.score,
+        estimate.entropy_bits(),
+        estimate.offline_crack_time()
+    );
+    match estimate.warning() {
+        Some(warning) => {
+            io.warning(message, OutputType::Standard);
+            io.warning(warning, OutputType::Standard);
+        }
+        None => io.info(message, OutputType::Standard),
+    }
+}
+
+pub fn min_master_password_score() -> u8 {
+    std::env::var(ROOSTER_MIN_MASTER_PASSWORD_SCORE_ENV_VAR)
+        .ok()
+        .and_then(|score| score.parse::<u8>().ok())
+        .unwrap_or(DEFAULT_MIN_MASTER_PASSWORD_SCORE)
+        .min(4)
+}
+
+/// Reports how strong a new master password is, refusing it if it is too weak
+pub fn check_master_password(
+    master_password: &str,
+    min_score: u8,
+    io: &mut impl CliInputOutput,
+) -> Result<(), i32> {
+    let estimate = estimate(master_password);
+    report(&estimate, io);
+    if estimate.score < min_score {
+        io.error(
+            format!(
+                "Woops! Your master password needs a score of at least {}/4. Try a longer one, \
+                 for instance a few random words. You can change the minimum score with {}.",
+                min_score, ROOSTER_MIN_MASTER_PASSWORD_SCORE_ENV_VAR
+            ),
+            OutputType::Error,
+        );
+        return Err(1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::{estimate, parse_date, Pattern, DEFAULT_MIN_MASTER_PASSWORD_SCORE};
+
+    #[test]
+    fn test_common_passwords_are_very_weak() {
+        for password in ["password", "123456", "qwerty", "P@ssw0rd", "iloveyou"].iter() {
+            let estimate = estimate(password);
+            assert_eq!(estimate.score, 0, "{}", password);
+            assert_eq!(estimate.weakness, Some(Pattern::CommonPassword));
+        }
+        assert_eq!(estimate("").score, 0);
+    }
+
+    #[test]
+    fn test_weak_passwords_cant_be_master_passwords() {
+        for password in [
+            "michelle",
+            "Michelle1",
+            "spongebob",
+            "tinkerbell",
+            "Elizabeth1",
+            "Password123!",
+            "P@ssw0rd1",
+            "Summer2023!",
+            "monkey2020",
+            "1qaz2wsx3edc",
+            "!QAZ2wsx",
+            "iloveyou2",
+            "letmein123",
+            "Liverpool1",
+            "babygirl1",
+        ]
+        .iter()
+        {
+            assert!(
+                estimate(password).score < DEFAULT_MIN_MASTER_PASSWORD_SCORE,
+                "{}",
+                password
+            );
+        }
+    }
+
+    #[test]
+    fn test_patterns() {
+        assert_eq!(estimate("sdfghjkl;").weakness, Some(Pattern::Keyboard));
+        assert_eq!(estimate("zxcvfrewq").weakness, Some(Pattern::Keyboard));
+        assert_eq!(estimate("abcdefghij").weakness, Some(Pattern::Sequence));
+        assert_eq!(estimate("97531").weakness, Some(Pattern::Sequence));
+        assert_eq!(estimate("zzzzzzzzzzzz").weakness, Some(Pattern::Repeat));
+        assert_eq!(estimate("xkcdxkcdxkcd").weakness, Some(Pattern::Repeat));
+        assert_eq!(estimate("abcabcabc").weakness, Some(Pattern::Repeat));
+        assert_eq!(estimate(&"a".repeat(64)).weakness, Some(Pattern::Repeat));
+        assert_eq!(estimate(&"a".repeat(64)).score, 0);
+        assert_eq!(estimate("14/07/1789").weakness, Some(Pattern::Date));
+        assert_eq!(estimate("orange").weakness, Some(Pattern::CommonPassword));
+        assert_eq!(estimate("Tomato").weakness, Some(Pattern::Word));
+        for password in ["sdfghjkl;", "abcdefghij", "zzzzzzzzzzzz", "14/07/1789"].iter() {
+            assert!(estimate(password).score <= 1, "{}", password);
+        }
+    }
+
+    #[test]
+    fn test_strong_passwords() {
+        assert!(estimate("tr|BA=+&&xpIP`b$DMQ]").score == 4);
+        assert!(estimate("correct horse battery staple").score >= 3);
+        assert!(estimate("Ponder-Vivid-Canoe-71").score >= 3);
+        // Words make a password weaker than random characters of the same length.
+        assert!(
+            estimate("sunshinefootball").guesses_log10 < estimate("qnxmvbzlwkpjtyrg").guesses_log10
+        );
+    }
+
+    #[test]
+    fn test_parse_date() {
+        assert_eq!(parse_date("1987"), Some((1987, false)));
+        assert_eq!(parse_date("2021-03-04"), Some((2021, true)));
+        assert_eq!(parse_date("040321"), Some((2021, false)));
+        assert_eq!(parse_date("14.07.89"), Some((1989, true)));
+        assert_eq!(parse_date("0000"), None);
+        assert_eq!(parse_date("45/45/45"), None);
+        assert_eq!(parse_date("2021-03/04"), None);
+    }
+}
@@ -0,0 +1,564 @@
+use crate::expiry;
+use crate::ffi;
+use crate::generate::{Generator, PasswordSpec, PUBLIC_KEY_FIELD};
+use crate::password::v2::{Password, PasswordStore};
+use crate::password::PasswordError;
+use crate::rutil::safe_string::SafeString;
+use std::ops::Deref;
+
+pub mod terminal;
+
+use self::terminal::Key;
+
+const HELP: &str = "Enter copy password  ^U copy username  Tab show  ^N new  ^G regenerate  ^R rename  ^D delete  Esc quit";
+
+/// What the terminal needs to do after a key press
+pub enum Effect {
+    Nothing,
+    Quit,
+    /// Copy something to the clipboard, along with what it is ("password", "username")
+    Copy(&'static str, SafeString),
+}
+
+/// Questions asked at the bottom of the screen
+enum Prompt {
+    NewName,
+    NewUsername { name: String },
+    Rename,
+    ConfirmRegenerate,
+    ConfirmDelete,
+}
+
+impl Prompt {
+    fn label(&self) -> &'static str {
+        match self {
+            Prompt::NewName => "New app: ",
+            Prompt::NewUsername { .. } => "Username: ",
+            Prompt::Rename => "Rename to: ",
+            Prompt::ConfirmRegenerate => "Replace this password with a new one? (y/n) ",
+            Prompt::ConfirmDelete => "Delete this password? (y/n) ",
+        }
+    }
+
+    fn is_confirmation(&self) -> bool {
+        matches!(self, Prompt::ConfirmRegenerate | Prompt::ConfirmDelete)
+    }
+}
+
+/// State of `rooster ui`, which is drawn from scratch after every key press
+pub struct App {
+    query: String,
+    /// Names of the apps matching the query, best matches first
+    results: Vec<String>,
+    selected: usize,
+    /// First result shown on screen, when there are more results than lines
+    scroll: usize,
+    list_height: usize,
+    revealed: bool,
+    prompt: Option<(Prompt, String)>,
+    status: Option<String>,
+    pub changed: bool,
+}
+
+impl App {
+    pub fn new(store: &PasswordStore) -> App {
+        let mut app = App {
+            query: String::new(),
+            results: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            list_height: 1,
+            revealed: false,
+            prompt: None,
+            status: None,
+            changed: false,
+        };
+        app.refresh(store);
+        app
+    }
+
+    pub fn set_status(&mut self, status: String) {
+        self.status = Some(status);
+    }
+
+    fn refresh(&mut self, store: &PasswordStore) {
+        self.results = store
+            .search_passwords(&self.query)
+            .iter()
+            .map(|p| p.name.clone())
+            .collect();
+        if self.selected >= self.results.len() {
+            self.selected = self.results.len().saturating_sub(1);
+        }
+    }
+
+    fn select_name(&mut self, name: &str) {
+        if let Some(i) = self.results.iter().position(|n| n == name) {
+            self.selected = i;
+        }
+    }
+
+    fn move_selection(&mut self, offset: isize) {
+        let last = self.results.len().saturating_sub(1) as isize;
+        let selected = (self.selected as isize + offset).max(0).min(last) as usize;
+        if selected != self.selected {
+            self.selected = selected;
+            self.revealed = false;
+        }
+    }
+
+    fn selected_password(&self, store: &PasswordStore) -> Option<Password> {
+        self.results
+            .get(self.selected)
+            .and_then(|name| store.get_password(name))
+    }
+
+    pub fn handle_key(&mut self, key: Key, store: &mut PasswordStore) -> Effect {
+        self.status = None;
+        match self.prompt.take() {
+            Some((prompt, input)) => self.handle_prompt_key(key, prompt, input, store),
+            None => self.handle_browse_key(key, store),
+        }
+    }
+
+    fn handle_browse_key(&mut self, key: Key, store: &mut PasswordStore) -> Effect {
+        let page = self.list_height as isize;
+        match key {
+            Key::Char(c) => {
+                self.query.push(c);
+                self.selected = 0;
+                self.revealed = false;
+                self.refresh(store);
+            }
+            Key::Backspace => {
+                self.query.pop();
+                self.refresh(store);
+            }
+            Key::Up => self.move_selection(-1),
+            Key::Down => self.move_selection(1),
+            Key::PageUp => self.move_selection(-page),
+            Key::PageDown => self.move_selection(page),
+            Key::Home => self.move_selection(-(self.results.len() as isize)),
+            Key::End => self.move_selection(self.results.len() as isize),
+            Key::Tab => self.revealed = !self.revealed,
+            Key::Enter => {
+                if let Some(password) = self.selected_password(store) {
+                    return Effect::Copy("password", password.password);
+                }
+            }
+            Key::Ctrl('u') => {
+                if let Some(password) = self.selected_password(store) {
+                    return Effect::Copy("username", SafeString::from_string(password.username));
+                }
+            }
+            Key::Ctrl('n') => self.prompt = Some((Prompt::NewName, String::new())),
+            Key::Ctrl('r') => {
+                if let Some(password) = self.selected_password(store) {
+                    self.prompt = Some((Prompt::Rename, password.name));
+                }
+            }
+            Key::Ctrl('g') if self.selected_password(store).is_some() => {
+                self.prompt = Some((Prompt::ConfirmRegenerate, String::new()));
+            }
+            Key::Ctrl('d') if self.selected_password(store).is_some() => {
+                self.prompt = Some((Prompt::ConfirmDelete, String::new()));
+            }
+            Key::Esc if !self.query.is_empty() => {
+                self.query.clear();
+                self.refresh(store);
+            }
+            Key::Esc | Key::Ctrl('c') | Key::Ctrl('q') => return Effect::Quit,
+            _ => {}
+        }
+        Effect::Nothing
+    }
+
+    fn handle_prompt_key(
+        &mut self,
+        key: Key,
+        prompt: Prompt,
+        mut input: String,
+        store: &mut PasswordStore,
+    ) -> Effect {
+        if prompt.is_confirmation() {
+            return match key {
+                Key::Char('y') | Key::Char('Y') => self.submit(prompt, input, store),
+                _ => {
+                    self.status = Some("Alright, I didn't change anything.".to_owned());
+                    Effect::Nothing
+                }
+            };
+        }
+
+        match key {
+            Key::Char(c) => input.push(c),
+            Key::Backspace => {
+                input.pop();
+            }
+            Key::Enter if !input.trim().is_empty() => return self.submit(prompt, input, store),
+            Key::Esc | Key::Ctrl('c') => {
+                self.status = Some("Alright, I didn't change anything.".to_owned());
+                return Effect::Nothing;
+            }
+            _ => {}
+        }
+        self.prompt = Some((prompt, input));
+        Effect::Nothing
+    }
+
+    fn submit(&mut self, prompt: Prompt, input: String, store: &mut PasswordStore) -> Effect {
+        let input = input.trim().to_owned();
+        let selected = self.selected_password(store);
+        let result = match (prompt, selected) {
+            (Prompt::NewName, _) => {
+                if store.has_password(&input) {
+                    Err(PasswordError::AppExistsError)
+                } else {
+                    self.prompt = Some((Prompt::NewUsername { name: input }, String::new()));
+                    return Effect::Nothing;
+                }
+            }
+            (Prompt::NewUsername { name }, _) => self.add(store, name, input),
+            (Prompt::Rename, Some(password)) => self.rename(store, &password, input),
+            (Prompt::ConfirmRegenerate, Some(password)) => self.regenerate(store, &password),
+            (Prompt::ConfirmDelete, Some(password)) => {
+                store.delete_password(&password.name).map(|_| {
+                    self.status = Some(format!("Done! I've deleted {}.", password.name));
+                    None
+                })
+            }
+            _ => Ok(None),
+        };
+
+        match result {
+            Ok(copy) => {
+                self.changed = true;
+                self.refresh(store);
+                match copy {
+                    Some(password) => Effect::Copy("new password", password),
+                    None => Effect::Nothing,
+                }
+            }
+            Err(PasswordError::AppExistsError) => {
+                self.status = Some("Woops, there is already an app with this name.".to_owned());
+                Effect::Nothing
+            }
+            Err(err) => {
+                self.status = Some(format!("Woops, something went wrong ({:?}).", err));
+                Effect::Nothing
+            }
+        }
+    }
+
+    fn add(
+        &mut self,
+        store: &mut PasswordStore,
+        name: String,
+        username: String,
+    ) -> Result<Option<SafeString>, PasswordError> {
+        let secret = new_secret()?;
+        let mut password = Password::new(name, username, secret.value.clone());
+        password.fields = secret.fields;
+        let name = password.name.clone();
+        store.add_password(password)?;
+        self.query.clear();
+        self.refresh(store);
+        self.select_name(&name);
+        self.revealed = false;
+        Ok(Some(secret.value))
+    }
+
+    fn rename(
+        &mut self,
+        store: &mut PasswordStore,
+        password: &Password,
+        new_name: String,
+    ) -> Result<Option<SafeString>, PasswordError> {
+        if new_name != password.name && store.has_password(&new_name) {
+            return Err(PasswordError::AppExistsError);
+        }
+        store.change_password(&password.name, &|old_password: Password| Password {
+            name: new_name.clone(),
+            ..old_password
+        })?;
+        self.query.clear();
+        self.refresh(store);
+        self.select_name(&new_name);
+        self.status = Some(format!(
+            "Done! I've renamed {} to {}.",
+            password.name, new_name
+        ));
+        Ok(None)
+    }
+
+    fn regenerate(
+        &mut self,
+        store: &mut PasswordStore,
+        password: &Password,
+    ) -> Result<Option<SafeString>, PasswordError> {
+        let secret = new_secret()?;
+        store.change_password(&password.name, &|old_password: Password| {
+            let mut fields = old_password.fields.clone();
+            fields.remove(PUBLIC_KEY_FIELD);
+            fields.extend(secret.fields.clone());
+            Password {
+                password: secret.value.clone(),
+                updated_at: ffi::time(),
+                fields,
+                ..old_password
+            }
+        })?;
+        self.revealed = false;
+        Ok(Some(secret.value))
+    }
+
+    /// Draws the screen: the search bar, the results, the selected password and the status line
+    pub fn render(&mut self, store: &PasswordStore, width: usize, height: usize) -> Vec<String> {
+        // The title, search and separator lines, then the results, then the status line
+        let height = height.max(5);
+        self.list_height = height - 4;
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if self.selected >= self.scroll + self.list_height {
+            self.scroll = self.selected + 1 - self.list_height;
+        }
+
+        let mut lines = Vec::with_capacity(height);
+        lines.push(format!(
+            "\x1b[7m{}",
+            fit(
+                &format!(" Rooster - {} passwords", store.get_all_passwords().len()),
+                width
+            )
+        ));
+        lines.push(match &self.prompt {
+            Some((prompt, input)) => fit(&format!("{}{}", prompt.label(), input), width),
+            None => fit(&format!("Search: {}", self.query), width),
+        });
+        lines.push("\u{2500}".repeat(width));
+
+        let list_width = (width * 2 / 5).max(20).min(width);
+        let detail_width = width.saturating_sub(list_width + 3);
+        let details = match self.selected_password(store) {
+            Some(password) => self.details(&password),
+            None => Vec::new(),
+        };
+        for row in 0..self.list_height {
+            let i = self.scroll + row;
+            let entry = match self
+                .results
+                .get(i)
+                .and_then(|name| store.get_password(name))
+            {
+                Some(password) if password.username.is_empty() => password.name,
+                Some(password) => format!("{}  {}", password.name, password.username),
+                None if i == 0 => "No match".to_owned(),
+                None => String::new(),
+            };
+            let entry = if i == self.selected && !self.results.is_empty() {
+                format!("\x1b[7m{}\x1b[0m", fit(&entry, list_width))
+            } else {
+                fit(&entry, list_width)
+            };
+            let detail = details.get(row).map(|s| s.as_str()).unwrap_or("");
+            lines.push(format!("{} \u{2502} {}", entry, fit(detail, detail_width)));
+        }
+
+        lines.push(format!(
+            "\x1b[7m{}",
+            fit(self.status.as_deref().unwrap_or(HELP), width)
+        ));
+        lines
+    }
+
+    fn details(&self, password: &Password) -> Vec<String> {
+        let mut details = vec![
+            format!("App       {}", password.name),
+            format!("Username  {}", password.username),
+        ];
+        if self.revealed {
+            for (i, line) in password.password.deref().lines().enumerate() {
+                details.push(format!(
+                    "{}{}",
+                    if i == 0 { "Password  " } else { "          " },
+                    line
+                ));
+            }
+        } else {
+            details.push("Password  ******** (Tab to show)".to_owned());
+        }
+        details.push(String::new());
+        details.push(format!(
+            "Created   {}",
+            expiry::format_date(password.created_at as i64)
+        ));
+        details.push(format!(
+            "Updated   {}",
+            expiry::format_date(password.updated_at as i64)
+        ));
+        if let (Some(policy), Some(due_at)) =
+            (expiry::describe_policy(password), expiry::due_at(password))
+        {
+            details.push(format!(
+                "Expiry    {}, due {}",
+                policy,
+                expiry::describe_due(due_at, ffi::time() as i64)
+            ));
+        }
+        for (name, value) in password.fields.iter() {
+            details.push(format!("{:9} {}", name, value.lines().next().unwrap_or("")));
+        }
+        details
+    }
+}
+
+fn new_secret() -> Result<crate::generate::Secret, PasswordError> {
+    Ok(Generator::Password(PasswordSpec::new(false, None)).generate()?)
+}
+
+/// Cuts or pads text to exactly `width` characters, so it doesn't spill over
+fn fit(text: &str, width: usize) -> String {
+    let fitted: String = text
+        .chars()
+        .map(|c| if c.is_control() { '?' } else { c })
+        .take(width)
+        .collect();
+    format!("{:width$}", fitted, width = width)
+}
+
+#[cfg(test)]
+mod test {
+    use super::terminal::Key;
+    use super::{fit, App, Effect};
+    use crate::password::v2::{Password, PasswordStore};
+    use crate::rutil::safe_string::SafeString;
+    use std::ops::Deref;
+
+    fn store() -> PasswordStore {
+        let mut store = PasswordStore::new(SafeString::from_string("****".to_owned())).unwrap();
+        for (name, username) in [
+            ("Gigahertz Shop", "alice"),
+            ("GitHub", "bob"),
+            ("Mail", "carol"),
+        ]
+        .iter()
+        {
+            store
+                .add_password(Password::new(*name, *username, "xxxx"))
+                .unwrap();
+        }
+        store
+    }
+
+    fn type_keys(app: &mut App, store: &mut PasswordStore, keys: &[Key]) {
+        for key in keys {
+            app.handle_key(*key, store);
+        }
+    }
+
+    fn type_text(app: &mut App, store: &mut PasswordStore, text: &str) {
+        for c in text.chars() {
+            app.handle_key(Key::Char(c), store);
+        }
+    }
+
+    #[test]
+    fn test_fit() {
+        assert_eq!(fit("abc", 5), "abc  ");
+        assert_eq!(fit("a\tbcdef", 4), "a?bc");
+    }
+
+    #[test]
+    fn test_search_and_select() {
+        let mut store = store();
+        let mut app = App::new(&store);
+        assert_eq!(app.results, vec!["Gigahertz Shop", "GitHub", "Mail"]);
+
+        type_text(&mut app, &mut store, "gh");
+        assert_eq!(app.results, vec!["GitHub", "Gigahertz Shop"]);
+
+        type_keys(&mut app, &mut store, &[Key::Down, Key::Down, Key::Tab]);
+        assert_eq!(app.selected, 1);
+        assert!(app.revealed);
+        type_keys(&mut app, &mut store, &[Key::Up]);
+        assert!(!app.revealed);
+
+        match app.handle_key(Key::Ctrl('u'), &mut store) {
+            Effect::Copy("username", username) => assert_eq!(username.deref(), "bob"),
+            _ => panic!("the username should be copied"),
+        }
+
+        type_keys(&mut app, &mut store, &[Key::Esc]);
+        assert_eq!(app.results.len(), 3);
+        assert!(match app.handle_key(Key::Esc, &mut store) {
+            Effect::Quit => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn test_rename_delete_and_new() {
+        let mut store = store();
+        let mut app = App::new(&store);
+
+        type_keys(&mut app, &mut store, &[Key::Down, Key::Ctrl('r')]);
+        for _ in 0.."GitHub".len() {
+            app.handle_key(Key::Backspace, &mut store);
+        }
+        type_text(&mut app, &mut store, "Mail");
+        type_keys(&mut app, &mut store, &[Key::Enter]);
+        assert!(store.has_password("GitHub"));
+        assert!(app.status.is_some());
+
+        type_keys(
+            &mut app,
+            &mut store,
+            &[Key::Ctrl('r'), Key::Char('2'), Key::Enter],
+        );
+        assert!(store.has_password("GitHub2"));
+        assert_eq!(app.results[app.selected], "GitHub2");
+
+        type_keys(&mut app, &mut store, &[Key::Ctrl('d'), Key::Char('n')]);
+        assert!(store.has_password("GitHub2"));
+        type_keys(&mut app, &mut store, &[Key::Ctrl('d'), Key::Char('y')]);
+        assert!(!store.has_password("GitHub2"));
+        assert!(app.changed);
+
+        app.handle_key(Key::Ctrl('n'), &mut store);
+        type_text(&mut app, &mut store, "Forum");
+        app.handle_key(Key::Enter, &mut store);
+        type_text(&mut app, &mut store, "dave");
+        match app.handle_key(Key::Enter, &mut store) {
+            Effect::Copy(_, password) => {
+                assert_eq!(store.get_password("Forum").unwrap().password, password)
+            }
+            _ => panic!("the new password should be copied"),
+        }
+        assert_eq!(app.results[app.selected], "Forum");
+    }
+
+    #[test]
+    fn test_regenerate() {
+        let mut store = store();
+        let mut app = App::new(&store);
+        type_keys(&mut app, &mut store, &[Key::Ctrl('g'), Key::Char('y')]);
+        let password = store.get_password("Gigahertz Shop").unwrap();
+        assert_ne!(password.password.deref(), "xxxx");
+        assert_eq!(password.username, "alice");
+    }
+
+    #[test]
+    fn test_render_hides_passwords() {
+        let mut store = store();
+        let mut app = App::new(&store);
+        let screen = app.render(&store, 80, 10).join("\n");
+        assert!(screen.contains("Gigahertz Shop  alice"));
+        assert!(screen.contains("********"));
+        assert!(!screen.contains("xxxx"));
+
+        app.handle_key(Key::Tab, &mut store);
+        let screen = app.render(&store, 80, 10).join("\n");
+        assert!(screen.contains("Password  xxxx"));
+        assert_eq!(app.render(&store, 80, 10).len(), 10);
+    }
+}
@@ -0,0 +1,240 @@
+use libc::{c_int, tcsetattr, termios, TCSANOW};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::mem;
+use std::os::unix::io::AsRawFd;
+
+const ENTER_ALTERNATE_SCREEN: &str = "\x1b[?1049h\x1b[?25l";
+const LEAVE_ALTERNATE_SCREEN: &str = "\x1b[?25h\x1b[?1049l";
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Key {
+    Char(char),
+    Ctrl(char),
+    Enter,
+    Tab,
+    Backspace,
+    Delete,
+    Esc,
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+}
+
+/// The terminal in raw mode, showing the alternate screen until it is dropped
+pub struct Terminal {
+    tty: File,
+    term_orig: termios,
+}
+
+impl Terminal {
+    pub fn open() -> io::Result<Terminal> {
+        let mut tty = OpenOptions::new().read(true).write(true).open("/dev/tty")?;
+        let fd = tty.as_raw_fd();
+
+        let term_orig = safe_tcgetattr(fd)?;
+        let mut term = term_orig;
+        // Read keys one by one, without echoing them, and let Ctrl-C, Ctrl-S, etc reach us.
+        term.c_iflag &= !(libc::BRKINT | libc::ICRNL | libc::INPCK | libc::ISTRIP | libc::IXON);
+        term.c_lflag &= !(libc::ECHO | libc::ICANON | libc::IEXTEN | libc::ISIG);
+        term.c_cc[libc::VMIN] = 1;
+        term.c_cc[libc::VTIME] = 0;
+        io_result(unsafe { tcsetattr(fd, TCSANOW, &term) })?;
+
+        tty.write_all(ENTER_ALTERNATE_SCREEN.as_bytes())?;
+        tty.flush()?;
+        Ok(Terminal { tty, term_orig })
+    }
+
+    /// The width and height of the terminal
+    pub fn size(&self) -> (usize, usize) {
+        let mut size = mem::MaybeUninit::<libc::winsize>::uninit();
+        let ret = unsafe { libc::ioctl(self.tty.as_raw_fd(), libc::TIOCGWINSZ, size.as_mut_ptr()) };
+        if ret != 0 {
+            return (80, 24);
+        }
+        let size = unsafe { size.assume_init() };
+        if size.ws_col == 0 || size.ws_row == 0 {
+            return (80, 24);
+        }
+        (size.ws_col as usize, size.ws_row as usize)
+    }
+
+    /// Waits for keys for up to `timeout_ms` milliseconds
+    pub fn read_keys(&mut self, timeout_ms: c_int) -> io::Result<Vec<Key>> {
+        let mut fds = libc::pollfd {
+            fd: self.tty.as_raw_fd(),
+            events: libc::POLLIN,
+            revents: 0,
+        };
+        match unsafe { libc::poll(&mut fds, 1, timeout_ms) } {
+            -1 => {
+                let err = io::Error::last_os_error();
+                if err.kind() == io::ErrorKind::Interrupted {
+                    Ok(Vec::new())
+                } else {
+                    Err(err)
+                }
+            }
+            0 => Ok(Vec::new()),
+            _ => {
+                let mut buffer = [0u8; 64];
+                let len = self.tty.read(&mut buffer)?;
+                Ok(parse_keys(&buffer[..len]))
+            }
+        }
+    }
+
+    /// Replaces what's on screen with these lines
+    pub fn draw(&mut self, lines: &[String]) -> io::Result<()> {
+        let mut screen = String::from("\x1b[H");
+        for (i, line) in lines.iter().enumerate() {
+            if i > 0 {
+                screen.push_str("\r\n");
+            }
+            screen.push_str(line);
+            screen.push_str("\x1b[0m\x1b[K");
+        }
+        screen.push_str("\x1b[J");
+        self.tty.write_all(screen.as_bytes())?;
+        self.tty.flush()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.tty.write_all(LEAVE_ALTERNATE_SCREEN.as_bytes());
+        let _ = self.tty.flush();
+        unsafe {
+            tcsetattr(self.tty.as_raw_fd(), TCSANOW, &self.term_orig);
+        }
+    }
+}
+
+/// Turns a C function return into an IO Result
+fn io_result(ret: c_int) -> io::Result<()> {
+    match ret {
+        0 => Ok(()),
+        _ => Err(io::Error::last_os_error()),
+    }
+}
+
+fn safe_tcgetattr(fd: c_int) -> io::Result<termios> {
+    let mut term = mem::MaybeUninit::<termios>::uninit();
+    io_result(unsafe { ::libc::tcgetattr(fd, term.as_mut_ptr()) })?;
+    Ok(unsafe { term.assume_init() })
+}
+
+/// Turns what the terminal sent into keys, ignoring escape sequences we don't know
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let mut keys = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            0x1b => {
+                let (key, len) = parse_escape_sequence(&bytes[i..]);
+                keys.extend(key);
+                i += len;
+            }
+            b'\r' | b'\n' => {
+                keys.push(Key::Enter);
+                i += 1;
+            }
+            b'\t' => {
+                keys.push(Key::Tab);
+                i += 1;
+            }
+            0x7f | 0x08 => {
+                keys.push(Key::Backspace);
+                i += 1;
+            }
+            byte @ 0x01..=0x1a => {
+                keys.push(Key::Ctrl((b'a' + byte - 1) as char));
+                i += 1;
+            }
+            0x00..=0x1f => i += 1,
+            _ => {
+                let start = i;
+                while i < bytes.len() && bytes[i] >= 0x20 && bytes[i] != 0x7f {
+                    i += 1;
+                }
+                keys.extend(
+                    String::from_utf8_lossy(&bytes[start..i])
+                        .chars()
+                        .map(Key::Char),
+                );
+            }
+        }
+    }
+    keys
+}
+
+/// Parses a sequence starting with ESC, returning the key and how many bytes it took
+fn parse_escape_sequence(bytes: &[u8]) -> (Option<Key>, usize) {
+    match bytes.get(1) {
+        None | Some(0x1b) => (Some(Key::Esc), 1),
+        Some(b'[') | Some(b'O') => {
+            // Parameters, then a final byte between '@' and '~'
+            let mut end = 2;
+            while end < bytes.len() && !(0x40..=0x7e).contains(&bytes[end]) {
+                end += 1;
+            }
+            if end == bytes.len() {
+                return (None, bytes.len());
+            }
+            let key = match (&bytes[2..end], bytes[end]) {
+                (_, b'A') => Some(Key::Up),
+                (_, b'B') => Some(Key::Down),
+                (_, b'C') => Some(Key::Right),
+                (_, b'D') => Some(Key::Left),
+                (_, b'H') | (b"1", b'~') | (b"7", b'~') => Some(Key::Home),
+                (_, b'F') | (b"4", b'~') | (b"8", b'~') => Some(Key::End),
+                (b"3", b'~') => Some(Key::Delete),
+                (b"5", b'~') => Some(Key::PageUp),
+                (b"6", b'~') => Some(Key::PageDown),
+                _ => None,
+            };
+            (key, end + 1)
+        }
+        // Alt and another key, which we don't use
+        Some(_) => (None, 2),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{parse_keys, Key};
+
+    #[test]
+    fn test_parse_keys() {
+        assert_eq!(
+            parse_keys(b"gh\x7f\r\t"),
+            vec![
+                Key::Char('g'),
+                Key::Char('h'),
+                Key::Backspace,
+                Key::Enter,
+                Key::Tab
+            ]
+        );
+        assert_eq!(
+            parse_keys(b"\x1b[A\x1b[B\x1bOH\x1b[6~\x1b[1;5C"),
+            vec![Key::Up, Key::Down, Key::Home, Key::PageDown, Key::Right]
+        );
+        assert_eq!(parse_keys(b"\x1b"), vec![Key::Esc]);
+        assert_eq!(parse_keys(b"\x1b\x1b[Z"), vec![Key::Esc]);
+        assert_eq!(
+            parse_keys(b"\x03\x15"),
+            vec![Key::Ctrl('c'), Key::Ctrl('u')]
+        );
+        assert_eq!(
+            parse_keys("é!".as_bytes()),
+            vec![Key::Char('é'), Key::Char('!')]
+        );
+    }
+}
@@ -0,0 +1,103 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::thread;
+use std::time::Duration;
+
+#[test]
+fn test_command_agent() {
+    let rooster_file = tempfile();
+    let socket_dir = tempfile::tempdir().unwrap();
+    let socket = socket_dir.path().join("agent.sock");
+    let socket_as_string = socket.to_string_lossy().into_owned();
+
+    let agent_rooster_file = rooster_file.clone();
+    thread::spawn(move || {
+        main_with_args(
+            &[
+                "rooster",
+                "agent",
+                "--foreground",
+                "--socket",
+                socket_as_string.as_str(),
+            ],
+            &mut CursorInputOutput::new("", ""),
+            &agent_rooster_file,
+        )
+    });
+    while !socket.exists() {
+        thread::sleep(Duration::from_millis(10));
+    }
+    std::env::set_var("ROOSTER_AGENT_SOCK", &socket);
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The master password is asked once, then cached by the agent
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "youtube"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+
+    // A new master password replaces the cached one
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "set-master-password"],
+            &mut CursorInputOutput::new("", "xxxx\nPonder-Vivid-Canoe-71\nPonder-Vivid-Canoe-71\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+
+    // Once locked, the master password is needed again
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "lock"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "-s", "youtube"],
+            &mut CursorInputOutput::new("", "Ponder-Vivid-Canoe-71\n"),
+            &rooster_file
+        )
+    );
+}
@@ -0,0 +1,104 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_askpass() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "db01", "alice"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // Without patterns, prompts aren't enough to pick an app
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "askpass", "alice@db01's password: "],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"");
+
+    std::env::set_var("ROOSTER_ASKPASS_PATTERNS", "{user}@db01's password => db01");
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "askpass", "alice@db01's password: "],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"abcd\n");
+
+    // Other hosts, and questions that aren't about passwords, get no answer
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "askpass", "alice@db01.evil.com's password: "],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"");
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "askpass",
+                "Are you sure you want to continue connecting (yes/no)? "
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"");
+
+    // Apps named after the prompt need the user's approval
+    std::env::set_var(
+        "ROOSTER_ASKPASS_PATTERNS",
+        "{user}@{host}'s password => {host}",
+    );
+    std::env::set_var("ROOSTER_APPROVE_COMMAND", "false");
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "askpass", "alice@db01's password: "],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"");
+
+    std::env::set_var("ROOSTER_APPROVE_COMMAND", "true");
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "askpass", "alice@db01's password: "],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"abcd\n");
+}
@@ -0,0 +1,215 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn sha1_hex(password: &str) -> String {
+    openssl::sha::sha1(password.as_bytes())
+        .iter()
+        .map(|byte| format!("{:02X}", byte))
+        .collect()
+}
+
+#[test]
+fn test_command_audit_breaches() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut lines = vec![
+        format!("{}:123", sha1_hex("abcd")),
+        format!("{}:9545824", sha1_hex("password")),
+        "0000000000000000000000000000000000000000:1".to_owned(),
+        "FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF:1".to_owned(),
+    ];
+    lines.sort();
+    let hibp_file = tempfile();
+    std::fs::write(&hibp_file, lines.join("\r\n")).unwrap();
+    let hibp_file_as_string = hibp_file.to_string_lossy().into_owned();
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Youtube", "yt@example.com"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Bank", "alice"],
+            &mut CursorInputOutput::new("", "xxxx\nPonder-Vivid-Canoe-71\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "audit",
+                "breaches",
+                "--hibp-file",
+                hibp_file_as_string.as_str()
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Youtube (yt@example.com): seen 123 times"));
+    assert!(!output_as_string.contains("Bank"));
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "delete", "youtube"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "audit",
+                "breaches",
+                "--hibp-file",
+                hibp_file_as_string.as_str()
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_audit() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let import_file = tempfile();
+    std::fs::write(
+        &import_file,
+        format!(
+            "{{\"passwords\":[\
+             {{\"name\":\"Bank\",\"username\":\"alice\",\"password\":\"Ponder-Vivid-Canoe-71\",\"created_at\":{now},\"updated_at\":{now}}},\
+             {{\"name\":\"Mail\",\"username\":\"\",\"password\":\"Ponder-Vivid-Canoe-71\",\"created_at\":{now},\"updated_at\":{now}}},\
+             {{\"name\":\"Forum\",\"username\":\"bob\",\"password\":\"abcd\",\"created_at\":1605554169,\"updated_at\":1605554169}}\
+             ]}}",
+            now = now
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "import", "json", import_file.to_str().unwrap()],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "audit", "--output", "json"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let report: serde_json::Value = serde_json::from_slice(output_as_vecu8.as_slice()).unwrap();
+    let issues: Vec<(&str, &str, &str)> = report["result"]["issues"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|issue| {
+            (
+                issue["severity"].as_str().unwrap(),
+                issue["app"].as_str().unwrap(),
+                issue["problem"].as_str().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        issues,
+        vec![
+            ("high", "Bank", "reused"),
+            ("high", "Forum", "weak"),
+            ("high", "Mail", "reused"),
+            ("low", "Forum", "old"),
+            ("low", "Mail", "no-username"),
+        ]
+    );
+
+    // The table doesn't show passwords.
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "audit"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("same password as Mail"));
+    assert!(!output_as_string.contains("Ponder-Vivid-Canoe-71"));
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "delete", "mail"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "audit", "--min-score", "0", "--max-age", "0"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    // Scores go from 0 to 4, and ages must fit
+    for args in [
+        ["--min-score", "300"],
+        ["--min-score", "5"],
+        ["--min-score", ""],
+        ["--max-age", "99999999999999999999999"],
+    ]
+    .iter()
+    {
+        let mut io = CursorInputOutput::new("", "xxxx\n");
+        assert_eq!(
+            2,
+            main_with_args(
+                &["rooster", "audit", "--output", "json", args[0], args[1]],
+                &mut io,
+                &rooster_file
+            )
+        );
+        assert!(io.stdout_cursor.into_inner().is_empty());
+    }
+}
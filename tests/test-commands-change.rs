@@ -9,7 +9,7 @@ fn test_command_change() {
         0,
         main_with_args(
             &["rooster", "init", "--force-for-tests"],
-            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
             &rooster_file
         )
     );
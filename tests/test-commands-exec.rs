@@ -0,0 +1,93 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_exec_pty() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "db", "admin"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // Like password prompts do, the script only asks once echo is off
+    let script =
+        "stty -echo; printf 'Enter password: '; read -r pass; stty echo; [ \"$pass\" = abcd ]";
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "exec",
+                "--pty",
+                "--answer",
+                "Enter password => db",
+                "--",
+                "sh",
+                "-c",
+                script
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The exit code of the command is ours
+    assert_eq!(
+        3,
+        main_with_args(
+            &["rooster", "exec", "--pty", "--", "sh", "-c", "exit 3"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "exec",
+                "--pty",
+                "--answer",
+                "Enter password",
+                "--",
+                "true"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The program doesn't get to choose which password is typed
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "exec",
+                "--pty",
+                "--answer",
+                "Password for {app} => {app}",
+                "--",
+                "sh",
+                "-c",
+                "printf 'Password for db: '"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
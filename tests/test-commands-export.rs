@@ -2,6 +2,7 @@ mod helpers;
 
 use crate::helpers::prelude::*;
 use serde_json::Value;
+use std::os::unix::fs::PermissionsExt;
 
 #[test]
 fn test_command_export_json() {
@@ -10,7 +11,7 @@ fn test_command_export_json() {
         0,
         main_with_args(
             &["rooster", "init", "--force-for-tests"],
-            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
             &rooster_file
         )
     );
@@ -64,7 +65,7 @@ fn test_command_export_csv() {
         0,
         main_with_args(
             &["rooster", "init", "--force-for-tests"],
-            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
             &rooster_file
         )
     );
@@ -95,7 +96,7 @@ fn test_command_export_1password() {
         0,
         main_with_args(
             &["rooster", "init", "--force-for-tests"],
-            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
             &rooster_file
         )
     );
@@ -118,3 +119,160 @@ fn test_command_export_1password() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert_eq!(output_as_string, "Youtube,yt@example.com,abcd\n");
 }
+
+#[test]
+fn test_command_export_deployment_formats() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "prod-db", "admin"],
+            &mut CursorInputOutput::new("", "xxxx\nab\"c$d\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "staging-db", "tester"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "field", "staging-db", "tags", "staging"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "export", "env"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert_eq!(
+        output_as_string,
+        "PROD_DB_USERNAME='admin'\n\
+         PROD_DB_PASSWORD='ab\"c$d'\n\
+         STAGING_DB_USERNAME='tester'\n\
+         STAGING_DB_PASSWORD='efgh'\n"
+    );
+
+    // Queries match the start of names, not any name with the same letters
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Dropbox", "alice"],
+            &mut CursorInputOutput::new("", "xxxx\nit's\n"),
+            &rooster_file
+        )
+    );
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "export", "env", "--query", "prod"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert_eq!(
+        output_as_string,
+        "PROD_DB_USERNAME='admin'\nPROD_DB_PASSWORD='ab\"c$d'\n"
+    );
+
+    // Single quotes can't be written so that every program reads them the same
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "export", "env", "--query", "dropbox"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert!(io.stdout_cursor.into_inner().is_empty());
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "export",
+                "k8s-secret",
+                "--name",
+                "app-creds",
+                "--query",
+                "tag:staging",
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert_eq!(
+        output_as_string,
+        "apiVersion: v1\n\
+         kind: Secret\n\
+         metadata:\n  name: app-creds\n\
+         type: Opaque\n\
+         data:\n  STAGING_DB_USERNAME: dGVzdGVy\n  STAGING_DB_PASSWORD: ZWZnaA==\n"
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "export", "k8s-secret", "--name", "App Creds"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let directory = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "export",
+                "systemd-creds",
+                directory.to_str().unwrap(),
+                "-q",
+                "tag:staging",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let password_file = directory.join("STAGING_DB_PASSWORD");
+    assert_eq!(std::fs::read_to_string(&password_file).unwrap(), "efgh");
+    assert_eq!(
+        std::fs::metadata(&password_file)
+            .unwrap()
+            .permissions()
+            .mode()
+            & 0o777,
+        0o600
+    );
+    assert_eq!(
+        std::fs::metadata(&directory).unwrap().permissions().mode() & 0o777,
+        0o700
+    );
+    assert!(!directory.join("PROD_DB_PASSWORD").exists());
+}
@@ -0,0 +1,116 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_field() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "generate", "-s", "Bank", "alice"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "field",
+                "bank",
+                "autotype",
+                "{username}{enter}{wait}{password}"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Other changes to the password keep its fields.
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "rename", "bank", "Savings"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "field", "savings", "autotype"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("{username}{enter}{wait}{password}"));
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "field", "savings", "autotype", "--unset"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "field", "savings", "autotype"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    // Fields aren't the password, so setting one doesn't make the password newer.
+    let import_file = tempfile();
+    std::fs::write(
+        &import_file,
+        "{\"passwords\":[{\"name\":\"Forum\",\"username\":\"bob\",\"password\":\"abcd\",\
+         \"created_at\":1605554169,\"updated_at\":1605554169}]}",
+    )
+    .unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "import", "json", import_file.to_str().unwrap()],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "--output",
+                "json",
+                "field",
+                "forum",
+                "url",
+                "https://forum.example.com"
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output: serde_json::Value =
+        serde_json::from_slice(io.stdout_cursor.into_inner().as_slice()).unwrap();
+    assert_eq!(output["result"]["entry"]["updated_at"], 1605554169);
+}
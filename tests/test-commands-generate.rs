@@ -9,7 +9,7 @@ fn test_command_generate() {
         0,
         main_with_args(
             &["rooster", "init", "--force-for-tests"],
-            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
             &rooster_file
         )
     );
@@ -42,3 +42,202 @@ fn test_command_generate() {
     let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
     assert!(output_as_string.contains("yt@example.com"));
 }
+
+#[test]
+fn test_command_generate_with_rules() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "-s",
+                "Bank",
+                "alice",
+                "--length",
+                "12",
+                "--exclude",
+                "!\"#$%&'()*+,./:;<=>?@[\\]^`{|}~",
+                "--min-symbols",
+                "3",
+                "--letter-first",
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let password = output_as_string
+        .split("Here is your password: ")
+        .nth(1)
+        .unwrap()
+        .split('\u{1b}')
+        .next()
+        .unwrap()
+        .trim_end();
+    assert_eq!(password.len(), 12);
+    assert!(password.chars().next().unwrap().is_ascii_alphabetic());
+    assert!(password.chars().filter(|c| *c == '-' || *c == '_').count() >= 3);
+
+    // Rules that no password can satisfy
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "-s",
+                "Bank 2",
+                "alice",
+                "--alnum",
+                "--min-symbols",
+                "1",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
+
+#[test]
+fn test_command_generate_passphrase() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let wordlist = tempfile();
+    std::fs::write(
+        &wordlist,
+        "11111\tcorrect\n11112\thorse\n11113\tbattery\n11114\tstaple\n",
+    )
+    .unwrap();
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "-s",
+                "Laptop",
+                "alice",
+                "--passphrase",
+                "--words",
+                "5",
+                "--separator",
+                ".",
+                "--wordlist",
+                wordlist.to_str().unwrap(),
+            ],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("5 words from a list of 4, that's 10 bits of entropy"));
+    let passphrase = output_as_string
+        .split("Here is your password: ")
+        .nth(1)
+        .unwrap()
+        .split('\u{1b}')
+        .next()
+        .unwrap()
+        .trim_end();
+    let words: Vec<&str> = passphrase.split('.').collect();
+    assert_eq!(words.len(), 5);
+    assert!(words
+        .iter()
+        .all(|w| ["correct", "horse", "battery", "staple"].contains(w)));
+}
+
+#[test]
+fn test_command_generate_other_kinds() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "generate",
+                "-s",
+                "Server",
+                "alice",
+                "--kind",
+                "ssh-ed25519",
+                "--comment",
+                "alice@server",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "field", "server", "public-key"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("ssh-ed25519 AAAA"));
+    assert!(output_as_string.contains(" alice@server"));
+
+    // Options of other kinds of secrets are refused.
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "gen", "--kind", "pin", "--alnum"],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+
+    // `gen` only prints the secret, it doesn't need the password file.
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "gen", "--kind", "pin", "--length", "8"],
+            &mut io,
+            &tempfile()
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    let pin = output_as_string.trim_end();
+    assert_eq!(pin.len(), 8);
+    assert!(pin.chars().all(|c| c.is_ascii_digit()));
+}
@@ -9,7 +9,7 @@ fn test_command_get() {
         0,
         main_with_args(
             &["rooster", "init", "--force-for-tests"],
-            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
             &rooster_file
         )
     );
@@ -59,3 +59,184 @@ fn test_command_get() {
     assert!(output_as_string.contains("efgh"));
     assert!(output_as_string.contains("second@example.com"));
 }
+
+#[test]
+fn test_command_get_ranks_results() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Gigahertz Shop", "alice"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "GitHub", "bob"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+
+    // The best match comes first, even though it isn't first alphabetically
+    let mut io = CursorInputOutput::new("", "xxxx\n1\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "gh"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("efgh"));
+
+    // Searching usernames
+    let mut io = CursorInputOutput::new("", "xxxx\n1\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "-s", "user:alice"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("abcd"));
+    assert!(!output_as_string.contains("GitHub"));
+}
+
+#[test]
+fn test_command_get_without_asking() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Gigahertz Shop", "alice"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "GitHub", "bob"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+
+    // Only the value, without a new line
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "gh", "--first", "--print", "password"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"efgh");
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "gh", "--index", "2", "--print", "username"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"alice");
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "gh", "--index", "3", "--print", "username"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "github", "--print", "url"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // An exact name is always fine, anything else fails instead of asking
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "github", "--exact", "--print", "name"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"GitHub");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "-s", "gh", "--exact"],
+            &mut CursorInputOutput::new("", "xxxx\n1\n"),
+            &rooster_file
+        )
+    );
+
+    // Names that look like searches of a field are still names
+    for name in ["host:8080", "ssh:db01"].iter() {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "add", "-s", name, "alice"],
+                &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+                &rooster_file
+            )
+        );
+        let mut io = CursorInputOutput::new("", "xxxx\n");
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "get", name, "--exact", "--print", "name"],
+                &mut io,
+                &rooster_file
+            )
+        );
+        assert_eq!(io.stdout_cursor.into_inner(), name.as_bytes());
+    }
+
+    // Even the master password can't be asked for
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "--no-input", "get", "-s", "github"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("--no-input"));
+}
@@ -0,0 +1,143 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+fn git_credential(operation: &str, request: &str, rooster_file: &std::path::PathBuf) -> String {
+    let mut io = CursorInputOutput::new(request, "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "git-credential", operation],
+            &mut io,
+            rooster_file
+        )
+    );
+    String::from_utf8(io.stdout_cursor.into_inner()).unwrap()
+}
+
+#[test]
+fn test_command_git_credential() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Unknown hosts are left to git
+    let request = "protocol=https\nhost=git.example.com\n\n";
+    assert_eq!(git_credential("get", request, &rooster_file), "");
+
+    git_credential(
+        "store",
+        "protocol=https\nhost=git.example.com\nusername=alice\npassword=token1\n\n",
+        &rooster_file,
+    );
+    assert_eq!(
+        git_credential("get", request, &rooster_file),
+        "username=alice\npassword=token1\n"
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "git.example.com", "--print", "url"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"https://git.example.com");
+
+    // A new token replaces the old one, and erasing keeps it
+    git_credential(
+        "store",
+        "protocol=https\nhost=git.example.com\nusername=alice\npassword=token2\n\n",
+        &rooster_file,
+    );
+    git_credential(
+        "erase",
+        "protocol=https\nhost=git.example.com\nusername=alice\npassword=token2\n\n",
+        &rooster_file,
+    );
+    assert_eq!(
+        git_credential("get", request, &rooster_file),
+        "username=alice\npassword=token2\n"
+    );
+    assert_eq!(
+        git_credential(
+            "get",
+            "protocol=https\nhost=git.example.com\nusername=bob\n\n",
+            &rooster_file
+        ),
+        ""
+    );
+
+    // Passwords don't go out in cleartext unless their URL says so
+    assert_eq!(
+        git_credential(
+            "get",
+            "protocol=http\nhost=git.example.com\n\n",
+            &rooster_file
+        ),
+        ""
+    );
+
+    // Apps can also be found by their URL field
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Work GitLab", "carol"],
+            &mut CursorInputOutput::new("", "xxxx\ntoken3\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "field",
+                "work gitlab",
+                "url",
+                "https://gitlab.example.com/team"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        git_credential(
+            "get",
+            "protocol=https\nhost=gitlab.example.com\npath=team/repo.git\n\n",
+            &rooster_file
+        ),
+        "username=carol\npassword=token3\n"
+    );
+    let http_request = "protocol=http\nhost=gitlab.example.com\n\n";
+    for (url, expected) in [
+        ("gitlab.example.com/team", ""),
+        (
+            "http://gitlab.example.com/team",
+            "username=carol\npassword=token3\n",
+        ),
+    ]
+    .iter()
+    {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "field", "work gitlab", "url", url],
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file
+            )
+        );
+        assert_eq!(
+            git_credential("get", http_request, &rooster_file),
+            *expected
+        );
+    }
+}
@@ -0,0 +1,46 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_init() {
+    // The confirmation must match.
+    let rooster_file = tempfile();
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "init"],
+            &mut CursorInputOutput::new("", "\nPonder-Vivid-Canoe-71\nPonder-Vivid-Canoe-17\n"),
+            &rooster_file
+        )
+    );
+    assert!(!rooster_file.exists());
+
+    // Weak master passwords are refused.
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "init"],
+            &mut CursorInputOutput::new("", "\npassword1\npassword1\n"),
+            &rooster_file
+        )
+    );
+    assert!(!rooster_file.exists());
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init"],
+            &mut CursorInputOutput::new("", "\nPonder-Vivid-Canoe-71\nPonder-Vivid-Canoe-71\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "list"],
+            &mut CursorInputOutput::new("", "Ponder-Vivid-Canoe-71\n"),
+            &rooster_file
+        )
+    );
+}
@@ -0,0 +1,140 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+/// Frames messages like browsers do: their length in native byte order, then the JSON
+fn frame(messages: &[&str]) -> Vec<u8> {
+    let mut framed = Vec::new();
+    for message in messages {
+        framed.extend_from_slice(&(message.len() as u32).to_ne_bytes());
+        framed.extend_from_slice(message.as_bytes());
+    }
+    framed
+}
+
+fn unframe(mut framed: &[u8]) -> Vec<serde_json::Value> {
+    let mut messages = Vec::new();
+    while !framed.is_empty() {
+        let mut len = [0u8; 4];
+        len.copy_from_slice(&framed[..4]);
+        let len = u32::from_ne_bytes(len) as usize;
+        messages.push(serde_json::from_slice(&framed[4..4 + len]).unwrap());
+        framed = &framed[4 + len..];
+    }
+    messages
+}
+
+fn native_host(messages: &[&str], rooster_file: &std::path::PathBuf) -> Vec<serde_json::Value> {
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    io.stdin_cursor = Cursor::new(frame(messages));
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "native-host", "chrome-extension://abcdef/"],
+            &mut io,
+            rooster_file
+        )
+    );
+    unframe(&io.stdout_cursor.into_inner())
+}
+
+#[test]
+fn test_command_native_host() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "example.com", "alice"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    std::env::set_var("ROOSTER_APPROVE_COMMAND", "true");
+    let responses = native_host(
+        &[
+            r#"{"id":1,"action":"lookup","origin":"https://example.com"}"#,
+            r#"{"id":2,"action":"get","origin":"https://example.com","name":"example.com"}"#,
+            r#"{"id":3,"action":"get","origin":"https://evil.example","name":"example.com"}"#,
+            r#"{"id":4,"action":"save","origin":"https://shop.example","username":"bob","password":"efgh"}"#,
+            "not json",
+        ],
+        &rooster_file,
+    );
+    assert_eq!(responses.len(), 5);
+    assert_eq!(responses[0]["id"], 1);
+    assert_eq!(responses[0]["ok"], true);
+    assert_eq!(responses[0]["entries"][0]["username"], "alice");
+    assert!(responses[0]["entries"][0].get("password").is_none());
+    assert_eq!(responses[1]["entry"]["password"], "abcd");
+    assert_eq!(responses[2]["ok"], false);
+    assert_eq!(
+        responses[3]["entry"]["fields"]["url"],
+        "https://shop.example"
+    );
+    assert_eq!(responses[4]["ok"], false);
+
+    // Saved passwords are in the file, and they aren't released without approval
+    std::env::set_var("ROOSTER_APPROVE_COMMAND", "false");
+    let responses = native_host(
+        &[r#"{"action":"get","origin":"https://shop.example","name":"shop.example"}"#],
+        &rooster_file,
+    );
+    assert_eq!(responses[0]["ok"], false);
+    assert_eq!(responses[0]["error"], "the user said no");
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "shop.example", "--print", "password"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"efgh");
+}
+
+#[test]
+fn test_command_native_host_install() {
+    let dir = tempfile::tempdir().unwrap();
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "native-host",
+                "install",
+                "firefox",
+                "--extension-id",
+                "rooster@example.com",
+                "--manifest-dir",
+                dir.path().to_str().unwrap()
+            ],
+            &mut CursorInputOutput::new("", ""),
+            &rooster_file
+        )
+    );
+
+    let manifest: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join("rooster.json")).unwrap())
+            .unwrap();
+    assert_eq!(manifest["name"], "rooster");
+    assert_eq!(manifest["allowed_extensions"][0], "rooster@example.com");
+    let wrapper = std::fs::read_to_string(manifest["path"].as_str().unwrap()).unwrap();
+    assert!(wrapper.starts_with("#!/bin/sh\n"));
+    assert!(wrapper.contains(&format!(
+        "export ROOSTER_FILE='{}'\n",
+        rooster_file.to_str().unwrap()
+    )));
+    assert!(wrapper.ends_with(" native-host \"$@\"\n"));
+}
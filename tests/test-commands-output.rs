@@ -0,0 +1,194 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use serde_json::Value;
+
+fn parse(output: Vec<u8>) -> Value {
+    serde_json::from_slice(output.as_slice()).unwrap()
+}
+
+#[test]
+fn test_output_json() {
+    let rooster_file = tempfile();
+    let mut io = CursorInputOutput::new("", "xxxx\nxxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--output", "json", "init", "--force-for-tests"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output = parse(io.stdout_cursor.into_inner());
+    assert_eq!(output["version"], 1);
+    assert_eq!(output["command"], "init");
+    assert_eq!(output["ok"], true);
+    assert_eq!(
+        output["result"]["path"],
+        rooster_file.to_string_lossy().as_ref()
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\nabcd\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "GitHub", "alice", "--output=json"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output = parse(io.stdout_cursor.into_inner());
+    assert_eq!(output["result"]["entry"]["name"], "GitHub");
+    assert_eq!(output["result"]["entry"]["password"], "abcd");
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "Gitea", "bob"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+
+    // Only JSON is printed
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--output", "json", "get", "-s", "github"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert!(io.stderr_cursor.into_inner().is_empty());
+    let output = parse(io.stdout_cursor.into_inner());
+    assert_eq!(output["command"], "get");
+    assert_eq!(output["result"]["entry"]["username"], "alice");
+    assert_eq!(output["result"]["entry"]["password"], "abcd");
+    assert_eq!(output["warnings"], Value::Array(vec![]));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--output", "json", "list"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output = parse(io.stdout_cursor.into_inner());
+    let entries = output["result"]["entries"].as_array().unwrap();
+    assert_eq!(entries.len(), 2);
+    assert!(entries.iter().all(|entry| entry.get("password").is_none()));
+
+    // Errors go to stderr
+    let mut io = CursorInputOutput::new("", "xxxx\nabcd\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "--output", "json", "add", "github", "alice"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert!(io.stdout_cursor.into_inner().is_empty());
+    let output = parse(io.stderr_cursor.into_inner());
+    assert_eq!(output["ok"], false);
+    assert_eq!(output["exit_code"], 1);
+    assert!(output["error"].as_str().unwrap().starts_with("Woops"));
+
+    // Scripts can't choose between several apps
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "--output", "json", "get", "-s", "git"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output = parse(io.stdout_cursor.into_inner());
+    assert_eq!(output["ok"], false);
+    assert_eq!(output["result"]["candidates"].as_array().unwrap().len(), 2);
+    let output = parse(io.stderr_cursor.into_inner());
+    assert!(output["error"].as_str().unwrap().contains("\"git\""));
+
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        2,
+        main_with_args(
+            &["rooster", "--output", "json", "get", "--nope"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output = parse(io.stderr_cursor.into_inner());
+    assert_eq!(output["command"], Value::Null);
+    assert_eq!(output["exit_code"], 2);
+}
+
+#[test]
+fn test_output_json_of_printed_secrets() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "GitHub", "alice"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    // What would be printed for humans is in the result, and nothing else is printed
+    let mut io = CursorInputOutput::new("", "");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--output", "json", "gen", "--kind", "pin"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert!(io.stderr_cursor.into_inner().is_empty());
+    let output = parse(io.stdout_cursor.into_inner());
+    assert_eq!(output["command"], "gen");
+    let secret = output["result"]["secret"].as_str().unwrap();
+    assert!(!secret.is_empty() && secret.chars().all(|c| c.is_ascii_digit()));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--output", "json", "export", "json"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert!(io.stderr_cursor.into_inner().is_empty());
+    let output = parse(io.stdout_cursor.into_inner());
+    assert_eq!(output["result"]["format"], "json");
+    let exported: Value =
+        serde_json::from_str(output["result"]["content"].as_str().unwrap()).unwrap();
+    assert_eq!(exported["passwords"][0]["name"], "GitHub");
+    assert_eq!(exported["passwords"][0]["password"], "abcd");
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "--output", "json", "get", "github", "--print", "username"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output = parse(io.stdout_cursor.into_inner());
+    assert_eq!(output["result"]["value"], "alice");
+}
@@ -0,0 +1,95 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_run() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "prod-db", "admin"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &[
+                "rooster",
+                "run",
+                "--env",
+                "DB_PASS=prod-db:password",
+                "--env",
+                "DB_USER=prod-db:username",
+                "--",
+                "sh",
+                "-c",
+                "test \"$DB_USER:$DB_PASS\" = admin:abcd",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // The exit code of the command is ours
+    assert_eq!(
+        3,
+        main_with_args(
+            &[
+                "rooster",
+                "run",
+                "-e",
+                "DB_PASS=prod-db:password",
+                "--",
+                "sh",
+                "-c",
+                "exit 3",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "run", "-e", "DB_PASS=prod-db", "--", "true"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "run", "-e", "DB_URL=prod-db:url", "--", "true"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &[
+                "rooster",
+                "run",
+                "-e",
+                "DB_PASS=prod-db:password",
+                "--",
+                "rooster-no-such-command",
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}
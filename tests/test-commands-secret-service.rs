@@ -0,0 +1,221 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::io::{BufRead, BufReader};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Duration;
+
+/// Whether a program can be found, since the test needs a bus of its own
+fn can_run(program: &str) -> bool {
+    Command::new(program)
+        .arg("--help")
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .is_ok()
+}
+
+/// Calls a method with dbus-send, returning what it printed
+fn dbus_send(address: &str, path: &str, method: &str, args: &[&str]) -> String {
+    let output = Command::new("dbus-send")
+        .arg(format!("--bus={}", address))
+        .arg("--print-reply")
+        .arg("--dest=org.freedesktop.secrets")
+        .arg(path)
+        .arg(method)
+        .args(args)
+        .output()
+        .unwrap();
+    String::from_utf8_lossy(&output.stdout).into_owned()
+        + String::from_utf8_lossy(&output.stderr).as_ref()
+}
+
+#[test]
+fn test_command_secret_service() {
+    if !can_run("dbus-daemon") || !can_run("dbus-send") {
+        return;
+    }
+
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "prod-db", "admin"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "field", "prod-db", "secret-service", "yes"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "private", "carol"],
+            &mut CursorInputOutput::new("", "xxxx\nijkl\n"),
+            &rooster_file
+        )
+    );
+
+    // A bus of our own, so that the test doesn't meet the user's keyring
+    let bus_dir = tempfile::tempdir().unwrap();
+    let config = bus_dir.path().join("bus.conf");
+    std::fs::write(
+        &config,
+        format!(
+            "<busconfig>
+              <type>session</type>
+              <listen>unix:path={}</listen>
+              <auth>EXTERNAL</auth>
+              <policy context=\"default\">
+                <allow send_destination=\"*\" eavesdrop=\"true\"/>
+                <allow eavesdrop=\"true\"/>
+                <allow own=\"*\"/>
+              </policy>
+            </busconfig>",
+            bus_dir.path().join("bus").to_string_lossy()
+        ),
+    )
+    .unwrap();
+    let mut bus = Command::new("dbus-daemon")
+        .arg(format!("--config-file={}", config.to_string_lossy()))
+        .arg("--nofork")
+        .arg("--print-address=1")
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+    let mut address = String::new();
+    BufReader::new(bus.stdout.take().unwrap())
+        .read_line(&mut address)
+        .unwrap();
+    let address = address.trim().to_owned();
+
+    let server_address = address.clone();
+    let server_rooster_file = rooster_file.clone();
+    thread::spawn(move || {
+        main_with_args(
+            &[
+                "rooster",
+                "secret-service",
+                "--address",
+                server_address.as_str(),
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &server_rooster_file,
+        )
+    });
+
+    let service = "/org/freedesktop/secrets";
+    let mut session = String::new();
+    for _ in 0..500 {
+        session = dbus_send(
+            &address,
+            service,
+            "org.freedesktop.Secret.Service.OpenSession",
+            &["string:plain", "variant:string:"],
+        );
+        if session.contains("object path") {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert!(session.contains("\"/org/freedesktop/secrets/session/"));
+    let session = session.split('"').nth(3).unwrap().to_owned();
+
+    let items = dbus_send(
+        &address,
+        "/org/freedesktop/secrets/aliases/default",
+        "org.freedesktop.DBus.Properties.Get",
+        &["string:org.freedesktop.Secret.Collection", "string:Items"],
+    );
+    let item = "/org/freedesktop/secrets/collection/rooster/prod_2ddb";
+    assert!(items.contains(item));
+    // Passwords that weren't shared with the Secret Service stay hidden.
+    assert!(!items.contains("private"));
+
+    // The service starts locked, and unlocking asks the user.
+    let secret = dbus_send(
+        &address,
+        item,
+        "org.freedesktop.Secret.Item.GetSecret",
+        &[format!("objpath:{}", session).as_str()],
+    );
+    assert!(secret.contains("org.freedesktop.Secret.Error.IsLocked"));
+    let unlock = dbus_send(
+        &address,
+        service,
+        "org.freedesktop.Secret.Service.Unlock",
+        &["array:objpath:/org/freedesktop/secrets/aliases/default"],
+    );
+    let prompt = unlock.split('"').nth(1).unwrap().to_owned();
+    assert!(prompt.starts_with("/org/freedesktop/secrets/prompt/"));
+    std::env::set_var("ROOSTER_APPROVE_COMMAND", "true");
+    dbus_send(
+        &address,
+        &prompt,
+        "org.freedesktop.Secret.Prompt.Prompt",
+        &["string:"],
+    );
+
+    let secret = dbus_send(
+        &address,
+        item,
+        "org.freedesktop.Secret.Item.GetSecret",
+        &[format!("objpath:{}", session).as_str()],
+    );
+    assert!(secret.contains("abcd"));
+
+    let secret = dbus_send(
+        &address,
+        item,
+        "org.freedesktop.Secret.Item.GetSecret",
+        &["objpath:/org/freedesktop/secrets/session/404"],
+    );
+    assert!(secret.contains("org.freedesktop.Secret.Error.NoSession"));
+
+    // Deleting an item deletes the password from the file, and keeps the passwords saved with
+    // Rooster itself in the meantime.
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "from-cli", "bob"],
+            &mut CursorInputOutput::new("", "xxxx\nefgh\n"),
+            &rooster_file
+        )
+    );
+    dbus_send(&address, item, "org.freedesktop.Secret.Item.Delete", &[]);
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "get", "prod-db"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "from-cli"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    bus.kill().unwrap();
+    bus.wait().unwrap();
+}
@@ -0,0 +1,179 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+use std::io::{BufRead, BufReader, Write};
+use std::os::unix::net::UnixStream;
+use std::thread;
+use std::time::Duration;
+
+fn call(stream: &mut UnixStream, request: &str) -> serde_json::Value {
+    stream.write_all(request.as_bytes()).unwrap();
+    stream.write_all(b"\n").unwrap();
+    let mut response = String::new();
+    BufReader::new(stream.try_clone().unwrap())
+        .read_line(&mut response)
+        .unwrap();
+    serde_json::from_str(&response).unwrap()
+}
+
+#[test]
+fn test_command_serve() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "prod-db", "admin"],
+            &mut CursorInputOutput::new("", "xxxx\nabcd\n"),
+            &rooster_file
+        )
+    );
+
+    let socket_dir = tempfile::tempdir().unwrap();
+    let socket = socket_dir.path().join("rooster.sock");
+    let socket_as_string = socket.to_string_lossy().into_owned();
+    let server_rooster_file = rooster_file.clone();
+    thread::spawn(move || {
+        main_with_args(
+            &["rooster", "serve", "--socket", socket_as_string.as_str()],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &server_rooster_file,
+        )
+    });
+    while !socket.exists() {
+        thread::sleep(Duration::from_millis(10));
+    }
+
+    let mut stream = UnixStream::connect(&socket).unwrap();
+    let response = call(
+        &mut stream,
+        r#"{"jsonrpc":"2.0","id":1,"method":"search","params":{"query":"prod"}}"#,
+    );
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"]["entries"][0]["name"], "prod-db");
+    assert!(response["result"]["entries"][0].get("password").is_none());
+
+    let response = call(
+        &mut stream,
+        r#"{"jsonrpc":"2.0","id":2,"method":"get","params":{"name":"prod-db"}}"#,
+    );
+    assert_eq!(response["result"]["entry"]["password"], "abcd");
+
+    let response = call(
+        &mut stream,
+        r#"{"jsonrpc":"2.0","id":3,"method":"generate","params":{"name":"staging-db","username":"admin","length":20}}"#,
+    );
+    let generated = response["result"]["entry"]["password"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    assert_eq!(generated.len(), 20);
+    for length in ["3", "1000000000000"].iter() {
+        let response = call(
+            &mut stream,
+            &format!(
+                r#"{{"jsonrpc":"2.0","id":3,"method":"generate","params":{{"name":"huge-db","username":"admin","length":{}}}}}"#,
+                length
+            ),
+        );
+        assert_eq!(response["error"]["code"], -32602);
+    }
+
+    let response = call(
+        &mut stream,
+        r#"{"jsonrpc":"2.0","id":4,"method":"change","params":{"name":"prod-db","password":"efgh"}}"#,
+    );
+    assert_eq!(response["result"]["entry"]["username"], "admin");
+
+    let response = call(
+        &mut stream,
+        r#"{"jsonrpc":"2.0","id":5,"method":"add","params":{"name":"prod-db","username":"root","password":"ijkl"}}"#,
+    );
+    assert_eq!(response["error"]["code"], 2);
+
+    let response = call(
+        &mut stream,
+        r#"{"jsonrpc":"2.0","id":6,"method":"delete","params":{"name":"nothing"}}"#,
+    );
+    assert_eq!(response["error"]["code"], 1);
+
+    let response = call(&mut stream, r#"{"jsonrpc":"2.0","id":7,"method":"nope"}"#);
+    assert_eq!(response["error"]["code"], -32601);
+
+    let response = call(&mut stream, "not json");
+    assert_eq!(response["error"]["code"], -32700);
+
+    // Passwords saved with Rooster itself in the meantime are kept.
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "add", "-s", "from-cli", "bob"],
+            &mut CursorInputOutput::new("", "xxxx\nmnop\n"),
+            &rooster_file
+        )
+    );
+    let response = call(
+        &mut stream,
+        r#"{"jsonrpc":"2.0","id":8,"method":"get","params":{"name":"from-cli"}}"#,
+    );
+    assert_eq!(response["result"]["entry"]["password"], "mnop");
+    let response = call(
+        &mut stream,
+        r#"{"jsonrpc":"2.0","id":9,"method":"add","params":{"name":"after-cli","username":"carol","password":"qrst"}}"#,
+    );
+    assert_eq!(response["result"]["entry"]["name"], "after-cli");
+
+    // A client that keeps quiet doesn't keep the others waiting.
+    let _quiet = UnixStream::connect(&socket).unwrap();
+    let mut other = UnixStream::connect(&socket).unwrap();
+    other
+        .set_read_timeout(Some(Duration::from_secs(5)))
+        .unwrap();
+    let response = call(
+        &mut other,
+        r#"{"jsonrpc":"2.0","id":10,"method":"search","params":{"query":"from"}}"#,
+    );
+    assert_eq!(response["result"]["entries"][0]["name"], "from-cli");
+    drop(stream);
+
+    // Changes are saved right away
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "prod-db", "--print", "password"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), b"efgh");
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "get", "staging-db", "--print", "password"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    assert_eq!(io.stdout_cursor.into_inner(), generated.as_bytes());
+
+    for name in &["from-cli", "after-cli"] {
+        assert_eq!(
+            0,
+            main_with_args(
+                &["rooster", "get", name, "--print", "password"],
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file
+            )
+        );
+    }
+}
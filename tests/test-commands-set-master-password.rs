@@ -9,13 +9,14 @@ fn test_command_set_master_password() {
         0,
         main_with_args(
             &["rooster", "init", "--force-for-tests"],
-            &mut CursorInputOutput::new("", "\nxxxx\n"),
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
             &rooster_file
         )
     );
 
+    // Weak master passwords are refused.
     assert_eq!(
-        0,
+        1,
         main_with_args(
             &["rooster", "set-master-password"],
             &mut CursorInputOutput::new("", "xxxx\nabcd\nabcd\n"),
@@ -23,6 +24,15 @@ fn test_command_set_master_password() {
         )
     );
 
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "set-master-password"],
+            &mut CursorInputOutput::new("", "xxxx\nPonder-Vivid-Canoe-71\nPonder-Vivid-Canoe-71\n"),
+            &rooster_file
+        )
+    );
+
     assert_eq!(
         1,
         main_with_args(
@@ -35,7 +45,7 @@ fn test_command_set_master_password() {
         0,
         main_with_args(
             &["rooster", "list"],
-            &mut CursorInputOutput::new("", "abcd\n"),
+            &mut CursorInputOutput::new("", "Ponder-Vivid-Canoe-71\n"),
             &rooster_file
         )
     );
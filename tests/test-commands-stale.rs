@@ -0,0 +1,185 @@
+mod helpers;
+
+use crate::helpers::prelude::*;
+
+#[test]
+fn test_command_stale() {
+    let rooster_file = tempfile();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "init", "--force-for-tests"],
+            &mut CursorInputOutput::new("", "\nxxxx\nxxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let now = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let import_file = tempfile();
+    std::fs::write(
+        &import_file,
+        format!(
+            "{{\"passwords\":[\
+             {{\"name\":\"Bank\",\"username\":\"alice\",\"password\":\"abcd\",\"created_at\":{now},\"updated_at\":{now}}},\
+             {{\"name\":\"Forum\",\"username\":\"bob\",\"password\":\"efgh\",\"created_at\":1605554169,\"updated_at\":1605554169}}\
+             ]}}",
+            now = now
+        ),
+    )
+    .unwrap();
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "import", "json", import_file.to_str().unwrap()],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    // Nothing expires by default
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "stale"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "expiry", "forum", "--every", "90"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "expiry", "bank", "--every", "30"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    // Passwords can't need changing every 0 days
+    for days in ["0", "99999999999"].iter() {
+        assert_eq!(
+            2,
+            main_with_args(
+                &["rooster", "--output", "json", "expiry", "bank", "--every", days],
+                &mut CursorInputOutput::new("", "xxxx\n"),
+                &rooster_file
+            )
+        );
+    }
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "expiry", "bank", "--on", "31/01/2030"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(&["rooster", "stale"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Forum (bob): due"));
+    assert!(output_as_string.contains("changed every 90 days"));
+    assert!(!output_as_string.contains("Bank"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "stale", "--within", "60"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Bank (alice): due in 30 days"));
+
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        0,
+        main_with_args(&["rooster", "get", "-s", "forum"], &mut io, &rooster_file)
+    );
+    let output_as_vecu8 = io.stderr_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("This password needed to be changed"));
+
+    // Changing the password resets the rotation
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "change", "-s", "forum"],
+            &mut CursorInputOutput::new("", "xxxx\nijkl\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "stale"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+
+    assert_eq!(
+        0,
+        main_with_args(
+            &["rooster", "expiry", "forum", "--never"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    let mut io = CursorInputOutput::new("", "xxxx\n");
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "stale", "--within", "100"],
+            &mut io,
+            &rooster_file
+        )
+    );
+    let output_as_vecu8 = io.stdout_cursor.into_inner();
+    let output_as_string = String::from_utf8_lossy(output_as_vecu8.as_slice());
+    assert!(output_as_string.contains("Bank"));
+    assert!(!output_as_string.contains("Forum"));
+
+    // The window is bounded, and the largest one doesn't overflow
+    assert_eq!(
+        2,
+        main_with_args(
+            &[
+                "rooster",
+                "--output",
+                "json",
+                "stale",
+                "--within",
+                "999999999999999"
+            ],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+    assert_eq!(
+        1,
+        main_with_args(
+            &["rooster", "stale", "--within", "4294967295"],
+            &mut CursorInputOutput::new("", "xxxx\n"),
+            &rooster_file
+        )
+    );
+}